@@ -90,6 +90,7 @@ abstract class Isar {
     String? directory,
     String name = defaultName,
     int maxSizeMiB = Isar.defaultMaxSizeMiB,
+    int? maxReaders,
     bool relaxedDurability = true,
     CompactCondition? compactOnLaunch,
     bool inspector = true,
@@ -109,6 +110,7 @@ abstract class Isar {
       directory: directory,
       name: name,
       maxSizeMiB: maxSizeMiB,
+      maxReaders: maxReaders,
       relaxedDurability: relaxedDurability,
       compactOnLaunch: compactOnLaunch,
     );
@@ -120,6 +122,7 @@ abstract class Isar {
     String? directory,
     String name = defaultName,
     int maxSizeMiB = Isar.defaultMaxSizeMiB,
+    int? maxReaders,
     bool relaxedDurability = true,
     CompactCondition? compactOnLaunch,
     bool inspector = true,
@@ -139,6 +142,7 @@ abstract class Isar {
       directory: directory,
       name: name,
       maxSizeMiB: maxSizeMiB,
+      maxReaders: maxReaders,
       relaxedDurability: relaxedDurability,
       compactOnLaunch: compactOnLaunch,
     );
@@ -329,6 +333,7 @@ class CompactCondition {
     this.minFileSize,
     this.minBytes,
     this.minRatio,
+    this.tempDirectory,
   }) : assert(
           minFileSize != null || minBytes != null || minRatio != null,
           'At least one condition needs to be specified.',
@@ -344,4 +349,8 @@ class CompactCondition {
   /// The minimum compaction ration. For example `2.0` would trigger compaction
   /// as soon as the file size can be halved.
   final double? minRatio;
+
+  /// The directory where the compacted copy is written before it replaces the
+  /// database. Defaults to the directory of the database.
+  final String? tempDirectory;
 }
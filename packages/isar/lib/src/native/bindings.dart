@@ -1286,10 +1286,13 @@ class IsarCoreBindings {
     ffi.Pointer<ffi.Char> path,
     ffi.Pointer<ffi.Char> schema_json,
     int max_size_mib,
+    int max_readers,
     bool relaxed_durability,
     int compact_min_file_size,
     int compact_min_bytes,
     double compact_min_ratio,
+    ffi.Pointer<ffi.Char> compact_temp_dir,
+    int migration_port,
   ) {
     return _isar_instance_create(
       isar,
@@ -1297,10 +1300,13 @@ class IsarCoreBindings {
       path,
       schema_json,
       max_size_mib,
+      max_readers,
       relaxed_durability,
       compact_min_file_size,
       compact_min_bytes,
       compact_min_ratio,
+      compact_temp_dir,
+      migration_port,
     );
   }
 
@@ -1312,10 +1318,13 @@ class IsarCoreBindings {
               ffi.Pointer<ffi.Char>,
               ffi.Pointer<ffi.Char>,
               ffi.Int64,
+              ffi.Uint32,
               ffi.Bool,
               ffi.Uint32,
               ffi.Uint32,
-              ffi.Double)>>('isar_instance_create');
+              ffi.Double,
+              ffi.Pointer<ffi.Char>,
+              DartPort)>>('isar_instance_create');
   late final _isar_instance_create = _isar_instance_createPtr.asFunction<
       int Function(
           ffi.Pointer<ffi.Pointer<CIsarInstance>>,
@@ -1323,10 +1332,13 @@ class IsarCoreBindings {
           ffi.Pointer<ffi.Char>,
           ffi.Pointer<ffi.Char>,
           int,
+          int,
           bool,
           int,
           int,
-          double)>();
+          double,
+          ffi.Pointer<ffi.Char>,
+          int)>();
 
   void isar_instance_create_async(
     ffi.Pointer<ffi.Pointer<CIsarInstance>> isar,
@@ -1334,10 +1346,13 @@ class IsarCoreBindings {
     ffi.Pointer<ffi.Char> path,
     ffi.Pointer<ffi.Char> schema_json,
     int max_size_mib,
+    int max_readers,
     bool relaxed_durability,
     int compact_min_file_size,
     int compact_min_bytes,
     double compact_min_ratio,
+    ffi.Pointer<ffi.Char> compact_temp_dir,
+    int migration_port,
     int port,
   ) {
     return _isar_instance_create_async(
@@ -1346,10 +1361,13 @@ class IsarCoreBindings {
       path,
       schema_json,
       max_size_mib,
+      max_readers,
       relaxed_durability,
       compact_min_file_size,
       compact_min_bytes,
       compact_min_ratio,
+      compact_temp_dir,
+      migration_port,
       port,
     );
   }
@@ -1362,10 +1380,13 @@ class IsarCoreBindings {
               ffi.Pointer<ffi.Char>,
               ffi.Pointer<ffi.Char>,
               ffi.Int64,
+              ffi.Uint32,
               ffi.Bool,
               ffi.Uint32,
               ffi.Uint32,
               ffi.Double,
+              ffi.Pointer<ffi.Char>,
+              DartPort,
               DartPort)>>('isar_instance_create_async');
   late final _isar_instance_create_async =
       _isar_instance_create_asyncPtr.asFunction<
@@ -1375,10 +1396,13 @@ class IsarCoreBindings {
               ffi.Pointer<ffi.Char>,
               ffi.Pointer<ffi.Char>,
               int,
+              int,
               bool,
               int,
               int,
               double,
+              ffi.Pointer<ffi.Char>,
+              int,
               int)>();
 
   bool isar_instance_close(
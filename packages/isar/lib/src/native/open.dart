@@ -75,6 +75,7 @@ Future<Isar> openIsar({
   String? directory,
   required String name,
   required int maxSizeMiB,
+  int? maxReaders,
   required bool relaxedDurability,
   CompactCondition? compactOnLaunch,
 }) async {
@@ -92,6 +93,8 @@ Future<Isar> openIsar({
     final compactMinBytes = compactOnLaunch?.minBytes;
     final compactMinRatio =
         compactOnLaunch == null ? double.nan : compactOnLaunch.minRatio;
+    final compactTempDirPtr =
+        compactOnLaunch?.tempDirectory?.toCString(alloc) ?? nullptr;
 
     final receivePort = ReceivePort();
     final nativePort = receivePort.sendPort.nativePort;
@@ -102,10 +105,13 @@ Future<Isar> openIsar({
       dirPtr,
       schemaStrPtr,
       maxSizeMiB,
+      maxReaders ?? 0,
       relaxedDurability,
       compactMinFileSize ?? 0,
       compactMinBytes ?? 0,
       compactMinRatio ?? 0,
+      compactTempDirPtr,
+      0,
       nativePort,
     );
     await stream.first;
@@ -121,6 +127,7 @@ Isar openIsarSync({
   String? directory,
   required String name,
   required int maxSizeMiB,
+  int? maxReaders,
   required bool relaxedDurability,
   CompactCondition? compactOnLaunch,
 }) {
@@ -137,6 +144,8 @@ Isar openIsarSync({
     final compactMinBytes = compactOnLaunch?.minBytes;
     final compactMinRatio =
         compactOnLaunch == null ? double.nan : compactOnLaunch.minRatio;
+    final compactTempDirPtr =
+        compactOnLaunch?.tempDirectory?.toCString(alloc) ?? nullptr;
 
     nCall(
       IC.isar_instance_create(
@@ -145,10 +154,13 @@ Isar openIsarSync({
         dirPtr,
         schemaStrPtr,
         maxSizeMiB,
+        maxReaders ?? 0,
         relaxedDurability,
         compactMinFileSize ?? 0,
         compactMinBytes ?? 0,
         compactMinRatio ?? 0,
+        compactTempDirPtr,
+        0,
       ),
     );
 
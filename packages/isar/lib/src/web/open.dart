@@ -43,6 +43,7 @@ Future<Isar> openIsar({
   String? directory,
   required String name,
   required int maxSizeMiB,
+  int? maxReaders,
   required bool relaxedDurability,
   CompactCondition? compactOnLaunch,
 }) async {
@@ -76,6 +77,7 @@ Isar openIsarSync({
   String? directory,
   required String name,
   required int maxSizeMiB,
+  int? maxReaders,
   required bool relaxedDurability,
   CompactCondition? compactOnLaunch,
 }) =>
@@ -0,0 +1,196 @@
+use crate::collection::IsarCollection;
+use crate::error::{illegal_arg, IsarError, Result};
+use crate::instance::IsarInstance;
+use crate::link::LinkPairs;
+use crate::object::isar_object::IsarObject;
+use crate::object::row_format::{decode_rows, encode_row, RowIter, ROW_FORMAT_VERSION};
+use crate::txn::IsarTxn;
+use byteorder::{ByteOrder, LittleEndian};
+use std::fs;
+
+/*
+Archive files start with a header followed by the rows and the links of the archived objects:
+
+[u8; 8] magic
+u32 row_format_version
+u64 collection_hash
+u32 name_length
+... name ...
+u64 rows_length
+... rows ...
+u32 link_count
+--- per link ---
+u64 link_id
+u32 pair_count
+(i64 id, i64 other_id) pairs
+*/
+
+const MAGIC: &[u8; 8] = b"ISARARCH";
+const HEADER_SIZE: usize = 24;
+
+/// Objects of a collection that were moved to an archive file.
+pub struct Archive {
+    pub collection: String,
+    pub collection_hash: u64,
+    bytes: Vec<u8>,
+    rows_offset: usize,
+    rows_end: usize,
+    links: Vec<LinkPairs>,
+}
+
+impl Archive {
+    pub fn open(path: &str) -> Result<Archive> {
+        let bytes = fs::read(path).map_err(|_| IsarError::PathError {})?;
+        if bytes.len() < HEADER_SIZE || &bytes[..8] != MAGIC {
+            return illegal_arg("The file is not an Isar archive.");
+        }
+        if LittleEndian::read_u32(&bytes[8..]) != ROW_FORMAT_VERSION {
+            return illegal_arg("The archive uses an unsupported row format.");
+        }
+        let collection_hash = LittleEndian::read_u64(&bytes[12..]);
+        let name_length = LittleEndian::read_u32(&bytes[20..]) as usize;
+        let mut reader = Reader {
+            bytes: &bytes,
+            offset: HEADER_SIZE,
+        };
+        let name = std::str::from_utf8(reader.take(name_length)?)
+            .map_err(|_| IsarError::InvalidObject {})?
+            .to_string();
+        let rows_length = LittleEndian::read_u64(reader.take(8)?) as usize;
+        let rows_offset = reader.offset;
+        reader.take(rows_length)?;
+        let rows_end = reader.offset;
+
+        let mut links = vec![];
+        for _ in 0..LittleEndian::read_u32(reader.take(4)?) {
+            let link_id = LittleEndian::read_u64(reader.take(8)?);
+            let pair_count = LittleEndian::read_u32(reader.take(4)?) as usize;
+            let pairs = reader.take(pair_count.saturating_mul(16))?;
+            let pairs = pairs
+                .chunks_exact(16)
+                .map(|pair| {
+                    let id = LittleEndian::read_i64(pair);
+                    (id, LittleEndian::read_i64(&pair[8..]))
+                })
+                .collect();
+            links.push((link_id, pairs));
+        }
+        if reader.offset != bytes.len() {
+            return Err(IsarError::InvalidObject {});
+        }
+
+        Ok(Archive {
+            collection: name,
+            collection_hash,
+            bytes,
+            rows_offset,
+            rows_end,
+            links,
+        })
+    }
+
+    /// Browses the archived objects without attaching them.
+    pub fn objects(&self) -> impl Iterator<Item = Result<(i64, IsarObject<'_>)>> {
+        self.rows()
+            .map(|row| row.map(|(id, bytes)| (id, IsarObject::from_bytes(bytes))))
+    }
+
+    fn rows(&self) -> RowIter<'_> {
+        decode_rows(&self.bytes[self.rows_offset..self.rows_end])
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.offset.checked_add(length);
+        let bytes = end.and_then(|end| self.bytes.get(self.offset..end));
+        let bytes = bytes.ok_or(IsarError::InvalidObject {})?;
+        self.offset += length;
+        Ok(bytes)
+    }
+}
+
+fn check_collection(instance: &IsarInstance, collection: &IsarCollection) -> Result<u64> {
+    let hash = instance.collection_hash(&collection.name);
+    match hash {
+        Some(hash) if collection.instance_id == instance.instance_id => Ok(hash),
+        _ => illegal_arg("The collection does not belong to the instance."),
+    }
+}
+
+/// Writes all objects of a collection and their links to an archive file and removes them from
+/// the database.
+pub fn detach_to_archive(
+    instance: &IsarInstance,
+    collection: &IsarCollection,
+    path: &str,
+) -> Result<usize> {
+    let collection_hash = check_collection(instance, collection)?;
+    instance.run_txn(true, false, |txn| {
+        let (bytes, count) = encode_archive(txn, collection, collection_hash)?;
+        collection.clear(txn)?;
+        fs::write(path, bytes).map_err(|_| IsarError::PathError {})?;
+        Ok(count)
+    })
+}
+
+fn encode_archive(
+    txn: &mut IsarTxn,
+    collection: &IsarCollection,
+    collection_hash: u64,
+) -> Result<(Vec<u8>, usize)> {
+    let mut rows = vec![];
+    let mut ids = vec![];
+    collection
+        .new_query_builder()
+        .build()
+        .scan_while(txn, |id, object| {
+            encode_row(&mut rows, id, object.as_bytes());
+            ids.push(id);
+            true
+        })?;
+    let links = collection.get_links(txn, &ids)?;
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + collection.name.len() + 8 + rows.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&ROW_FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&collection_hash.to_le_bytes());
+    bytes.extend_from_slice(&(collection.name.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(collection.name.as_bytes());
+    bytes.extend_from_slice(&(rows.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&rows);
+    bytes.extend_from_slice(&(links.len() as u32).to_le_bytes());
+    for (link_id, pairs) in &links {
+        bytes.extend_from_slice(&link_id.to_le_bytes());
+        bytes.extend_from_slice(&(pairs.len() as u32).to_le_bytes());
+        for (id, other_id) in pairs {
+            bytes.extend_from_slice(&id.to_le_bytes());
+            bytes.extend_from_slice(&other_id.to_le_bytes());
+        }
+    }
+    Ok((bytes, ids.len()))
+}
+
+/// Puts the unchanged objects of an archive and their links back into the collection it was
+/// detached from. The schema of the collection must not have changed since.
+pub fn attach_archive(
+    instance: &IsarInstance,
+    collection: &IsarCollection,
+    archive: &Archive,
+) -> Result<usize> {
+    let collection_hash = check_collection(instance, collection)?;
+    if archive.collection != collection.name || archive.collection_hash != collection_hash {
+        return illegal_arg("The archive does not match the collection.");
+    }
+
+    let objects = archive.objects().collect::<Result<Vec<_>>>()?;
+    instance.run_txn(true, false, |txn| {
+        collection.restore(txn, &objects, &archive.links)
+    })?;
+    Ok(objects.len())
+}
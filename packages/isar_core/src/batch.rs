@@ -0,0 +1,264 @@
+use crate::collection::IsarCollection;
+use crate::error::{illegal_arg, IsarError, Result};
+use crate::instance::IsarInstance;
+use crate::mdbx::is_transient;
+use crate::object::isar_object::IsarObject;
+use crate::query::Query;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+enum BatchOp {
+    Put {
+        col_id: u64,
+        id: Option<i64>,
+        bytes: Vec<u8>,
+    },
+    Delete {
+        col_id: u64,
+        id: i64,
+    },
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct AutoFlush {
+    pub max_ops: Option<usize>,
+    pub max_bytes: Option<usize>,
+    pub max_age: Option<Duration>,
+}
+
+pub struct WriteBatch {
+    instance: Arc<IsarInstance>,
+    auto_flush: AutoFlush,
+    ops: Vec<BatchOp>,
+    bytes: usize,
+    first_op: Option<Instant>,
+}
+
+impl WriteBatch {
+    pub fn new(instance: Arc<IsarInstance>, auto_flush: AutoFlush) -> Self {
+        WriteBatch {
+            instance,
+            auto_flush,
+            ops: vec![],
+            bytes: 0,
+            first_op: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn put(
+        &mut self,
+        collection: &IsarCollection,
+        id: Option<i64>,
+        object: IsarObject,
+    ) -> Result<()> {
+        self.verify_collection(collection)?;
+        if object.len() > IsarObject::MAX_SIZE as usize {
+            return Err(IsarError::IllegalArg {
+                message: "Object is bigger than 16MB".to_string(),
+            });
+        }
+        let bytes = object.as_bytes().to_vec();
+        self.bytes += bytes.len();
+        self.push(BatchOp::Put {
+            col_id: collection.id,
+            id,
+            bytes,
+        })
+    }
+
+    pub fn delete(&mut self, collection: &IsarCollection, id: i64) -> Result<()> {
+        self.verify_collection(collection)?;
+        self.push(BatchOp::Delete {
+            col_id: collection.id,
+            id,
+        })
+    }
+
+    fn verify_collection(&self, collection: &IsarCollection) -> Result<()> {
+        if collection.instance_id != self.instance.instance_id {
+            Err(IsarError::InstanceMismatch {})
+        } else {
+            Ok(())
+        }
+    }
+
+    fn push(&mut self, op: BatchOp) -> Result<()> {
+        self.ops.push(op);
+        let first_op = *self.first_op.get_or_insert_with(Instant::now);
+
+        let auto_flush = self.auto_flush;
+        let flush = auto_flush.max_ops.is_some_and(|max| self.ops.len() >= max)
+            || auto_flush.max_bytes.is_some_and(|max| self.bytes >= max)
+            || auto_flush
+                .max_age
+                .is_some_and(|max| first_op.elapsed() >= max);
+        if flush {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn get_collection(&self, col_id: u64) -> Result<&IsarCollection> {
+        self.instance
+            .collections
+            .iter()
+            .find(|c| c.id == col_id)
+            .ok_or(IsarError::IllegalArg {
+                message: "Collection does not exist.".to_string(),
+            })
+    }
+
+    /// Applies all pending operations in a single write transaction. If the transaction fails
+    /// with a transient error, the operations stay pending and are retried by the next flush.
+    /// Otherwise they are discarded and the error is returned.
+    pub fn flush(&mut self) -> Result<usize> {
+        if self.ops.is_empty() {
+            return Ok(0);
+        }
+        if let Err(err) = self.apply() {
+            if !Self::is_transient(&err) {
+                self.discard();
+            }
+            return Err(err);
+        }
+        Ok(self.discard())
+    }
+
+    /// Drops all pending operations without applying them and returns their number.
+    pub fn discard(&mut self) -> usize {
+        let len = self.ops.len();
+        self.ops.clear();
+        self.bytes = 0;
+        self.first_op = None;
+        len
+    }
+
+    fn is_transient(err: &IsarError) -> bool {
+        match err {
+            IsarError::ReadOnly {} => true,
+            IsarError::MdbxError { code, .. } => is_transient(*code),
+            _ => false,
+        }
+    }
+
+    fn apply(&self) -> Result<()> {
+        let mut txn = self.instance.begin_txn(true, false)?;
+        for op in &self.ops {
+            match op {
+                BatchOp::Put { col_id, id, bytes } => {
+                    let col = self.get_collection(*col_id)?;
+                    col.put(&mut txn, *id, IsarObject::from_bytes(bytes))?;
+                }
+                BatchOp::Delete { col_id, id } => {
+                    let col = self.get_collection(*col_id)?;
+                    col.delete(&mut txn, *id)?;
+                }
+            }
+        }
+        txn.commit()
+    }
+}
+
+/// Pending operations are not applied when the batch is dropped. Call [WriteBatch::flush] or
+/// [WriteBatch::discard] first.
+impl Drop for WriteBatch {
+    fn drop(&mut self) {
+        debug_assert!(
+            self.is_empty() || std::thread::panicking(),
+            "WriteBatch dropped with pending operations."
+        );
+    }
+}
+
+/// Deletes the results of `query` in write transactions of at most `chunk_size` objects so
+/// other writers can run in between. `progress` is called with the number of deleted objects
+/// after every commit. Returns the total number of deleted objects.
+pub fn delete_chunked<F>(
+    instance: &IsarInstance,
+    collection: &IsarCollection,
+    query: &Query,
+    chunk_size: usize,
+    mut progress: F,
+) -> Result<usize>
+where
+    F: FnMut(usize),
+{
+    if chunk_size == 0 {
+        return illegal_arg("Chunk size must be greater than zero.");
+    }
+    let mut deleted = 0;
+    loop {
+        let remaining = query.limit().saturating_sub(deleted).min(chunk_size);
+        if remaining == 0 {
+            break;
+        }
+
+        let mut txn = instance.begin_txn(true, false)?;
+        let mut ids = Vec::with_capacity(remaining);
+        query.find_ids_while(&mut txn, |id| {
+            ids.push(id);
+            ids.len() < remaining
+        })?;
+        for id in &ids {
+            collection.delete(&mut txn, *id)?;
+        }
+        txn.commit()?;
+
+        deleted += ids.len();
+        progress(deleted);
+        if ids.len() < remaining {
+            break;
+        }
+    }
+    Ok(deleted)
+}
+
+/// Imports a JSON array in write transactions of at most `chunk_size` objects. Objects of
+/// committed chunks stay imported if a later chunk fails. `progress` is called with the number
+/// of imported objects after every commit.
+pub fn import_json_chunked<F>(
+    instance: &IsarInstance,
+    collection: &IsarCollection,
+    id_name: Option<&str>,
+    json: Value,
+    chunk_size: usize,
+    mut progress: F,
+) -> Result<usize>
+where
+    F: FnMut(usize),
+{
+    if chunk_size == 0 {
+        return illegal_arg("Chunk size must be greater than zero.");
+    }
+    let array = if let Value::Array(array) = json {
+        array
+    } else {
+        return Err(IsarError::InvalidJson {});
+    };
+
+    let mut imported = 0;
+    let mut objects = array.into_iter();
+    loop {
+        let chunk: Vec<Value> = objects.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        let len = chunk.len();
+        let mut txn = instance.begin_txn(true, false)?;
+        collection.import_json(&mut txn, id_name, Value::Array(chunk))?;
+        txn.commit()?;
+
+        imported += len;
+        progress(imported);
+    }
+    Ok(imported)
+}
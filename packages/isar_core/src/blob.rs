@@ -0,0 +1,303 @@
+use crate::cursor::IsarCursors;
+use crate::error::{illegal_arg, IsarError, Result};
+use crate::mdbx::cursor::Cursor;
+use crate::mdbx::db::Db;
+use crate::mdbx::Key;
+use crate::object::isar_object::IsarObject;
+use crate::object::property::Property;
+use crate::query::DeadlineCheck;
+use crate::txn::IsarTxn;
+use byteorder::{ByteOrder, LittleEndian};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use xxhash_rust::xxh3::xxh3_128;
+
+pub(crate) struct BlobKey([u8; 16]);
+
+impl BlobKey {
+    fn new(hash: u128) -> Self {
+        BlobKey(hash.to_be_bytes())
+    }
+
+    fn to_hash(&self) -> u128 {
+        u128::from_be_bytes(self.0)
+    }
+}
+
+impl Key for BlobKey {
+    fn as_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.0)
+    }
+
+    fn cmp_bytes(&self, other: &[u8]) -> Ordering {
+        self.0[..].cmp(other)
+    }
+}
+
+const REF_COUNT_SIZE: usize = 4;
+
+/// Collected blobs that other blobs probed past are replaced by an empty value so lookups
+/// keep following the chain.
+fn is_tombstone(value: &[u8]) -> bool {
+    value.is_empty()
+}
+
+fn move_to_blob<'txn>(cursor: &mut Cursor<'txn>, key: &BlobKey) -> Result<Option<&'txn [u8]>> {
+    let value = cursor.move_to(key)?.map(|(_, value)| value);
+    Ok(value.filter(|value| !is_tombstone(value)))
+}
+
+fn update_ref_count(cursor: &mut Cursor, key: &BlobKey, add: bool) -> Result<()> {
+    if let Some(existing) = move_to_blob(cursor, key)? {
+        let ref_count = LittleEndian::read_u32(existing);
+        let ref_count = if add {
+            ref_count.saturating_add(1)
+        } else {
+            ref_count.saturating_sub(1)
+        };
+        let mut value = existing.to_vec();
+        LittleEndian::write_u32(&mut value, ref_count);
+        cursor.put(key, &value)?;
+    }
+    Ok(())
+}
+
+/// Content addressed storage for large binary data. Each blob is stored once per content
+/// hash together with the number of objects referencing it.
+pub struct BlobStore {
+    instance_id: u64,
+    db: Db,
+}
+
+impl BlobStore {
+    pub(crate) fn new(instance_id: u64, db: Db) -> Self {
+        BlobStore { instance_id, db }
+    }
+
+    pub fn hash(bytes: &[u8]) -> u128 {
+        xxh3_128(bytes)
+    }
+
+    /// Stores the blob if it does not exist yet and returns its key. The key is the content
+    /// hash unless a different blob with the same hash is stored already. Objects reference
+    /// the blob by storing the key in a blob property. Unreferenced blobs are removed by
+    /// [BlobStore::collect_garbage].
+    pub fn put(&self, txn: &mut IsarTxn, bytes: &[u8]) -> Result<u128> {
+        txn.write(self.instance_id, |cursors, _| {
+            let mut cursor = cursors.get_cursor(self.db)?;
+            let mut hash = Self::hash(bytes);
+            let mut free = None;
+            loop {
+                let key = BlobKey::new(hash);
+                match cursor.move_to(&key)? {
+                    Some((_, existing)) if is_tombstone(existing) => {
+                        free.get_or_insert(hash);
+                    }
+                    Some((_, existing)) if &existing[REF_COUNT_SIZE..] == bytes => {
+                        return Ok(hash);
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+                hash = hash.wrapping_add(1);
+            }
+            let hash = free.unwrap_or(hash);
+            let mut value = vec![0; REF_COUNT_SIZE];
+            value.extend_from_slice(bytes);
+            cursor.put(&BlobKey::new(hash), &value)?;
+            Ok(hash)
+        })
+    }
+
+    pub fn get<'txn>(&self, txn: &'txn mut IsarTxn, hash: u128) -> Result<Option<&'txn [u8]>> {
+        let key = BlobKey::new(hash);
+        txn.read(self.instance_id, |cursors| {
+            let mut cursor = cursors.get_cursor(self.db)?;
+            let blob = move_to_blob(&mut cursor, &key)?.map(|value| &value[REF_COUNT_SIZE..]);
+            Ok(blob)
+        })
+    }
+
+    pub fn ref_count(&self, txn: &mut IsarTxn, hash: u128) -> Result<u32> {
+        let key = BlobKey::new(hash);
+        txn.read(self.instance_id, |cursors| {
+            let mut cursor = cursors.get_cursor(self.db)?;
+            let ref_count = move_to_blob(&mut cursor, &key)?.map_or(0, LittleEndian::read_u32);
+            Ok(ref_count)
+        })
+    }
+
+    /// Deletes all blobs without references and returns their count.
+    pub fn collect_garbage(&self, txn: &mut IsarTxn) -> Result<usize> {
+        self.collect_garbage_until(txn, &mut DeadlineCheck::new(None))
+    }
+
+    /// Stops scanning for unreferenced blobs once `deadline` expired.
+    pub(crate) fn collect_garbage_until(
+        &self,
+        txn: &mut IsarTxn,
+        deadline: &mut DeadlineCheck,
+    ) -> Result<usize> {
+        txn.write(self.instance_id, |cursors, _| {
+            let mut cursor = cursors.get_cursor(self.db)?;
+            let mut unreferenced = vec![];
+            cursor.iter_all(false, true, |_, key, value| {
+                if is_tombstone(value) || LittleEndian::read_u32(value) == 0 {
+                    unreferenced.push((BlobKey(key.try_into().unwrap()), is_tombstone(value)));
+                }
+                Ok(!deadline.expired())
+            })?;
+            // Walk backwards so the end of a probe chain is removed before the slots before it.
+            let mut collected = 0;
+            for (key, tombstone) in unreferenced.iter().rev() {
+                let next = BlobKey::new(key.to_hash().wrapping_add(1));
+                if cursor.move_to(&next)?.is_some() {
+                    if !tombstone {
+                        cursor.put(key, &[])?;
+                    }
+                } else if cursor.move_to(key)?.is_some() {
+                    cursor.delete_current()?;
+                }
+                if !tombstone {
+                    collected += 1;
+                }
+            }
+            Ok(collected)
+        })
+    }
+
+    pub fn get_size(&self, txn: &mut IsarTxn) -> Result<u64> {
+        txn.read(self.instance_id, |cursors| Ok(cursors.db_stat(self.db)?.1))
+    }
+}
+
+/// Keeps the reference counts of blobs in sync with the blob properties of a collection.
+pub(crate) struct BlobRefs {
+    db: Db,
+    properties: Vec<Property>,
+}
+
+impl BlobRefs {
+    pub fn new(db: Db, properties: Vec<Property>) -> Self {
+        BlobRefs { db, properties }
+    }
+
+    fn read_key(property: &Property, object: IsarObject) -> Result<Option<BlobKey>> {
+        match object.read_byte_list(property.offset) {
+            Some(bytes) if !bytes.is_empty() => match bytes.try_into() {
+                Ok(key) => Ok(Some(BlobKey(key))),
+                Err(_) => illegal_arg("Blob keys must be 16 bytes long."),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the blobs referenced by `object` and fails if one of them does not exist.
+    pub fn verify(&self, cursors: &IsarCursors, object: IsarObject) -> Result<Vec<BlobKey>> {
+        let mut cursor = cursors.get_cursor(self.db)?;
+        let mut keys = vec![];
+        for property in &self.properties {
+            if let Some(key) = Self::read_key(property, object)? {
+                if move_to_blob(&mut cursor, &key)?.is_none() {
+                    return Err(IsarError::IllegalArg {
+                        message: format!("Blob {:032x} does not exist.", key.to_hash()),
+                    });
+                }
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    pub fn add(&self, cursors: &IsarCursors, keys: &[BlobKey]) -> Result<()> {
+        let mut cursor = cursors.get_cursor(self.db)?;
+        for key in keys {
+            update_ref_count(&mut cursor, key, true)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the references of an object that is deleted or overwritten.
+    pub fn remove(&self, cursors: &IsarCursors, object: IsarObject) -> Result<()> {
+        let mut cursor = cursors.get_cursor(self.db)?;
+        for property in &self.properties {
+            if let Some(key) = Self::read_key(property, object)? {
+                update_ref_count(&mut cursor, &key, false)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::IsarInstance;
+    use crate::schema::Schema;
+
+    #[test]
+    fn test_put_hash_collision() {
+        let isar = IsarInstance::open_test("test_put_hash_collision", Schema::new(vec![]).unwrap());
+        let blobs = &isar.blobs;
+
+        // Store different bytes under the hash of "hello" to simulate a collision.
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let hash = BlobStore::hash(b"hello");
+        txn.write(isar.instance_id, |cursors, _| {
+            let mut cursor = cursors.get_cursor(blobs.db)?;
+            cursor.put(&BlobKey::new(hash), &[0, 0, 0, 0, 1, 2, 3])
+        })
+        .unwrap();
+
+        let key = blobs.put(&mut txn, b"hello").unwrap();
+        assert_eq!(key, hash.wrapping_add(1));
+        assert_eq!(blobs.put(&mut txn, b"hello").unwrap(), key);
+        assert_eq!(blobs.get(&mut txn, key).unwrap(), Some(&b"hello"[..]));
+        assert_eq!(blobs.get(&mut txn, hash).unwrap(), Some(&[1, 2, 3][..]));
+
+        txn.abort();
+        isar.close_and_delete();
+    }
+
+    #[test]
+    fn test_put_after_collected_collision() {
+        let isar = IsarInstance::open_test(
+            "test_put_after_collected_collision",
+            Schema::new(vec![]).unwrap(),
+        );
+        let blobs = &isar.blobs;
+
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let hash = BlobStore::hash(b"hello");
+        txn.write(isar.instance_id, |cursors, _| {
+            let mut cursor = cursors.get_cursor(blobs.db)?;
+            cursor.put(&BlobKey::new(hash), &[0, 0, 0, 0, 1, 2, 3])
+        })
+        .unwrap();
+        let key = blobs.put(&mut txn, b"hello").unwrap();
+        txn.write(isar.instance_id, |cursors, _| {
+            let mut cursor = cursors.get_cursor(blobs.db)?;
+            update_ref_count(&mut cursor, &BlobKey::new(key), true)
+        })
+        .unwrap();
+
+        // The colliding blob is unreferenced and collected but "hello" must still be found.
+        assert_eq!(blobs.collect_garbage(&mut txn).unwrap(), 1);
+        assert_eq!(blobs.get(&mut txn, hash).unwrap(), None);
+        assert_eq!(blobs.put(&mut txn, b"hello").unwrap(), key);
+        assert_eq!(blobs.ref_count(&mut txn, key).unwrap(), 1);
+
+        // Once "hello" is unreferenced too, the tombstone before it is removed as well.
+        txn.write(isar.instance_id, |cursors, _| {
+            let mut cursor = cursors.get_cursor(blobs.db)?;
+            update_ref_count(&mut cursor, &BlobKey::new(key), false)
+        })
+        .unwrap();
+        assert_eq!(blobs.collect_garbage(&mut txn).unwrap(), 1);
+        let entries = txn.read(isar.instance_id, |cursors| Ok(cursors.db_stat(blobs.db)?.0));
+        assert_eq!(entries.unwrap(), 0);
+
+        txn.abort();
+        isar.close_and_delete();
+    }
+}
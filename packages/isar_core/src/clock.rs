@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Time source for timestamps written by the database.
+pub trait Clock: Send + Sync {
+    /// Milliseconds since the unix epoch.
+    fn now_millis(&self) -> i64;
+}
+
+/// System time shifted by an adjustable offset to correct skewed device clocks.
+#[derive(Default)]
+pub struct SystemClock {
+    offset_millis: AtomicI64,
+}
+
+impl SystemClock {
+    pub fn new(offset_millis: i64) -> Self {
+        SystemClock {
+            offset_millis: AtomicI64::new(offset_millis),
+        }
+    }
+
+    pub fn set_offset(&self, offset_millis: i64) {
+        self.offset_millis.store(offset_millis, Ordering::Relaxed);
+    }
+}
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64);
+        now.saturating_add(self.offset_millis.load(Ordering::Relaxed))
+    }
+}
+
+/// Clock that only moves when told to. Useful for tests.
+#[derive(Default)]
+pub struct ManualClock {
+    now_millis: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(now_millis: i64) -> Self {
+        ManualClock {
+            now_millis: AtomicI64::new(now_millis),
+        }
+    }
+
+    pub fn set(&self, now_millis: i64) {
+        self.now_millis.store(now_millis, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, millis: i64) {
+        self.now_millis.fetch_add(millis, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> i64 {
+        self.now_millis.load(Ordering::Relaxed)
+    }
+}
@@ -1,23 +1,33 @@
+use crate::blob::BlobRefs;
 use crate::cursor::IsarCursors;
 use crate::error::{illegal_arg, IsarError, Result};
 use crate::index::index_key::IndexKey;
 use crate::index::index_key_builder::IndexKeyBuilder;
 use crate::index::IsarIndex;
-use crate::link::IsarLink;
+use crate::link::{IsarLink, LinkPairs};
 use crate::mdbx::db::Db;
-use crate::object::id::BytesToId;
+use crate::object::columns::{ColumnData, Columns};
+use crate::object::id::{BytesToId, IdToBytes};
 use crate::object::isar_object::IsarObject;
 use crate::object::json_encode_decode::JsonEncodeDecode;
 use crate::object::object_builder::ObjectBuilder;
 use crate::object::property::Property;
 use crate::query::query_builder::QueryBuilder;
+use crate::query::DeadlineCheck;
+use crate::schema::constraints::Constraints;
+use crate::stats::{
+    CollectionFragmentation, ImportReport, IndexAdvice, IndexAdvisor, IndexConsistency,
+    SweepReport, WriteCounter, WriteStats,
+};
 use crate::txn::IsarTxn;
 use crate::watch::change_set::ChangeSet;
+use byteorder::{ByteOrder, LittleEndian};
 use intmap::IntMap;
 use itertools::Itertools;
 use serde_json::Value;
-use std::cell::Cell;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
 use xxhash_rust::xxh3::xxh3_64;
 
 pub struct IsarCollection {
@@ -33,8 +43,15 @@ pub struct IsarCollection {
     pub(crate) indexes: Vec<IsarIndex>,
     pub(crate) links: Vec<IsarLink>, // links from this collection
     backlinks: Vec<IsarLink>,        // links to this collection
+    modified_at: Option<Property>,
+    sequence_db: Option<Db>,
+    constraints: Vec<(Property, Constraints)>,
+    blob_refs: Option<BlobRefs>,
+    append_only: bool,
 
-    auto_increment: Cell<i64>,
+    pub(crate) auto_increment: Arc<AtomicI64>,
+    write_counter: Arc<WriteCounter>,
+    pub(crate) index_advisor: Arc<IndexAdvisor>,
 }
 
 unsafe impl Send for IsarCollection {}
@@ -51,6 +68,11 @@ impl IsarCollection {
         indexes: Vec<IsarIndex>,
         links: Vec<IsarLink>,
         backlinks: Vec<IsarLink>,
+        modified_at: Option<Property>,
+        sequence_db: Option<Db>,
+        constraints: Vec<(Property, Constraints)>,
+        blob_refs: Option<BlobRefs>,
+        append_only: bool,
     ) -> Self {
         let id = xxh3_64(name.as_bytes());
         IsarCollection {
@@ -63,7 +85,14 @@ impl IsarCollection {
             indexes,
             links,
             backlinks,
-            auto_increment: Cell::new(0),
+            modified_at,
+            sequence_db,
+            constraints,
+            blob_refs,
+            append_only,
+            auto_increment: Arc::new(AtomicI64::new(0)),
+            write_counter: Arc::new(WriteCounter::default()),
+            index_advisor: Arc::new(IndexAdvisor::default()),
         }
     }
 
@@ -81,12 +110,18 @@ impl IsarCollection {
             let id = key.deref().to_id();
             self.update_auto_increment(id);
         }
+        if let Some(sequence_db) = self.sequence_db {
+            let mut cursor = cursors.get_cursor(sequence_db)?;
+            if let Some((_, sequence)) = cursor.move_to(&(self.id as i64))? {
+                self.update_auto_increment(sequence.to_id());
+            }
+        }
         Ok(())
     }
 
     pub(crate) fn update_auto_increment(&self, id: i64) {
-        if id > self.auto_increment.get() {
-            self.auto_increment.set(id);
+        if id > self.auto_increment.load(Ordering::SeqCst) {
+            self.auto_increment.store(id, Ordering::SeqCst);
         }
     }
 
@@ -94,10 +129,28 @@ impl IsarCollection {
         self.auto_increment_internal()
     }
 
+    pub fn next_auto_increment(&self, _: &mut IsarTxn) -> Option<i64> {
+        self.auto_increment.load(Ordering::SeqCst).checked_add(1)
+    }
+
+    pub fn min_id(&self, txn: &mut IsarTxn) -> Result<Option<i64>> {
+        txn.read(self.instance_id, |cursors| {
+            let mut cursor = cursors.get_cursor(self.db)?;
+            Ok(cursor.move_to_first()?.map(|(key, _)| key.to_id()))
+        })
+    }
+
+    pub fn max_id(&self, txn: &mut IsarTxn) -> Result<Option<i64>> {
+        txn.read(self.instance_id, |cursors| {
+            let mut cursor = cursors.get_cursor(self.db)?;
+            Ok(cursor.move_to_last()?.map(|(key, _)| key.to_id()))
+        })
+    }
+
     pub(crate) fn auto_increment_internal(&self) -> Result<i64> {
-        let last = self.auto_increment.get();
+        let last = self.auto_increment.load(Ordering::SeqCst);
         if last < i64::MAX {
-            self.auto_increment.set(last + 1);
+            self.auto_increment.store(last + 1, Ordering::SeqCst);
             Ok(last + 1)
         } else {
             Err(IsarError::AutoIncrementOverflow {})
@@ -114,6 +167,44 @@ impl IsarCollection {
         })
     }
 
+    pub fn exists(&self, txn: &mut IsarTxn, id: i64) -> Result<bool> {
+        Ok(self.exists_many(txn, &[id])?[0])
+    }
+
+    pub fn exists_many(&self, txn: &mut IsarTxn, ids: &[i64]) -> Result<Vec<bool>> {
+        txn.read(self.instance_id, |cursors| {
+            let mut cursor = cursors.get_cursor(self.db)?;
+            let mut result = Vec::with_capacity(ids.len());
+            for id in ids {
+                result.push(cursor.move_to(id)?.is_some());
+            }
+            Ok(result)
+        })
+    }
+
+    pub fn get_json(
+        &self,
+        txn: &mut IsarTxn,
+        id: i64,
+        id_name: Option<&str>,
+        primitive_null: bool,
+    ) -> Result<Option<Value>> {
+        let object = self.get(txn, id)?;
+        let json = object.map(|object| {
+            let mut json = JsonEncodeDecode::encode(
+                &self.properties,
+                &self.embedded_properties,
+                object,
+                primitive_null,
+            );
+            if let Some(id_name) = id_name {
+                json.insert(id_name.to_string(), Value::from(id));
+            }
+            Value::Object(json)
+        });
+        Ok(json)
+    }
+
     pub(crate) fn get_index_by_id(&self, index_id: u64) -> Result<&IsarIndex> {
         self.indexes
             .iter()
@@ -143,9 +234,13 @@ impl IsarCollection {
     }
 
     pub fn put(&self, txn: &mut IsarTxn, id: Option<i64>, object: IsarObject) -> Result<i64> {
-        txn.write(self.instance_id, |cursors, change_set| {
-            self.put_internal(cursors, change_set, id, object)
-        })
+        let now = txn.now();
+        let mut stats = WriteStats::default();
+        let id = txn.write(self.instance_id, |cursors, change_set| {
+            self.put_internal(cursors, change_set, id, object, now, &mut stats)
+        })?;
+        txn.record_write(&self.write_counter, stats);
+        Ok(id)
     }
 
     pub fn put_by_index(
@@ -159,26 +254,120 @@ impl IsarCollection {
             illegal_arg("Cannot put by a multi-entry index")?;
         }
         let key_builder = IndexKeyBuilder::new(&index.properties);
-        txn.write(self.instance_id, |cursors, change_set| {
+        let now = txn.now();
+        let mut stats = WriteStats::default();
+        let new_id = txn.write(self.instance_id, |cursors, change_set| {
             let key = key_builder.create_primitive_key(object);
             let id = index.get_id(cursors, &key)?;
-            let new_id = self.put_internal(cursors, change_set, id, object)?;
-            Ok(new_id)
-        })
+            self.put_internal(cursors, change_set, id, object, now, &mut stats)
+        })?;
+        txn.record_write(&self.write_counter, stats);
+        Ok(new_id)
+    }
+
+    /// Inserts one object per row of the columns. Properties without a column are null.
+    pub fn put_columns(
+        &self,
+        txn: &mut IsarTxn,
+        ids: Option<&[i64]>,
+        columns: Vec<(&Property, ColumnData)>,
+    ) -> Result<Vec<i64>> {
+        let columns = Columns::new(&self.properties, columns)?;
+        if let Some(ids) = ids {
+            if ids.len() != columns.rows() {
+                illegal_arg("The number of ids must match the number of rows.")?;
+            }
+        }
+
+        let mut stats = WriteStats::default();
+        let now = txn.now();
+        let new_ids = txn.write(self.instance_id, |cursors, mut change_set| {
+            let mut new_ids = Vec::with_capacity(columns.rows());
+            let mut buffer = None;
+            for row in 0..columns.rows() {
+                let mut ob = ObjectBuilder::new(&self.properties, buffer);
+                columns.write_row(row, &mut ob);
+                let object = ob.finish();
+                let id = ids.map(|ids| ids[row]);
+                let change_set = change_set.as_deref_mut();
+                let id = self.put_internal(cursors, change_set, id, object, now, &mut stats)?;
+                new_ids.push(id);
+                buffer = Some(ob.recycle());
+            }
+            Ok(new_ids)
+        })?;
+        txn.record_write(&self.write_counter, stats);
+        Ok(new_ids)
     }
 
     fn put_internal(
+        &self,
+        cursors: &IsarCursors,
+        change_set: Option<&mut ChangeSet>,
+        id: Option<i64>,
+        object: IsarObject,
+        now: i64,
+        stats: &mut WriteStats,
+    ) -> Result<i64> {
+        self.put_object(cursors, change_set, id, object, Some(now), stats, false)
+    }
+
+    /// With `verify_first` unique index violations are reported before anything is written.
+    /// Without `now` the modified at property is left unchanged.
+    #[allow(clippy::too_many_arguments)]
+    fn put_object(
         &self,
         cursors: &IsarCursors,
         mut change_set: Option<&mut ChangeSet>,
         id: Option<i64>,
         object: IsarObject,
+        now: Option<i64>,
+        stats: &mut WriteStats,
+        verify_first: bool,
     ) -> Result<i64> {
         if object.len() > IsarObject::MAX_SIZE as usize {
             illegal_arg("Object is bigger than 16MB")?;
         }
 
+        let truncated_bytes;
+        let object = if let Some((bytes, truncated)) = self.truncate(object) {
+            truncated_bytes = bytes;
+            stats.truncated += truncated;
+            IsarObject::from_bytes(&truncated_bytes)
+        } else {
+            object
+        };
+
+        let stamped_bytes;
+        let object = if let (Some(property), Some(now)) = (&self.modified_at, now) {
+            stamped_bytes = Self::stamp_modified_at(object, property.offset, now);
+            IsarObject::from_bytes(&stamped_bytes)
+        } else {
+            object
+        };
+
+        for (property, constraints) in &self.constraints {
+            constraints.validate(property, object)?;
+        }
+
+        let blob_keys = if let Some(blob_refs) = &self.blob_refs {
+            blob_refs.verify(cursors, object)?
+        } else {
+            vec![]
+        };
+
+        if verify_first {
+            for index in &self.indexes {
+                if index.verify_for_object(cursors, id, object)? {
+                    self.check_not_append_only()?;
+                }
+            }
+        }
+
         let id = if let Some(id) = id {
+            if self.append_only && cursors.get_cursor(self.db)?.move_to(&id)?.is_some() {
+                return Err(IsarError::AppendOnly {});
+            }
             self.delete_internal(cursors, false, change_set.as_deref_mut(), id)?;
             self.update_auto_increment(id);
             id
@@ -188,23 +377,105 @@ impl IsarCollection {
 
         for index in &self.indexes {
             index.create_for_object(cursors, id, object, |id| {
+                self.check_not_append_only()?;
                 self.delete_internal(cursors, true, change_set.as_deref_mut(), id)?;
                 Ok(())
             })?;
         }
 
+        if let Some(blob_refs) = &self.blob_refs {
+            blob_refs.add(cursors, &blob_keys)?;
+        }
+
         let mut cursor = cursors.get_cursor(self.db)?;
         cursor.put(&id, object.as_bytes())?;
+        if let Some(sequence_db) = self.sequence_db {
+            if id == self.auto_increment.load(Ordering::SeqCst) {
+                let mut cursor = cursors.get_cursor(sequence_db)?;
+                cursor.put(&(self.id as i64), &id.to_id_bytes())?;
+            }
+        }
         if let Some(change_set) = change_set {
             change_set.register_change(self.id, id, object);
         }
+        stats.add(WriteStats::put(object.len()));
         Ok(id)
     }
 
+    /// Rebuilds the object if a String exceeds the max length of a truncating constraint.
+    fn truncate(&self, object: IsarObject) -> Option<(Vec<u8>, u64)> {
+        let mut truncated = vec![];
+        for (property, constraints) in &self.constraints {
+            if let Some(value) = constraints.truncate(property, &object) {
+                truncated.push((property, value));
+            }
+        }
+        if truncated.is_empty() {
+            return None;
+        }
+
+        let mut ob = ObjectBuilder::new(&self.properties, None);
+        for property in &self.properties {
+            if let Some((_, value)) = truncated.iter().find(|(p, _)| *p == property) {
+                ob.write_string(property.offset, Some(value));
+            } else {
+                ob.write_from(property, object);
+            }
+        }
+        Some((ob.finish().as_bytes().to_vec(), truncated.len() as u64))
+    }
+
+    fn stamp_modified_at(object: IsarObject, offset: usize, now: i64) -> Vec<u8> {
+        let mut bytes = object.as_bytes().to_vec();
+        if object.contains_offset(offset) {
+            LittleEndian::write_i64(&mut bytes[offset..], now);
+        }
+        bytes
+    }
+
+    fn check_not_append_only(&self) -> Result<()> {
+        if self.append_only {
+            Err(IsarError::AppendOnly {})
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Copies the object with `id` into `target` which may be this collection or one with a
+    /// compatible schema. Returns the id of the copy or `None` if the object does not exist.
+    /// Fails without writing if a property has a different type in `target`.
+    pub fn copy_object(
+        &self,
+        txn: &mut IsarTxn,
+        id: i64,
+        target: &IsarCollection,
+        target_id: Option<i64>,
+    ) -> Result<Option<i64>> {
+        let mut ob = target.new_object_builder(None);
+        if let Some(object) = self.get(txn, id)? {
+            ob.write_all_from(
+                &target.properties,
+                &target.embedded_properties,
+                object,
+                &self.properties,
+                &self.embedded_properties,
+            )?;
+        } else {
+            return Ok(None);
+        }
+        let id = target.put(txn, target_id, ob.finish())?;
+        Ok(Some(id))
+    }
+
     pub fn delete(&self, txn: &mut IsarTxn, id: i64) -> Result<bool> {
-        txn.write(self.instance_id, |cursors, change_set| {
+        self.check_not_append_only()?;
+        let deleted = txn.write(self.instance_id, |cursors, change_set| {
             self.delete_internal(cursors, true, change_set, id)
-        })
+        })?;
+        if deleted {
+            txn.record_write(&self.write_counter, WriteStats::delete());
+        }
+        Ok(deleted)
     }
 
     pub fn delete_by_index(
@@ -213,15 +484,40 @@ impl IsarCollection {
         index_id: u64,
         key: &IndexKey,
     ) -> Result<bool> {
+        self.check_not_append_only()?;
         let index = self.get_index_by_id(index_id)?;
-        txn.write(self.instance_id, |cursors, change_set| {
+        let deleted = txn.write(self.instance_id, |cursors, change_set| {
             if let Some(id) = index.get_id(cursors, key)? {
                 self.delete_internal(cursors, true, change_set, id)?;
                 Ok(true)
             } else {
                 Ok(false)
             }
-        })
+        })?;
+        if deleted {
+            txn.record_write(&self.write_counter, WriteStats::delete());
+        }
+        Ok(deleted)
+    }
+
+    /// Deletes objects even if the collection is append-only. Returns the number of deleted
+    /// objects.
+    pub fn purge(&self, txn: &mut IsarTxn, ids: &[i64]) -> Result<u32> {
+        let deleted = txn.write(self.instance_id, |cursors, mut change_set| {
+            let mut deleted = 0;
+            for id in ids {
+                if self.delete_internal(cursors, true, change_set.as_deref_mut(), *id)? {
+                    deleted += 1;
+                }
+            }
+            Ok(deleted)
+        })?;
+        let stats = WriteStats {
+            deletes: deleted as u64,
+            ..Default::default()
+        };
+        txn.record_write(&self.write_counter, stats);
+        Ok(deleted)
     }
 
     fn delete_internal(
@@ -237,6 +533,9 @@ impl IsarCollection {
             for index in &self.indexes {
                 index.delete_for_object(cursors, id, object)?;
             }
+            if let Some(blob_refs) = &self.blob_refs {
+                blob_refs.remove(cursors, object)?;
+            }
             if delete_links {
                 for link in &self.links {
                     link.delete_all_for_object(cursors, id)?;
@@ -286,7 +585,101 @@ impl IsarCollection {
         })
     }
 
+    /// Creates many `(id, target_id)` links at once. With `replace` the existing links of all
+    /// source ids are removed first. Returns the number of links whose objects exist.
+    pub fn link_many(
+        &self,
+        txn: &mut IsarTxn,
+        link_id: u64,
+        links: &[(i64, i64)],
+        replace: bool,
+    ) -> Result<u32> {
+        let link = self.get_link_backlink(link_id)?;
+        txn.write(self.instance_id, |cursors, _| {
+            if replace {
+                for id in links.iter().map(|(id, _)| *id).unique() {
+                    link.delete_all_for_object(cursors, id)?;
+                }
+            }
+            let mut created = 0;
+            for (id, target_id) in links {
+                if link.create(cursors, *id, *target_id)? {
+                    created += 1;
+                }
+            }
+            Ok(created)
+        })
+    }
+
+    /// Removes all links of many source ids at once.
+    pub fn unlink_all_many(&self, txn: &mut IsarTxn, link_id: u64, ids: &[i64]) -> Result<()> {
+        let link = self.get_link_backlink(link_id)?;
+        txn.write(self.instance_id, |cursors, _| {
+            for id in ids {
+                link.delete_all_for_object(cursors, *id)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns the `(id, other_id)` pairs of all links and backlinks of the objects with `ids`
+    /// grouped by link id.
+    pub(crate) fn get_links(&self, txn: &mut IsarTxn, ids: &[i64]) -> Result<Vec<LinkPairs>> {
+        txn.read(self.instance_id, |cursors| {
+            let mut result = vec![];
+            for link in self.links.iter().chain(&self.backlinks) {
+                let mut pairs = vec![];
+                for id in ids {
+                    link.iter_ids(cursors, *id, |_, other_id| {
+                        pairs.push((*id, other_id));
+                        Ok(true)
+                    })?;
+                }
+                if !pairs.is_empty() {
+                    result.push((link.id, pairs));
+                }
+            }
+            Ok(result)
+        })
+    }
+
+    /// Puts objects back unchanged and recreates their links. Links to objects that no longer
+    /// exist are skipped.
+    pub(crate) fn restore(
+        &self,
+        txn: &mut IsarTxn,
+        objects: &[(i64, IsarObject)],
+        links: &[LinkPairs],
+    ) -> Result<()> {
+        let mut stats = WriteStats::default();
+        txn.write(self.instance_id, |cursors, mut change_set| {
+            for (id, object) in objects {
+                let change_set = change_set.as_deref_mut();
+                self.put_object(
+                    cursors,
+                    change_set,
+                    Some(*id),
+                    *object,
+                    None,
+                    &mut stats,
+                    false,
+                )?;
+            }
+            for (link_id, pairs) in links {
+                let link = self.get_link_backlink(*link_id)?;
+                for (id, other_id) in pairs {
+                    link.create(cursors, *id, *other_id)?;
+                }
+            }
+            Ok(())
+        })?;
+        txn.record_write(&self.write_counter, stats);
+        Ok(())
+    }
+
     pub fn clear(&self, txn: &mut IsarTxn) -> Result<()> {
+        self.check_not_append_only()?;
+        let count = self.count(txn)?;
         txn.write(self.instance_id, |cursors, change_set| {
             for index in &self.indexes {
                 index.clear(cursors)?;
@@ -297,15 +690,49 @@ impl IsarCollection {
             for link in &self.backlinks {
                 link.clear(cursors)?;
             }
+            if let Some(blob_refs) = &self.blob_refs {
+                let mut cursor = cursors.get_cursor(self.db)?;
+                cursor.iter_all(false, true, |_, _, object| {
+                    blob_refs.remove(cursors, IsarObject::from_bytes(object))?;
+                    Ok(true)
+                })?;
+            }
             cursors.clear_db(self.db)?;
-            self.auto_increment.set(0);
+            if self.sequence_db.is_none() {
+                self.auto_increment.store(0, Ordering::SeqCst);
+            }
 
             if let Some(change_set) = change_set {
                 change_set.register_all(self.id);
             }
 
             Ok(())
-        })
+        })?;
+        let stats = WriteStats {
+            deletes: count,
+            ..Default::default()
+        };
+        txn.record_write(&self.write_counter, stats);
+        Ok(())
+    }
+
+    pub fn get_write_stats(&self) -> WriteStats {
+        self.write_counter.get()
+    }
+
+    /// Properties that built queries filtered or sorted by without an index starting with them.
+    pub fn get_index_advice(&self) -> Vec<IndexAdvice> {
+        self.index_advisor.advice()
+    }
+
+    pub fn reset_index_advice(&self) {
+        self.index_advisor.reset();
+    }
+
+    pub(crate) fn has_index_on(&self, offset: usize) -> bool {
+        self.indexes
+            .iter()
+            .any(|i| i.properties[0].property.offset == offset)
     }
 
     pub fn count(&self, txn: &mut IsarTxn) -> Result<u64> {
@@ -337,7 +764,84 @@ impl IsarCollection {
         })
     }
 
+    /// Removes index entries and links that refer to objects that no longer exist. Such entries
+    /// are only left behind by failed writes or bugs.
+    pub fn sweep_orphans(&self, txn: &mut IsarTxn) -> Result<SweepReport> {
+        self.sweep_orphans_until(txn, &mut DeadlineCheck::new(None))
+    }
+
+    pub(crate) fn sweep_orphans_until(
+        &self,
+        txn: &mut IsarTxn,
+        deadline: &mut DeadlineCheck,
+    ) -> Result<SweepReport> {
+        txn.write(self.instance_id, |cursors, _| {
+            let mut report = SweepReport::default();
+            for index in &self.indexes {
+                if deadline.expired_now() {
+                    break;
+                }
+                report.index_entries += index.sweep_orphans(cursors, self.db, deadline)?;
+            }
+            for link in &self.links {
+                if deadline.expired_now() {
+                    break;
+                }
+                report.links += link.sweep_orphans(cursors, deadline)?;
+            }
+            Ok(report)
+        })
+    }
+
+    /// Cross-checks every index with the objects of the collection. Use `sample_every` to only
+    /// look up the entries of every nth object in large collections.
+    pub fn check_index_consistency(
+        &self,
+        txn: &mut IsarTxn,
+        sample_every: u64,
+    ) -> Result<Vec<IndexConsistency>> {
+        txn.read(self.instance_id, |cursors| {
+            self.indexes
+                .iter()
+                .map(|index| index.check_consistency(cursors, self.db, sample_every))
+                .collect()
+        })
+    }
+
+    /// Scans all objects of the collection to compare stored bytes with allocated pages.
+    pub fn get_fragmentation(&self, txn: &mut IsarTxn) -> Result<CollectionFragmentation> {
+        txn.read(self.instance_id, |cursors| {
+            let (objects, allocated_bytes) = cursors.db_stat(self.db)?;
+            let mut payload_bytes = 0;
+            let mut cursor = cursors.get_cursor(self.db)?;
+            cursor.iter_all(false, true, |_, id_bytes, object| {
+                payload_bytes += (id_bytes.len() + object.len()) as u64;
+                Ok(true)
+            })?;
+
+            let mut index_bytes = 0;
+            for index in &self.indexes {
+                index_bytes += index.get_size(cursors)?;
+            }
+            let mut link_bytes = 0;
+            for link in &self.links {
+                link_bytes += link.get_size(cursors)?;
+            }
+
+            Ok(CollectionFragmentation {
+                name: self.name.clone(),
+                objects,
+                allocated_bytes,
+                payload_bytes,
+                index_bytes,
+                link_bytes,
+            })
+        })
+    }
+
     pub fn import_json(&self, txn: &mut IsarTxn, id_name: Option<&str>, json: Value) -> Result<()> {
+        let mut stats = WriteStats::default();
+        let now = txn.now();
         txn.write(self.instance_id, |cursors, mut change_set| {
             let array = json.as_array().ok_or(IsarError::InvalidJson {})?;
             let mut ob_result_cache = None;
@@ -361,11 +865,51 @@ impl IsarCollection {
                     value,
                 )?;
                 let object = ob.finish();
-                self.put_internal(cursors, change_set.as_deref_mut(), id, object)?;
+                let change_set = change_set.as_deref_mut();
+                self.put_internal(cursors, change_set, id, object, now, &mut stats)?;
                 ob_result_cache = Some(ob.recycle());
             }
             Ok(())
-        })
+        })?;
+        txn.record_write(&self.write_counter, stats);
+        Ok(())
+    }
+
+    /// Imports the objects of a JSON array one by one. Invalid objects are skipped and reported
+    /// by their position in the array while all other objects are imported.
+    pub fn import_json_partial(
+        &self,
+        txn: &mut IsarTxn,
+        id_name: Option<&str>,
+        json: Value,
+    ) -> Result<ImportReport> {
+        let mut stats = WriteStats::default();
+        let mut report = ImportReport::default();
+        let now = txn.now();
+        txn.write(self.instance_id, |cursors, mut change_set| {
+            let array = json.as_array().ok_or(IsarError::InvalidJson {})?;
+            for (line, value) in array.iter().enumerate() {
+                let result = self.decode_json(id_name, value).and_then(|(id, bytes)| {
+                    let object = IsarObject::from_bytes(&bytes);
+                    let change_set = change_set.as_deref_mut();
+                    self.put_object(cursors, change_set, id, object, Some(now), &mut stats, true)
+                });
+                report.add(line, result)?;
+            }
+            Ok(())
+        })?;
+        txn.record_write(&self.write_counter, stats);
+        Ok(report)
+    }
+
+    fn decode_json(&self, id_name: Option<&str>, value: &Value) -> Result<(Option<i64>, Vec<u8>)> {
+        let id = match id_name.and_then(|id_name| value.get(id_name)) {
+            Some(id) => Some(id.as_i64().ok_or(IsarError::InvalidJson {})?),
+            None => None,
+        };
+        let mut ob = ObjectBuilder::new(&self.properties, None);
+        JsonEncodeDecode::decode(&self.properties, &self.embedded_properties, &mut ob, value)?;
+        Ok((id, ob.finish().as_bytes().to_vec()))
     }
 
     pub(crate) fn fill_indexes(&self, index_ids: &[u64], cursors: &IsarCursors) -> Result<()> {
@@ -445,3 +989,159 @@ impl IsarCollection {
         txn.read(self.instance_id, |cursors| link.verify(cursors, links))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::IsarInstance;
+    use crate::object::data_type::DataType;
+    use crate::schema::collection_schema::CollectionSchema;
+    use crate::schema::index_schema::{IndexPropertySchema, IndexSchema, IndexType};
+    use crate::schema::link_schema::LinkSchema;
+    use crate::schema::property_schema::PropertySchema;
+    use crate::schema::Schema;
+    use std::time::Duration;
+    use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+    fn open_instance(name: &str, unique: bool) -> Arc<IsarInstance> {
+        let properties = vec![PropertySchema::new(
+            Some("value".to_string()),
+            DataType::Long,
+            None,
+        )];
+        let indexes = vec![IndexSchema::new(
+            "value",
+            vec![IndexPropertySchema::new("value", IndexType::Value, false)],
+            unique,
+            false,
+        )];
+        let links = vec![LinkSchema::new("links", "col")];
+        let col = CollectionSchema::new("col", false, properties, indexes, links);
+        IsarInstance::open_test(name, Schema::new(vec![col]).unwrap())
+    }
+
+    fn put_values(col: &IsarCollection, txn: &mut IsarTxn, values: &[(i64, i64)]) {
+        for (id, value) in values {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(col.properties[0].offset, *value);
+            col.put(txn, Some(*id), ob.finish()).unwrap();
+        }
+    }
+
+    /// Deletes the object but keeps its index entries and links.
+    fn delete_object_only(col: &IsarCollection, txn: &mut IsarTxn, id: i64) {
+        txn.write(col.instance_id, |cursors, _| {
+            let mut cursor = cursors.get_cursor(col.db)?;
+            cursor.move_to(&id)?.unwrap();
+            cursor.delete_current()
+        })
+        .unwrap();
+    }
+
+    /// Deletes the index entries of the object but keeps the object.
+    fn delete_index_entries_only(col: &IsarCollection, txn: &mut IsarTxn, id: i64) {
+        txn.write(col.instance_id, |cursors, _| {
+            let mut cursor = cursors.get_cursor(col.db)?;
+            let (_, object) = cursor.move_to(&id)?.unwrap();
+            col.indexes[0].delete_for_object(cursors, id, IsarObject::from_bytes(&object))
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_sweep_orphans() {
+        let isar = open_instance("test_sweep_orphans", false);
+        let col = &isar.collections[0];
+        let link_id = xxh3_64_with_seed(b"links", xxh3_64_with_seed(b"col", 0));
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put_values(col, &mut txn, &[(1, 10), (2, 20), (3, 30)]);
+        col.link(&mut txn, link_id, 1, 2).unwrap();
+        col.link(&mut txn, link_id, 1, 3).unwrap();
+
+        delete_object_only(col, &mut txn, 2);
+        let report = col.sweep_orphans(&mut txn).unwrap();
+        assert_eq!(report.index_entries, 1);
+        // the link and its backlink
+        assert_eq!(report.links, 2);
+
+        let consistency = col.check_index_consistency(&mut txn, 1).unwrap();
+        assert!(consistency[0].is_consistent());
+        assert_eq!(consistency[0].entries, 2);
+        let mut qb = col.new_query_builder();
+        qb.add_link_where_clause(col, link_id, 1).unwrap();
+        assert_eq!(qb.build().count(&mut txn).unwrap(), 1);
+
+        let report = col.sweep_orphans(&mut txn).unwrap();
+        assert_eq!(report, SweepReport::default());
+
+        txn.abort();
+        isar.close_and_delete();
+    }
+
+    #[test]
+    fn test_maintenance_sweeps_orphans() {
+        let isar = open_instance("test_maintenance_sweeps_orphans", false);
+        let col = &isar.collections[0];
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put_values(col, &mut txn, &[(1, 10), (2, 20)]);
+        delete_object_only(col, &mut txn, 2);
+        txn.commit().unwrap();
+
+        let report = isar.perform_maintenance(Duration::ZERO).unwrap();
+        assert_eq!(report.swept, SweepReport::default());
+
+        let report = isar.perform_maintenance(Duration::from_secs(10)).unwrap();
+        assert_eq!(report.swept.index_entries, 1);
+        assert!(report.completed);
+
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let consistency = col.check_index_consistency(&mut txn, 1).unwrap();
+        assert!(consistency[0].is_consistent());
+        assert_eq!(consistency[0].entries, 1);
+        txn.abort();
+        isar.close_and_delete();
+    }
+
+    fn check_index_consistency_detects_corruption(name: &str, unique: bool) {
+        let isar = open_instance(name, unique);
+        let col = &isar.collections[0];
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        put_values(col, &mut txn, &[(1, 10), (2, 20), (3, 30)]);
+        let report = col.check_index_consistency(&mut txn, 1).unwrap();
+        assert!(report[0].is_consistent());
+
+        delete_index_entries_only(col, &mut txn, 1);
+        delete_object_only(col, &mut txn, 3);
+
+        let report = col.check_index_consistency(&mut txn, 1).unwrap();
+        assert!(!report[0].is_consistent());
+        assert_eq!(report[0].missing_entries, vec![1]);
+        assert_eq!(report[0].orphaned_entries, vec![3]);
+        assert_eq!(report[0].entries, 2);
+        assert_eq!(report[0].expected_entries, 2);
+
+        assert_eq!(col.sweep_orphans(&mut txn).unwrap().index_entries, 1);
+        let report = col.check_index_consistency(&mut txn, 1).unwrap();
+        assert!(report[0].orphaned_entries.is_empty());
+        assert_eq!(report[0].entries, 1);
+
+        txn.abort();
+        isar.close_and_delete();
+    }
+
+    #[test]
+    fn test_check_index_consistency_detects_corruption() {
+        check_index_consistency_detects_corruption(
+            "test_check_index_consistency_detects_corruption",
+            false,
+        );
+    }
+
+    #[test]
+    fn test_check_unique_index_consistency_detects_corruption() {
+        check_index_consistency_detects_corruption(
+            "test_check_unique_index_consistency_detects_corruption",
+            true,
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Behavior suite that every storage backend has to pass. Backends provide a function that
+//! opens an instance with the given name and schema and run [run_conformance_suite] from their
+//! own tests.
+
+use crate::collection::IsarCollection;
+use crate::error::IsarError;
+use crate::instance::IsarInstance;
+use crate::object::data_type::DataType;
+use crate::object::property::Property;
+use crate::query::filter::Filter;
+use crate::query::Sort;
+use crate::schema::collection_schema::CollectionSchema;
+use crate::schema::index_schema::{IndexPropertySchema, IndexSchema, IndexType};
+use crate::schema::property_schema::PropertySchema;
+use crate::schema::Schema;
+use crate::txn::IsarTxn;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub type OpenInstance<'a> = dyn Fn(&str, Schema) -> Arc<IsarInstance> + 'a;
+
+pub struct ConformanceCase {
+    pub name: &'static str,
+    run: fn(&Arc<IsarInstance>),
+}
+
+pub const CASES: &[ConformanceCase] = &[
+    ConformanceCase {
+        name: "schema",
+        run: check_schema,
+    },
+    ConformanceCase {
+        name: "crud",
+        run: check_crud,
+    },
+    ConformanceCase {
+        name: "unique_index",
+        run: check_unique_index,
+    },
+    ConformanceCase {
+        name: "filters",
+        run: check_filters,
+    },
+    ConformanceCase {
+        name: "sorts",
+        run: check_sorts,
+    },
+    ConformanceCase {
+        name: "transactions",
+        run: check_transactions,
+    },
+    ConformanceCase {
+        name: "watchers",
+        run: check_watchers,
+    },
+];
+
+/// Runs all cases with a fresh instance each. Panics on the first failing case.
+pub fn run_conformance_suite(open: &OpenInstance) {
+    for case in CASES {
+        run_conformance_case(open, case);
+    }
+}
+
+pub fn run_conformance_case(open: &OpenInstance, case: &ConformanceCase) {
+    let name = format!("conformance_{}", case.name);
+    let isar = open(&name, conformance_schema());
+    (case.run)(&isar);
+    isar.close_and_delete();
+}
+
+fn conformance_schema() -> Schema {
+    let properties = vec![
+        PropertySchema::new(Some("name".to_string()), DataType::String, None),
+        PropertySchema::new(Some("value".to_string()), DataType::Long, None),
+    ];
+    let index = IndexSchema::new(
+        "name",
+        vec![IndexPropertySchema::new("name", IndexType::Value, true)],
+        true,
+        false,
+    );
+    let col = CollectionSchema::new("col", false, properties, vec![index], vec![]);
+    Schema::new(vec![col]).unwrap()
+}
+
+fn property<'a>(col: &'a IsarCollection, name: &str) -> &'a Property {
+    col.properties.iter().find(|p| p.name == name).unwrap()
+}
+
+fn put(col: &IsarCollection, txn: &mut IsarTxn, id: Option<i64>, name: &str, value: i64) -> i64 {
+    try_put(col, txn, id, name, value).unwrap()
+}
+
+fn try_put(
+    col: &IsarCollection,
+    txn: &mut IsarTxn,
+    id: Option<i64>,
+    name: &str,
+    value: i64,
+) -> crate::error::Result<i64> {
+    let mut ob = col.new_object_builder(None);
+    ob.write_string(property(col, "name").offset, Some(name));
+    ob.write_long(property(col, "value").offset, value);
+    col.put(txn, id, ob.finish())
+}
+
+fn read_value(col: &IsarCollection, txn: &mut IsarTxn, id: i64) -> i64 {
+    let object = col.get(txn, id).unwrap().unwrap();
+    object.read_long(property(col, "value").offset)
+}
+
+fn query_ids(col: &IsarCollection, txn: &mut IsarTxn, filter: Option<Filter>) -> Vec<i64> {
+    let mut qb = col.new_query_builder();
+    if let Some(filter) = filter {
+        qb.set_filter(filter);
+    }
+    let mut ids = vec![];
+    qb.build()
+        .find_ids_while(txn, |id| {
+            ids.push(id);
+            true
+        })
+        .unwrap();
+    ids
+}
+
+fn check_schema(isar: &Arc<IsarInstance>) {
+    assert_eq!(isar.collections.len(), 1);
+    let col = &isar.collections[0];
+    assert_eq!(col.name, "col");
+    assert_eq!(property(col, "name").data_type, DataType::String);
+    assert_eq!(property(col, "value").data_type, DataType::Long);
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    isar.verify(&mut txn).unwrap();
+    txn.abort();
+}
+
+fn check_crud(isar: &Arc<IsarInstance>) {
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+
+    let id1 = put(col, &mut txn, None, "a", 1);
+    let id2 = put(col, &mut txn, None, "b", 2);
+    assert!(id2 > id1);
+    put(col, &mut txn, Some(10), "c", 3);
+    assert_eq!(col.count(&mut txn).unwrap(), 3);
+    assert_eq!(read_value(col, &mut txn, 10), 3);
+
+    put(col, &mut txn, Some(10), "c", 4);
+    assert_eq!(col.count(&mut txn).unwrap(), 3);
+    assert_eq!(read_value(col, &mut txn, 10), 4);
+
+    assert!(col.delete(&mut txn, id1).unwrap());
+    assert!(!col.delete(&mut txn, id1).unwrap());
+    assert!(col.get(&mut txn, id1).unwrap().is_none());
+
+    col.clear(&mut txn).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 0);
+    txn.commit().unwrap();
+}
+
+fn check_unique_index(isar: &Arc<IsarInstance>) {
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put(col, &mut txn, Some(1), "a", 1);
+    txn.commit().unwrap();
+
+    // A failed write closes the transaction.
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let result = try_put(col, &mut txn, Some(2), "a", 2);
+    assert!(matches!(result, Err(IsarError::UniqueViolated {})));
+    txn.abort();
+
+    // Updating the object that owns the key is allowed.
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put(col, &mut txn, Some(1), "a", 3);
+    col.delete(&mut txn, 1).unwrap();
+    put(col, &mut txn, Some(2), "a", 2);
+    col.clear(&mut txn).unwrap();
+    txn.commit().unwrap();
+}
+
+fn check_filters(isar: &Arc<IsarInstance>) {
+    let col = &isar.collections[0];
+    let name = property(col, "name");
+    let value = property(col, "value");
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for i in 1..=5 {
+        put(col, &mut txn, Some(i), &format!("name{}", i), i * 10);
+    }
+
+    let between = Filter::long(value, 20, 40).unwrap();
+    assert_eq!(query_ids(col, &mut txn, Some(between)), vec![2, 3, 4]);
+
+    let equal = Filter::string(name, Some("name5"), Some("name5"), true).unwrap();
+    assert_eq!(query_ids(col, &mut txn, Some(equal)), vec![5]);
+
+    let insensitive = Filter::string(name, Some("NAME1"), Some("NAME1"), false).unwrap();
+    assert_eq!(query_ids(col, &mut txn, Some(insensitive)), vec![1]);
+
+    let or = Filter::or(vec![Filter::id(1, 1), Filter::id(5, 5)]);
+    assert_eq!(query_ids(col, &mut txn, Some(or)), vec![1, 5]);
+
+    let not = Filter::not(Filter::long(value, 20, 50).unwrap());
+    assert_eq!(query_ids(col, &mut txn, Some(not)), vec![1]);
+    txn.abort();
+}
+
+fn check_sorts(isar: &Arc<IsarInstance>) {
+    let col = &isar.collections[0];
+    let name = property(col, "name");
+    let value = property(col, "value");
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put(col, &mut txn, Some(1), "b", 2);
+    put(col, &mut txn, Some(2), "a", 3);
+    put(col, &mut txn, Some(3), "c", 1);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort(value, Sort::Descending).unwrap();
+    let ids: Vec<i64> = qb
+        .build()
+        .find_all_vec(&mut txn)
+        .unwrap()
+        .iter()
+        .map(|(id, _)| *id)
+        .collect();
+    assert_eq!(ids, vec![2, 1, 3]);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort(name, Sort::Ascending).unwrap();
+    qb.set_offset(1);
+    qb.set_limit(1);
+    let result = qb.build().find_all_vec(&mut txn).unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].1.read_string(name.offset), Some("b"));
+    txn.abort();
+}
+
+fn check_transactions(isar: &Arc<IsarInstance>) {
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put(col, &mut txn, Some(1), "a", 1);
+    txn.abort();
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert!(col.get(&mut txn, 1).unwrap().is_none());
+    txn.abort();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put(col, &mut txn, Some(1), "a", 1);
+    txn.commit().unwrap();
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert!(col.get(&mut txn, 1).unwrap().is_some());
+    let result = try_put(col, &mut txn, Some(2), "b", 2);
+    assert!(matches!(result, Err(IsarError::WriteTxnRequired {})));
+    txn.abort();
+}
+
+fn check_watchers(isar: &Arc<IsarInstance>) {
+    let col = &isar.collections[0];
+    let col_count = Arc::new(AtomicUsize::new(0));
+    let object_count = Arc::new(AtomicUsize::new(0));
+
+    let counter = col_count.clone();
+    let col_handle = isar.watch_collection(
+        col,
+        Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+    let counter = object_count.clone();
+    let object_handle = isar.watch_object(
+        col,
+        2,
+        Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put(col, &mut txn, Some(1), "a", 1);
+    txn.commit().unwrap();
+    assert_eq!(col_count.load(Ordering::SeqCst), 1);
+    assert_eq!(object_count.load(Ordering::SeqCst), 0);
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put(col, &mut txn, Some(2), "b", 2);
+    txn.abort();
+    assert_eq!(col_count.load(Ordering::SeqCst), 1);
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put(col, &mut txn, Some(2), "b", 2);
+    txn.commit().unwrap();
+    assert_eq!(col_count.load(Ordering::SeqCst), 2);
+    assert_eq!(object_count.load(Ordering::SeqCst), 1);
+
+    let mut txn = isar.begin_txn(true, true).unwrap();
+    put(col, &mut txn, Some(3), "c", 3);
+    txn.commit().unwrap();
+    assert_eq!(col_count.load(Ordering::SeqCst), 2);
+
+    col_handle.stop();
+    object_handle.stop();
+}
@@ -0,0 +1,253 @@
+use crate::collection::IsarCollection;
+use crate::error::Result;
+use crate::instance::IsarInstance;
+use crate::object::json_encode_decode::JsonEncodeDecode;
+use crate::query::Query;
+use crate::txn::IsarTxn;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ChangedObject {
+    pub id: i64,
+    pub old: Map<String, Value>,
+    pub new: Map<String, Value>,
+}
+
+/// Objects of a collection that differ between two instances.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CollectionDelta {
+    pub name: String,
+    pub added: Vec<(i64, Map<String, Value>)>,
+    pub changed: Vec<ChangedObject>,
+    pub removed: Vec<(i64, Map<String, Value>)>,
+}
+
+impl CollectionDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compares all collections of two instances by id and JSON value. Open a backup with
+/// [IsarInstance::open_copy_of] to compare it with another backup or the live database.
+/// Collections without differences are omitted.
+///
+/// Each instance is read in a single transaction so the delta reflects one snapshot of it.
+pub fn diff(old: &IsarInstance, new: &IsarInstance) -> Result<Vec<CollectionDelta>> {
+    let mut names: Vec<&str> = old
+        .collections
+        .iter()
+        .chain(new.collections.iter())
+        .map(|c| c.name.as_str())
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut old_txn = old.begin_txn(false, false)?;
+    let mut new_txn = new.begin_txn(false, false)?;
+    let mut deltas = vec![];
+    for name in names {
+        let old_objects = ObjectReader::new(old, &mut old_txn, name);
+        let new_objects = ObjectReader::new(new, &mut new_txn, name);
+        let delta = diff_objects(name, old_objects, new_objects)?;
+        if !delta.is_empty() {
+            deltas.push(delta);
+        }
+    }
+    old_txn.abort();
+    new_txn.abort();
+    Ok(deltas)
+}
+
+type JsonObject = (i64, Map<String, Value>);
+
+/// Reads the objects of a collection in id order without loading all of them at once.
+struct ObjectReader<'a, 'env> {
+    txn: &'a mut IsarTxn<'env>,
+    collection: Option<&'a IsarCollection>,
+    query: Option<Query>,
+    buffer: VecDeque<JsonObject>,
+    last_id: Option<i64>,
+}
+
+impl<'a, 'env> ObjectReader<'a, 'env> {
+    const CHUNK_SIZE: usize = 1000;
+
+    fn new(instance: &'a IsarInstance, txn: &'a mut IsarTxn<'env>, name: &str) -> Self {
+        let collection = instance.collections.iter().find(|c| c.name == name);
+        ObjectReader {
+            txn,
+            collection,
+            query: collection.map(|c| c.new_query_builder().build()),
+            buffer: VecDeque::new(),
+            last_id: None,
+        }
+    }
+
+    fn read_chunk(&mut self) -> Result<()> {
+        let (collection, query) = match (self.collection, &self.query) {
+            (Some(collection), Some(query)) => (collection, query),
+            _ => return Ok(()),
+        };
+        let chunk_query = match self.last_id {
+            Some(last_id) => query.with_seek(last_id),
+            None => query.clone(),
+        };
+        let buffer = &mut self.buffer;
+        chunk_query.scan_while(self.txn, |id, object| {
+            let json = JsonEncodeDecode::encode(
+                &collection.properties,
+                &collection.embedded_properties,
+                object,
+                false,
+            );
+            buffer.push_back((id, json));
+            buffer.len() < Self::CHUNK_SIZE
+        })?;
+        match self.buffer.back() {
+            Some((id, _)) if self.buffer.len() == Self::CHUNK_SIZE => self.last_id = Some(*id),
+            _ => self.query = None,
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'env> Iterator for ObjectReader<'a, 'env> {
+    type Item = Result<JsonObject>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() {
+            if let Err(e) = self.read_chunk() {
+                self.query = None;
+                return Some(Err(e));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+fn next_object(
+    objects: &mut impl Iterator<Item = Result<JsonObject>>,
+) -> Result<Option<JsonObject>> {
+    objects.next().transpose()
+}
+
+fn diff_objects(
+    name: &str,
+    mut old: impl Iterator<Item = Result<JsonObject>>,
+    mut new: impl Iterator<Item = Result<JsonObject>>,
+) -> Result<CollectionDelta> {
+    let mut delta = CollectionDelta {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let mut old_object = next_object(&mut old)?;
+    let mut new_object = next_object(&mut new)?;
+    loop {
+        let ordering = match (&old_object, &new_object) {
+            (Some((old_id, _)), Some((new_id, _))) => old_id.cmp(new_id),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        match ordering {
+            Ordering::Less => {
+                delta.removed.push(old_object.take().unwrap());
+                old_object = next_object(&mut old)?;
+            }
+            Ordering::Greater => {
+                delta.added.push(new_object.take().unwrap());
+                new_object = next_object(&mut new)?;
+            }
+            Ordering::Equal => {
+                let (id, old_json) = old_object.take().unwrap();
+                let (_, new_json) = new_object.take().unwrap();
+                if old_json != new_json {
+                    delta.changed.push(ChangedObject {
+                        id,
+                        old: old_json,
+                        new: new_json,
+                    });
+                }
+                old_object = next_object(&mut old)?;
+                new_object = next_object(&mut new)?;
+            }
+        }
+    }
+    Ok(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::data_type::DataType;
+    use crate::schema::collection_schema::CollectionSchema;
+    use crate::schema::property_schema::PropertySchema;
+    use crate::schema::Schema;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn object(id: i64, value: i64) -> (i64, Map<String, Value>) {
+        let json = json!({ "value": value });
+        (id, json.as_object().unwrap().clone())
+    }
+
+    fn diff_vecs(old: Vec<JsonObject>, new: Vec<JsonObject>) -> CollectionDelta {
+        diff_objects("col", old.into_iter().map(Ok), new.into_iter().map(Ok)).unwrap()
+    }
+
+    #[test]
+    fn test_diff_objects() {
+        let old = vec![object(1, 1), object(2, 2), object(4, 4)];
+        let new = vec![object(2, 2), object(3, 3), object(4, 5)];
+        let delta = diff_vecs(old, new);
+        assert_eq!(delta.removed, vec![object(1, 1)]);
+        assert_eq!(delta.added, vec![object(3, 3)]);
+        assert_eq!(delta.changed.len(), 1);
+        assert_eq!(delta.changed[0].id, 4);
+        assert_eq!(delta.changed[0].new, object(4, 5).1);
+
+        assert!(diff_vecs(vec![object(1, 1)], vec![object(1, 1)]).is_empty());
+    }
+
+    fn open_instance(name: &str, values: impl Iterator<Item = (i64, i64)>) -> Arc<IsarInstance> {
+        let property = PropertySchema::new(Some("value".to_string()), DataType::Long, None);
+        let col = CollectionSchema::new("col", false, vec![property], vec![], vec![]);
+        let isar = IsarInstance::open_test(name, Schema::new(vec![col]).unwrap());
+        let col = &isar.collections[0];
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        for (id, value) in values {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(col.properties[0].offset, value);
+            col.put(&mut txn, Some(id), ob.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+        isar
+    }
+
+    #[test]
+    fn test_diff_chunks() {
+        let old = open_instance("test_diff_chunks_old", (1..=2500).map(|id| (id, id)));
+        let new = open_instance(
+            "test_diff_chunks_new",
+            (1..=2500)
+                .chain([3000])
+                .filter(|id| *id != 2000)
+                .map(|id| (id, if id == 1500 { 0 } else { id })),
+        );
+
+        let deltas = diff(&old, &new).unwrap();
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].removed, vec![object(2000, 2000)]);
+        assert_eq!(deltas[0].added, vec![object(3000, 3000)]);
+        assert_eq!(deltas[0].changed.len(), 1);
+        assert_eq!(deltas[0].changed[0].id, 1500);
+        assert!(diff(&old, &old).unwrap().is_empty());
+
+        old.close_and_delete();
+        new.close_and_delete();
+    }
+}
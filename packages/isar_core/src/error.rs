@@ -1,3 +1,4 @@
+use crate::object::data_type::DataType;
 use snafu::Snafu;
 
 pub type Result<T> = std::result::Result<T, IsarError>;
@@ -51,12 +52,69 @@ pub enum IsarError {
     #[snafu(display("SchemaError: {}", message))]
     SchemaError { message: String },
 
-    #[snafu(display("SchemaMismatch: The schema of the existing instance does not match."))]
-    SchemaMismatch {},
+    #[snafu(display(
+        "SchemaMismatch: The schema of the existing instance (hash {}) does not match the provided schema (hash {}). Differing collections: {}",
+        existing_hash,
+        new_hash,
+        collections.join(", ")
+    ))]
+    SchemaMismatch {
+        existing_hash: u64,
+        new_hash: u64,
+        collections: Vec<String>,
+    },
+
+    #[snafu(display("InstanceConflict: {}", message))]
+    InstanceConflict { message: String },
 
     #[snafu(display("InstanceMismatch: The transaction is from a different instance."))]
     InstanceMismatch {},
 
+    #[snafu(display("Timeout: The time budget of the transaction is exhausted."))]
+    Timeout {},
+
+    #[snafu(display(
+        "TransactionExpired: The transaction was open for too long and has been aborted."
+    ))]
+    TransactionExpired {},
+
+    #[snafu(display(
+        "ResultLimitExceeded: The query result exceeds the limit ({} objects, {} bytes).",
+        rows,
+        bytes
+    ))]
+    ResultLimitExceeded { rows: usize, bytes: usize },
+
+    #[snafu(display(
+        "AppendOnly: Objects of an append-only collection cannot be updated or deleted."
+    ))]
+    AppendOnly {},
+
+    #[snafu(display("ReadOnly: The instance does not accept write transactions."))]
+    ReadOnly {},
+
+    #[snafu(display(
+        "PlanChanged: Expected the query plan \"{}\" but got \"{}\".",
+        expected,
+        actual
+    ))]
+    PlanChanged { expected: String, actual: String },
+
+    #[snafu(display("ValidationFailed ({}): {}", property, message))]
+    ValidationFailed { property: String, message: String },
+
+    #[snafu(display(
+        "PropertyMismatch: Property \"{}\" has type {:?} but was used as {:?}.",
+        property,
+        actual,
+        expected
+    ))]
+    PropertyMismatch {
+        property: String,
+        expected: DataType,
+        actual: DataType,
+    },
+
     #[snafu(display("MdbxError ({}): {}", code, message))]
     MdbxError { code: i32, message: String },
 }
@@ -0,0 +1,82 @@
+//! Simulated storage faults for durability tests. Faults are armed per thread and trigger once.
+
+use crate::error::{IsarError, Result};
+use crate::instance::IsarInstance;
+use crate::mdbx::mdbx_result;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Fault {
+    /// The next write fails with an I/O error.
+    IoError,
+    /// The next write fails because the database is full.
+    DiskFull,
+    /// The next commit is lost as if the process crashed before it became durable.
+    CrashAtCommit,
+    /// The next write fails with a transient error.
+    Busy,
+}
+
+thread_local! {
+    static ARMED: RefCell<Vec<(Fault, u32)>> = const { RefCell::new(vec![]) };
+}
+
+/// Arms a fault that triggers after `skip` operations of its kind succeeded.
+pub fn inject(fault: Fault, skip: u32) {
+    ARMED.with(|armed| armed.borrow_mut().push((fault, skip)));
+}
+
+pub fn clear() {
+    ARMED.with(|armed| armed.borrow_mut().clear());
+}
+
+fn trigger(matches: impl Fn(Fault) -> bool) -> Option<Fault> {
+    ARMED.with(|armed| {
+        let mut armed = armed.borrow_mut();
+        let index = armed.iter().position(|(fault, _)| matches(*fault))?;
+        if armed[index].1 == 0 {
+            Some(armed.remove(index).0)
+        } else {
+            armed[index].1 -= 1;
+            None
+        }
+    })
+}
+
+pub(crate) fn check_write() -> Result<()> {
+    match trigger(|f| f != Fault::CrashAtCommit) {
+        Some(Fault::DiskFull) => Err(IsarError::DbFull {}),
+        Some(Fault::Busy) => mdbx_result(ffi::MDBX_BUSY),
+        Some(_) => mdbx_result(libc::EIO),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn check_commit() -> bool {
+    trigger(|f| f == Fault::CrashAtCommit).is_some()
+}
+
+/// Closes the instance and opens it again with `open(name, dir)`. Panics if the instance is
+/// still referenced elsewhere.
+pub fn reopen<F>(isar: Arc<IsarInstance>, open: F) -> Arc<IsarInstance>
+where
+    F: FnOnce(&str, &str) -> Arc<IsarInstance>,
+{
+    let name = isar.name.clone();
+    let dir = isar.dir.clone();
+    assert!(isar.close(), "Instance is still in use.");
+    open(&name, &dir)
+}
+
+/// Panics if the databases of the instance are inconsistent.
+pub fn assert_invariants(isar: &IsarInstance) {
+    let mut txn = isar.begin_txn(false, true).unwrap();
+    isar.verify(&mut txn).unwrap();
+    for col in &isar.collections {
+        let count = col.count(&mut txn).unwrap();
+        let queried = col.new_query_builder().build().count(&mut txn).unwrap();
+        assert_eq!(count, queried as u64, "Count mismatch in {}", col.name);
+    }
+    txn.abort();
+}
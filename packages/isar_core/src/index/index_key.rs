@@ -1,5 +1,7 @@
 use crate::index::IsarIndex;
 use crate::mdbx::Key;
+use crate::object::isar_object::IsarObject;
+use crate::query::string_normalizer::StringNormalizer;
 use std::borrow::Cow;
 use std::cmp;
 use std::cmp::Ordering;
@@ -86,6 +88,22 @@ impl IndexKey {
         }
     }
 
+    /// Adds a string to a key of an index that folds diacritics.
+    pub fn add_folded_string(&mut self, value: Option<&str>, case_sensitive: bool) {
+        let value = value.map(Self::fold_diacritics);
+        self.add_string(value.as_deref(), case_sensitive)
+    }
+
+    pub fn add_folded_string_hash(&mut self, value: Option<&str>, case_sensitive: bool) {
+        let value = value.map(Self::fold_diacritics);
+        let hash = IsarObject::hash_string(value.as_deref(), case_sensitive, 0);
+        self.add_hash(hash);
+    }
+
+    pub(crate) fn fold_diacritics(value: &str) -> Cow<'_, str> {
+        StringNormalizer::new(true, false, false, true).normalize(value)
+    }
+
     pub fn add_hash(&mut self, value: u64) {
         let bytes: [u8; 8] = value.to_be_bytes();
         self.bytes.extend_from_slice(&bytes);
@@ -35,7 +35,12 @@ impl<'a> IndexKeyBuilder<'a> {
         for index_property in self.properties {
             let property = &index_property.property;
 
-            if index_property.index_type == IndexType::Hash {
+            if index_property.index_type == IndexType::Hash && index_property.fold_diacritics {
+                let value = index_property.fold(object.read_string(property.offset));
+                let hash =
+                    IsarObject::hash_string(value.as_deref(), index_property.case_sensitive, 0);
+                key.add_hash(hash);
+            } else if index_property.index_type == IndexType::Hash {
                 let hash = object.hash_property(
                     property.offset,
                     property.data_type,
@@ -53,10 +58,10 @@ impl<'a> IndexKeyBuilder<'a> {
                     DataType::Float => key.add_float(object.read_float(property.offset)),
                     DataType::Long => key.add_long(object.read_long(property.offset)),
                     DataType::Double => key.add_double(object.read_double(property.offset)),
-                    DataType::String => key.add_string(
-                        object.read_string(property.offset),
-                        index_property.case_sensitive,
-                    ),
+                    DataType::String => {
+                        let value = index_property.fold(object.read_string(property.offset));
+                        key.add_string(value.as_deref(), index_property.case_sensitive)
+                    }
                     _ => unreachable!(),
                 }
             }
@@ -123,6 +128,8 @@ impl<'a> IndexKeyBuilder<'a> {
             DataType::StringList => {
                 for value in object.read_string_list(property.offset).unwrap() {
                     key.truncate(0);
+                    let value = index_property.fold(value);
+                    let value = value.as_deref();
                     if index_property.index_type == IndexType::HashElements {
                         let hash = IsarObject::hash_string(value, index_property.case_sensitive, 0);
                         key.add_hash(hash);
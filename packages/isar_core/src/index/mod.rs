@@ -2,12 +2,17 @@ use crate::cursor::IsarCursors;
 use crate::error::{IsarError, Result};
 use crate::index::index_key::IndexKey;
 use crate::index::index_key_builder::IndexKeyBuilder;
+use crate::mdbx::cursor::Cursor;
 use crate::mdbx::db::Db;
+use crate::mdbx::{Key, KeyVal};
 use crate::object::id::{BytesToId, IdToBytes};
 use crate::object::isar_object::IsarObject;
 use crate::object::property::Property;
+use crate::query::DeadlineCheck;
 use crate::schema::index_schema::IndexType;
+use crate::stats::IndexConsistency;
 use intmap::IntMap;
+use std::borrow::Cow;
 use xxhash_rust::xxh3::xxh3_64;
 
 pub mod index_key;
@@ -18,14 +23,30 @@ pub struct IndexProperty {
     pub property: Property,
     pub index_type: IndexType,
     pub case_sensitive: bool,
+    pub fold_diacritics: bool,
 }
 
 impl IndexProperty {
-    pub(crate) fn new(property: Property, index_type: IndexType, case_sensitive: bool) -> Self {
+    pub(crate) fn new(
+        property: Property,
+        index_type: IndexType,
+        case_sensitive: bool,
+        fold_diacritics: bool,
+    ) -> Self {
         IndexProperty {
             property,
             index_type,
             case_sensitive,
+            fold_diacritics,
+        }
+    }
+
+    /// Applies the diacritic folding of the index. Case is handled by the key.
+    pub(crate) fn fold<'a>(&self, value: Option<&'a str>) -> Option<Cow<'a, str>> {
+        if self.fold_diacritics {
+            value.map(|value| IndexKey::fold_diacritics(value))
+        } else {
+            value.map(Cow::Borrowed)
         }
     }
 
@@ -109,6 +130,39 @@ impl IsarIndex {
         Ok(())
     }
 
+    /// Checks without writing whether `create_for_object` would succeed. Returns whether other
+    /// objects would be replaced.
+    pub(crate) fn verify_for_object(
+        &self,
+        cursors: &IsarCursors,
+        id: Option<i64>,
+        object: IsarObject,
+    ) -> Result<bool> {
+        if !self.unique {
+            return Ok(false);
+        }
+        let mut cursor = cursors.get_cursor(self.db)?;
+        let key_builder = IndexKeyBuilder::new(&self.properties);
+        let mut keys: Vec<IndexKey> = vec![];
+        let mut replaces = false;
+        key_builder.create_keys(object, |key| {
+            if keys.contains(key) {
+                return Err(IsarError::UniqueViolated {});
+            }
+            if let Some((_, existing_id_bytes)) = cursor.move_to(key)? {
+                if Some(existing_id_bytes.to_id()) != id {
+                    if !self.replace {
+                        return Err(IsarError::UniqueViolated {});
+                    }
+                    replaces = true;
+                }
+            }
+            keys.push(key.clone());
+            Ok(true)
+        })?;
+        Ok(replaces)
+    }
+
     pub fn delete_for_object(
         &self,
         cursors: &IsarCursors,
@@ -118,12 +172,7 @@ impl IsarIndex {
         let mut cursor = cursors.get_cursor(self.db)?;
         let key_builder = IndexKeyBuilder::new(&self.properties);
         key_builder.create_keys(object, |key| {
-            let entry = if self.unique {
-                cursor.move_to(key)?
-            } else {
-                cursor.move_to_key_val(key, &id.to_id_bytes())?
-            };
-            if entry.is_some() {
+            if Self::move_to_entry(&mut cursor, key, &id.to_id_bytes())?.is_some() {
                 cursor.delete_current()?;
             }
             Ok(true)
@@ -164,6 +213,96 @@ impl IsarIndex {
         Ok(result)
     }
 
+    /// Moves to the entry of `key` that belongs to the object with `id_bytes`. Unique indexes
+    /// are looked up the same way so an entry of another object is never matched.
+    fn move_to_entry<'txn>(
+        cursor: &mut Cursor<'txn>,
+        key: &IndexKey,
+        id_bytes: &[u8],
+    ) -> Result<Option<KeyVal<'txn>>> {
+        cursor.move_to_key_val(key, id_bytes)
+    }
+
+    /// Removes entries of objects that no longer exist in `object_db`. Stops scanning once
+    /// `deadline` expired.
+    pub fn sweep_orphans(
+        &self,
+        cursors: &IsarCursors,
+        object_db: Db,
+        deadline: &mut DeadlineCheck,
+    ) -> Result<u64> {
+        let mut object_cursor = cursors.get_cursor(object_db)?;
+        let mut cursor = cursors.get_cursor(self.db)?;
+        let mut orphans = vec![];
+        cursor.iter_all(false, true, |_, key, id_bytes| {
+            if object_cursor.move_to(&id_bytes.to_id())?.is_none() {
+                orphans.push((IndexKey::from_bytes(key.to_vec()), id_bytes.to_vec()));
+            }
+            Ok(!deadline.expired())
+        })?;
+
+        for (key, id_bytes) in &orphans {
+            if Self::move_to_entry(&mut cursor, key, id_bytes)?.is_some() {
+                cursor.delete_current()?;
+            }
+        }
+        Ok(orphans.len() as u64)
+    }
+
+    /// Compares the index entries with the keys of every `sample_every`th object and reports
+    /// objects without entries and entries without objects.
+    pub fn check_consistency(
+        &self,
+        cursors: &IsarCursors,
+        object_db: Db,
+        sample_every: u64,
+    ) -> Result<IndexConsistency> {
+        let mut report = IndexConsistency {
+            index: self.name.clone(),
+            entries: cursors.db_stat(self.db)?.0,
+            ..Default::default()
+        };
+
+        let mut cursor = cursors.get_cursor(self.db)?;
+        let mut object_cursor = cursors.get_cursor(object_db)?;
+        let key_builder = IndexKeyBuilder::new(&self.properties);
+        let mut position = 0;
+        object_cursor.iter_all(false, true, |_, id_bytes, bytes| {
+            let mut keys = vec![];
+            key_builder.create_keys(IsarObject::from_bytes(bytes), |key| {
+                keys.push(key.as_bytes().to_vec());
+                Ok(true)
+            })?;
+            // list elements with equal values share a single entry
+            keys.sort_unstable();
+            keys.dedup();
+            report.expected_entries += keys.len() as u64;
+
+            if position % sample_every.max(1) == 0 {
+                report.sampled_objects += 1;
+                for key in keys {
+                    let key = IndexKey::from_bytes(key);
+                    if Self::move_to_entry(&mut cursor, &key, id_bytes)?.is_none() {
+                        report.missing_entries.push(id_bytes.to_id());
+                        break;
+                    }
+                }
+            }
+            position += 1;
+            Ok(true)
+        })?;
+
+        let mut object_cursor = cursors.get_cursor(object_db)?;
+        cursor.iter_all(false, true, |_, _, id_bytes| {
+            let id = id_bytes.to_id();
+            if object_cursor.move_to(&id)?.is_none() {
+                report.orphaned_entries.push(id);
+            }
+            Ok(true)
+        })?;
+        Ok(report)
+    }
+
     pub fn get_size(&self, cursors: &IsarCursors) -> Result<u64> {
         Ok(cursors.db_stat(self.db)?.1)
     }
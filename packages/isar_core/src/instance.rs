@@ -1,22 +1,32 @@
+use crate::blob::BlobStore;
+use crate::clock::{Clock, SystemClock};
 use crate::collection::IsarCollection;
 use crate::error::*;
 use crate::mdbx::env::Env;
-use crate::query::Query;
+use crate::mdbx::is_transient;
+use crate::query::{DeadlineCheck, Query, ResultLimit};
+use crate::schema::db_name;
 use crate::schema::schema_manager::SchemaManager;
 use crate::schema::Schema;
+use crate::stats::{
+    FragmentationReport, MigrationRecord, PendingWrite, SweepReport, WritePressure,
+};
 use crate::txn::IsarTxn;
 use crate::watch::change_set::ChangeSet;
 use crate::watch::isar_watchers::{IsarWatchers, WatcherModifier};
 use crate::watch::watcher::WatcherCallback;
 use crate::watch::WatchHandle;
+use crate::watchdog::{StaleTxnCallback, TxnWatchdog};
 use crossbeam_channel::{unbounded, Sender};
 use intmap::IntMap;
 use once_cell::sync::Lazy;
+use serde_json::{Map, Value};
 use std::fs::remove_file;
 use std::fs::{self, metadata};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use xxhash_rust::xxh3::xxh3_64;
 
 static INSTANCES: Lazy<RwLock<IntMap<Arc<IsarInstance>>>> =
@@ -24,51 +34,126 @@ static INSTANCES: Lazy<RwLock<IntMap<Arc<IsarInstance>>>> =
 
 static WATCHER_ID: AtomicU64 = AtomicU64::new(0);
 
+static COPY_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Called while an instance is opened after the schema of a collection has been migrated.
+pub type MigrationListener = Box<dyn Fn(&MigrationRecord)>;
+
 pub struct CompactCondition {
     pub min_file_size: u64,
     pub min_bytes: u64,
     pub min_ratio: f64,
+    /// Directory for the compacted copy. Defaults to the directory of the database.
+    pub temp_dir: Option<String>,
+}
+
+/// Operations that fail with a transient error are retried. The backoff doubles after every
+/// attempt up to `max_backoff`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct MaintenanceReport {
+    pub stale_readers: u32,
+    pub swept: SweepReport,
+    pub collected_blobs: usize,
+    pub synced: bool,
+    pub completed: bool,
 }
 
 pub struct IsarInstance {
     pub name: String,
     pub dir: String,
     pub collections: Vec<IsarCollection>,
+    pub blobs: BlobStore,
     pub(crate) instance_id: u64,
     pub(crate) schema_hash: u64,
+    collection_hashes: Vec<(String, u64)>,
 
     env: Env,
     watchers: Mutex<IsarWatchers>,
     watcher_modifier_sender: Sender<WatcherModifier>,
+    result_limit: RwLock<Option<ResultLimit>>,
+    retry_policy: RwLock<Option<RetryPolicy>>,
+    retries: AtomicU64,
+    read_only: AtomicBool,
+    clock: RwLock<Arc<dyn Clock>>,
+    pending_writes: Arc<AtomicU32>,
+    txn_watchdog: Arc<TxnWatchdog>,
+    /// Throwaway copies are deleted from disk when they are closed.
+    delete_on_close: AtomicBool,
 }
 
 impl IsarInstance {
+    #[allow(clippy::too_many_arguments)]
     pub fn open(
         name: &str,
         dir: Option<&str>,
         mut schema: Schema,
         max_size_mib: usize,
+        max_readers: u32,
         relaxed_durability: bool,
         compact_condition: Option<CompactCondition>,
+        migration_listener: Option<MigrationListener>,
     ) -> Result<Arc<Self>> {
         let mut lock = INSTANCES.write().unwrap();
         let instance_id = xxh3_64(name.as_bytes());
         if let Some(instance) = lock.get(instance_id) {
-            if instance.schema_hash == schema.hash() {
+            if let Some(dir) = dir {
+                if Self::canonical_isar_path(name, dir)
+                    != Self::canonical_isar_path(&instance.name, &instance.dir)
+                {
+                    return Err(IsarError::InstanceConflict {
+                        message: format!(
+                            "An instance named \"{}\" is already open in a different directory.",
+                            name
+                        ),
+                    });
+                }
+            }
+            let collection_hashes = schema.collection_hashes();
+            let schema_hash = Schema::hash_collections(&collection_hashes);
+            if instance.schema_hash == schema_hash {
                 Ok(instance.clone())
             } else {
-                Err(IsarError::SchemaMismatch {})
+                Err(IsarError::SchemaMismatch {
+                    existing_hash: instance.schema_hash,
+                    new_hash: schema_hash,
+                    collections: Schema::diff_collections(
+                        &instance.collection_hashes,
+                        &collection_hashes,
+                    ),
+                })
             }
         } else {
             if let Some(dir) = dir {
+                let path = Self::canonical_isar_path(name, dir);
+                let conflict = lock
+                    .values()
+                    .find(|i| Self::canonical_isar_path(&i.name, &i.dir) == path);
+                if let Some(conflict) = conflict {
+                    return Err(IsarError::InstanceConflict {
+                        message: format!(
+                            "The file of instance \"{}\" is already open as instance \"{}\".",
+                            name, conflict.name
+                        ),
+                    });
+                }
+
                 let new_instance = Self::open_internal(
                     name,
                     dir,
                     instance_id,
                     schema,
                     max_size_mib,
+                    max_readers,
                     relaxed_durability,
                     compact_condition,
+                    migration_listener.as_ref(),
                 )?;
                 let new_instance = Arc::new(new_instance);
                 lock.insert(instance_id, new_instance.clone());
@@ -81,6 +166,55 @@ impl IsarInstance {
         }
     }
 
+    /// Opens a copy of the database file at `path` in the temp directory. Changes never reach
+    /// the original file and the copy is deleted when it is closed.
+    ///
+    /// Instances that are open in this process are copied as a consistent snapshot. Other files
+    /// are copied as they are, so the copy may be inconsistent if another process writes to the
+    /// file at the same time.
+    pub fn open_copy_of(path: &str, schema: Schema, max_size_mib: usize) -> Result<Arc<Self>> {
+        let name = format!(
+            "copy_{}_{}",
+            std::process::id(),
+            COPY_ID.fetch_add(1, Ordering::SeqCst)
+        );
+        let dir = std::env::temp_dir();
+        let dir = dir.to_str().ok_or(IsarError::PathError {})?;
+        let copy_path = Self::get_isar_path(&name, dir);
+
+        let source = fs::canonicalize(path).map_err(|_| IsarError::PathError {})?;
+        let open_instance = INSTANCES
+            .read()
+            .unwrap()
+            .values()
+            .find(|i| Self::canonical_isar_path(&i.name, &i.dir) == source)
+            .cloned();
+        if let Some(instance) = open_instance {
+            // Copying through MDBX gives a consistent snapshot of an instance in use.
+            instance.copy_to_file(&copy_path)?;
+        } else {
+            fs::copy(&source, &copy_path).map_err(|_| IsarError::PathError {})?;
+        }
+
+        match Self::open(&name, Some(dir), schema, max_size_mib, 0, true, None, None) {
+            Ok(instance) => {
+                instance.delete_on_close.store(true, Ordering::SeqCst);
+                Ok(instance)
+            }
+            Err(e) => {
+                Self::remove_files(copy_path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Removes the database file and its lock file.
+    fn remove_files(mut path: String) {
+        let _ = remove_file(&path);
+        path.push_str(".lock");
+        let _ = remove_file(&path);
+    }
+
     fn get_isar_path(name: &str, dir: &str) -> String {
         let mut file_name = name.to_string();
         file_name.push_str(".isar");
@@ -90,6 +224,15 @@ impl IsarInstance {
         path_buf.as_path().to_str().unwrap().to_string()
     }
 
+    fn canonical_isar_path(name: &str, dir: &str) -> PathBuf {
+        let path = PathBuf::from(Self::get_isar_path(name, dir));
+        if let Ok(path) = fs::canonicalize(&path) {
+            return path;
+        }
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir));
+        dir.join(path.file_name().unwrap())
+    }
+
     fn move_old_database(name: &str, dir: &str, new_path: &str) {
         let mut old_path_buf = PathBuf::from(dir);
         old_path_buf.push(name);
@@ -104,24 +247,28 @@ impl IsarInstance {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn open_internal(
         name: &str,
         dir: &str,
         instance_id: u64,
         mut schema: Schema,
         max_size_mib: usize,
+        max_readers: u32,
         relaxed_durability: bool,
         compact_condition: Option<CompactCondition>,
+        migration_listener: Option<&MigrationListener>,
     ) -> Result<Self> {
         let isar_file = Self::get_isar_path(name, dir);
 
         Self::move_old_database(name, dir, &isar_file);
 
-        let db_count = schema.count_dbs() as u64 + 3;
+        let db_count = schema.count_dbs() as u64 + 5;
         let env = Env::create(
             &isar_file,
             db_count,
             max_size_mib.max(1),
+            max_readers,
             relaxed_durability,
         )
         .map_err(|e| IsarError::EnvError { error: Box::new(e) })?;
@@ -136,6 +283,11 @@ impl IsarInstance {
             let col = manager.open_collection(&txn, col_schema.clone(), &schema)?;
             collections.push(col);
             txn.commit()?;
+            for migration in manager.migrations.drain(..) {
+                if let Some(listener) = migration_listener {
+                    listener(&migration);
+                }
+            }
         }
 
         if !manager.schemas.is_empty() {
@@ -146,15 +298,26 @@ impl IsarInstance {
 
         let (tx, rx) = unbounded();
 
+        let collection_hashes = schema.collection_hashes();
         let instance = IsarInstance {
             env,
             name: name.to_string(),
             dir: dir.to_string(),
             collections,
+            blobs: BlobStore::new(instance_id, manager.blob_db),
             instance_id,
-            schema_hash: schema.hash(),
+            schema_hash: Schema::hash_collections(&collection_hashes),
+            collection_hashes,
             watchers: Mutex::new(IsarWatchers::new(rx)),
             watcher_modifier_sender: tx,
+            result_limit: RwLock::new(None),
+            retry_policy: RwLock::new(None),
+            retries: AtomicU64::new(0),
+            read_only: AtomicBool::new(false),
+            clock: RwLock::new(Arc::new(SystemClock::default())),
+            pending_writes: Arc::new(AtomicU32::new(0)),
+            txn_watchdog: Arc::new(TxnWatchdog::new()),
+            delete_on_close: AtomicBool::new(false),
         };
 
         if let Some(compact_condition) = compact_condition {
@@ -168,8 +331,10 @@ impl IsarInstance {
                     instance_id,
                     schema,
                     max_size_mib,
+                    max_readers,
                     relaxed_durability,
                     None,
+                    None,
                 )
             }
         } else {
@@ -179,30 +344,33 @@ impl IsarInstance {
 
     fn compact(self, compact_condition: CompactCondition) -> Result<Option<Self>> {
         let mut txn = self.begin_txn(false, true)?;
-        let instance_size = self.get_size(&mut txn, true, true)?;
+        let report = self.get_fragmentation_report(&mut txn, false)?;
         txn.abort();
 
         let isar_file = Self::get_isar_path(&self.name, &self.dir);
-        let file_size = metadata(&isar_file)
-            .map_err(|_| IsarError::PathError {})?
-            .len();
-
-        let compact_bytes = file_size.saturating_sub(instance_size);
-        let compact_ratio = if instance_size == 0 {
-            f64::INFINITY
-        } else {
-            (file_size as f64) / (instance_size as f64)
-        };
-        let should_compact = file_size >= compact_condition.min_file_size
-            && compact_bytes >= compact_condition.min_bytes
-            && compact_ratio >= compact_condition.min_ratio;
-
-        if should_compact {
+        if report.should_compact(&compact_condition) {
             let compact_file = format!("{}.compact", &isar_file);
-            self.copy_to_file(&compact_file)?;
+            let temp_file = if let Some(temp_dir) = &compact_condition.temp_dir {
+                format!("{}.compact", Self::get_isar_path(&self.name, temp_dir))
+            } else {
+                compact_file.clone()
+            };
+            // Leftovers of an interrupted compaction would make the copy fail.
+            let _ = remove_file(&temp_file);
+            let _ = remove_file(&compact_file);
+
+            if let Err(e) = self.copy_to_file(&temp_file) {
+                let _ = remove_file(&temp_file);
+                return Err(e);
+            }
             drop(self);
 
-            let _ = fs::rename(&compact_file, &isar_file);
+            // The temp dir may be on a different file system. Copying over the closed database
+            // avoids a second full copy in the database directory.
+            let swapped = fs::rename(&temp_file, &isar_file)
+                .or_else(|_| fs::copy(&temp_file, &isar_file).map(|_| ()));
+            let _ = remove_file(&temp_file);
+            swapped.map_err(|_| IsarError::PathError {})?;
             Ok(None)
         } else {
             Ok(Some(self))
@@ -214,7 +382,38 @@ impl IsarInstance {
         INSTANCES.read().unwrap().get(instance_id).cloned()
     }
 
+    /// Reads and queries of a write transaction observe its own uncommitted changes.
     pub fn begin_txn(&self, write: bool, silent: bool) -> Result<IsarTxn> {
+        self.retry(|| self.begin_txn_internal(write, silent))
+    }
+
+    /// Runs `job` in a new transaction that is committed afterwards. If the transaction fails
+    /// with a transient error, the whole transaction including `job` is repeated.
+    pub fn run_txn<T, F>(&self, write: bool, silent: bool, mut job: F) -> Result<T>
+    where
+        F: FnMut(&mut IsarTxn) -> Result<T>,
+    {
+        self.retry(|| {
+            let mut txn = self.begin_txn_internal(write, silent)?;
+            let result = job(&mut txn)?;
+            if write {
+                txn.commit()?;
+            } else {
+                txn.abort();
+            }
+            Ok(result)
+        })
+    }
+
+    fn begin_txn_internal(&self, write: bool, silent: bool) -> Result<IsarTxn<'_>> {
+        if write && self.is_read_only() {
+            return Err(IsarError::ReadOnly {});
+        }
+        let pending_write = if write {
+            Some(PendingWrite::new(&self.pending_writes))
+        } else {
+            None
+        };
         let change_set = if write && !silent {
             let mut watchers_lock = self.watchers.lock().unwrap();
             watchers_lock.sync();
@@ -225,7 +424,102 @@ impl IsarInstance {
         };
 
         let txn = self.env.txn(write)?;
-        IsarTxn::new(self.instance_id, txn, write, change_set)
+        let lease = if write {
+            None
+        } else {
+            self.txn_watchdog.lease()
+        };
+        let auto_increments = if write {
+            self.collections
+                .iter()
+                .map(|c| c.auto_increment.clone())
+                .collect()
+        } else {
+            vec![]
+        };
+        let result_limit = self.result_limit.read().unwrap().clone();
+        let clock = self.clock.read().unwrap().clone();
+        IsarTxn::new(
+            self.instance_id,
+            txn,
+            write,
+            change_set,
+            result_limit,
+            clock,
+            pending_write,
+            lease,
+            auto_increments,
+        )
+    }
+
+    fn retry<T>(&self, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+        let retry_policy = *self.retry_policy.read().unwrap();
+        let mut attempt = 1;
+        let mut backoff =
+            retry_policy.map_or(Duration::ZERO, |p| p.initial_backoff.min(p.max_backoff));
+        loop {
+            let result = operation();
+            match (&result, retry_policy) {
+                (Err(IsarError::MdbxError { code, .. }), Some(policy))
+                    if is_transient(*code) && attempt < policy.max_attempts =>
+                {
+                    self.retries.fetch_add(1, Ordering::Relaxed);
+                    std::thread::sleep(backoff);
+                    attempt += 1;
+                    backoff = backoff.saturating_mul(2).min(policy.max_backoff);
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    /// Write transactions fail with [IsarError::ReadOnly] while the instance is read-only.
+    /// Running write transactions are not affected.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::SeqCst);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    pub fn set_retry_policy(&self, retry_policy: Option<RetryPolicy>) {
+        *self.retry_policy.write().unwrap() = retry_policy;
+    }
+
+    /// Number of retries performed since the instance was opened.
+    pub fn get_retry_count(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Expires read transactions that are open longer than `max_age` and reports their age to
+    /// `callback`. The next access of an expired transaction releases its snapshot and fails.
+    pub fn start_txn_watchdog(&self, max_age: Duration, callback: StaleTxnCallback) {
+        self.txn_watchdog.start(max_age, callback);
+    }
+
+    pub fn stop_txn_watchdog(&self) {
+        self.txn_watchdog.stop();
+    }
+
+    pub fn get_write_pressure(&self) -> Result<WritePressure> {
+        let (unsynced_bytes, used_bytes, max_bytes) = self.env.space_info()?;
+        Ok(WritePressure {
+            pending_write_txns: self.pending_writes.load(Ordering::Relaxed),
+            unsynced_bytes,
+            used_bytes,
+            max_bytes,
+        })
+    }
+
+    /// Replaces the time source used for timestamps in transactions started afterwards.
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        *self.clock.write().unwrap() = clock;
+    }
+
+    /// Limits the result size of queries without limit in transactions started afterwards.
+    pub fn set_result_limit(&self, result_limit: Option<ResultLimit>) {
+        *self.result_limit.write().unwrap() = result_limit;
     }
 
     pub fn get_size(
@@ -243,10 +537,117 @@ impl IsarInstance {
         Ok(size)
     }
 
+    /// Compares the file size with the space used by collections. Per collection details require
+    /// a scan of all objects and are only collected when `include_collections` is set.
+    pub fn get_fragmentation_report(
+        &self,
+        txn: &mut IsarTxn,
+        include_collections: bool,
+    ) -> Result<FragmentationReport> {
+        let used_bytes = self.get_size(txn, true, true)?;
+        let isar_file = Self::get_isar_path(&self.name, &self.dir);
+        let file_size = metadata(&isar_file)
+            .map_err(|_| IsarError::PathError {})?
+            .len();
+
+        let mut collections = vec![];
+        if include_collections {
+            for col in &self.collections {
+                collections.push(col.get_fragmentation(txn)?);
+            }
+        }
+
+        Ok(FragmentationReport {
+            file_size,
+            used_bytes,
+            collections,
+        })
+    }
+
+    pub fn check_readers(&self) -> Result<u32> {
+        self.env.check_readers()
+    }
+
+    /// Runs housekeeping steps until the budget is used up. Steps that write are skipped while
+    /// the instance is read-only. Work that does not fit into the budget is reported as not
+    /// completed so it can be resumed later.
+    pub fn perform_maintenance(&self, budget: Duration) -> Result<MaintenanceReport> {
+        let mut deadline = DeadlineCheck::new(Some(Instant::now() + budget));
+        let mut report = MaintenanceReport::default();
+
+        if deadline.expired_now() {
+            return Ok(report);
+        }
+        report.stale_readers = self.env.check_readers()?;
+
+        let writable = !self.is_read_only();
+        if writable && !deadline.expired_now() {
+            let mut txn = self.begin_txn(true, true)?;
+            for col in &self.collections {
+                report
+                    .swept
+                    .add(col.sweep_orphans_until(&mut txn, &mut deadline)?);
+            }
+            if !deadline.expired_now() {
+                report.collected_blobs =
+                    self.blobs.collect_garbage_until(&mut txn, &mut deadline)?;
+            }
+            txn.commit()?;
+        }
+
+        if deadline.expired_now() {
+            return Ok(report);
+        }
+        report.synced = self.env.sync(false, true)?;
+
+        report.completed = writable && report.synced;
+        Ok(report)
+    }
+
+    /// Forces all committed transactions to be written and synced to disk. Instances opened with
+    /// relaxed durability can use this at points where data must not be lost.
+    pub fn flush(&self) -> Result<()> {
+        self.env.sync(true, false)?;
+        Ok(())
+    }
+
+    pub fn sweep_orphans(&self, txn: &mut IsarTxn) -> Result<SweepReport> {
+        let mut report = SweepReport::default();
+        for col in &self.collections {
+            report.add(col.sweep_orphans(txn)?);
+        }
+        Ok(report)
+    }
+
+    pub(crate) fn collection_hash(&self, name: &str) -> Option<u64> {
+        self.collection_hashes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, hash)| *hash)
+    }
+
+    /// Copies a consistent snapshot of the database. Writes can continue during the copy.
     pub fn copy_to_file(&self, path: &str) -> Result<()> {
         self.env.copy(path)
     }
 
+    /// Exports all collections as seen by `txn`. Using a read transaction exports a consistent
+    /// snapshot without blocking concurrent writes.
+    pub fn export_json(
+        &self,
+        txn: &mut IsarTxn,
+        id_name: Option<&str>,
+        primitive_null: bool,
+    ) -> Result<Value> {
+        let mut collections = Map::new();
+        for col in &self.collections {
+            let query = col.new_query_builder().build();
+            let json = query.export_json(txn, col, id_name, primitive_null)?;
+            collections.insert(col.name.clone(), json);
+        }
+        Ok(Value::Object(collections))
+    }
+
     fn new_watcher(&self, start: WatcherModifier, stop: WatcherModifier) -> WatchHandle {
         self.watcher_modifier_sender.try_send(start).unwrap();
 
@@ -321,12 +722,10 @@ impl IsarInstance {
             if Arc::strong_count(&self) == 2 {
                 lock.remove(self.instance_id);
 
-                if delete_from_disk {
-                    let mut path = Self::get_isar_path(&self.name, &self.dir);
+                if delete_from_disk || self.delete_on_close.load(Ordering::SeqCst) {
+                    let path = Self::get_isar_path(&self.name, &self.dir);
                     drop(self);
-                    let _ = remove_file(&path);
-                    path.push_str(".lock");
-                    let _ = remove_file(&path);
+                    Self::remove_files(path);
                 }
                 return true;
             }
@@ -344,16 +743,18 @@ impl IsarInstance {
 
     pub fn verify(&self, txn: &mut IsarTxn) -> Result<()> {
         let mut db_names = vec![];
-        db_names.push("_info".to_string());
+        db_names.push(db_name::INFO.to_string());
+        db_names.push(db_name::BLOBS.to_string());
+        db_names.push(db_name::SEQUENCES.to_string());
         for col in &self.collections {
             db_names.push(col.name.clone());
             for index in &col.indexes {
-                db_names.push(format!("_i_{}_{}", col.name, index.name));
+                db_names.push(db_name::index(&col.name, &index.name));
             }
 
             for link in &col.links {
-                db_names.push(format!("_l_{}_{}", col.name, link.name));
-                db_names.push(format!("_b_{}_{}", col.name, link.name));
+                db_names.push(db_name::link(&col.name, &link.name));
+                db_names.push(db_name::backlink(&col.name, &link.name));
             }
         }
         let mut actual_db_names = txn.db_names()?;
@@ -370,3 +771,12 @@ impl IsarInstance {
         }
     }
 }
+
+#[cfg(test)]
+impl IsarInstance {
+    /// Opens an instance in the temp directory for unit tests.
+    pub(crate) fn open_test(name: &str, schema: Schema) -> Arc<Self> {
+        let dir = std::env::temp_dir();
+        Self::open(name, dir.to_str(), schema, 10, 0, false, None, None).unwrap()
+    }
+}
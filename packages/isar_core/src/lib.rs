@@ -3,9 +3,18 @@
 #[cfg(not(target_endian = "little"))]
 compile_error!("Only little endian systems are supported.");
 
+pub mod archive;
+pub mod batch;
+pub mod blob;
+pub mod clock;
 pub mod collection;
+#[cfg(feature = "conformance")]
+pub mod conformance;
 mod cursor;
+pub mod delta;
 pub mod error;
+#[cfg(feature = "fault-injection")]
+pub mod fault;
 pub mod index;
 pub mod instance;
 mod legacy;
@@ -14,5 +23,7 @@ mod mdbx;
 pub mod object;
 pub mod query;
 pub mod schema;
+pub mod stats;
 pub mod txn;
 pub mod watch;
+pub mod watchdog;
@@ -4,10 +4,14 @@ use crate::mdbx::cursor::Cursor;
 use crate::mdbx::db::Db;
 use crate::object::id::{BytesToId, IdToBytes};
 use crate::object::isar_object::IsarObject;
+use crate::query::DeadlineCheck;
 use std::ops::Deref;
 use xxhash_rust::xxh3::xxh3_64_with_seed;
 
-#[derive(Clone)]
+/// The id of a link and its `(id, other_id)` pairs.
+pub(crate) type LinkPairs = (u64, Vec<(i64, i64)>);
+
+#[derive(Clone, PartialEq)]
 pub(crate) struct IsarLink {
     pub name: String,
     pub id: u64,
@@ -40,6 +44,10 @@ impl IsarLink {
         }
     }
 
+    pub fn target_db(&self) -> Db {
+        self.target_db
+    }
+
     pub fn iter_ids<F>(&self, cursors: &IsarCursors, id: i64, mut callback: F) -> Result<bool>
     where
         F: FnMut(&mut Cursor, i64) -> Result<bool>,
@@ -135,6 +143,73 @@ impl IsarLink {
         Ok(())
     }
 
+    /// Removes links whose source or target does not exist and links without backlink (and
+    /// vice versa). Returns the number of removed entries. Stops scanning once `deadline`
+    /// expired.
+    pub fn sweep_orphans(
+        &self,
+        cursors: &IsarCursors,
+        deadline: &mut DeadlineCheck,
+    ) -> Result<u64> {
+        let mut source_cursor = cursors.get_cursor(self.source_db)?;
+        let mut target_cursor = cursors.get_cursor(self.target_db)?;
+        let mut link_cursor = cursors.get_cursor(self.db)?;
+        let mut backlink_cursor = cursors.get_cursor(self.bl_db)?;
+
+        let mut orphan_links = vec![];
+        link_cursor.iter_all(false, true, |_, id_bytes, target_id_bytes| {
+            let id = id_bytes.to_id();
+            let target_id = target_id_bytes.to_id();
+            let valid = source_cursor.move_to(&id)?.is_some()
+                && target_cursor.move_to(&target_id)?.is_some()
+                && backlink_cursor
+                    .move_to_key_val(&target_id, id_bytes)?
+                    .is_some();
+            if !valid {
+                orphan_links.push((id, target_id));
+            }
+            Ok(!deadline.expired())
+        })?;
+
+        let mut orphan_backlinks = vec![];
+        backlink_cursor.iter_all(false, true, |_, target_id_bytes, id_bytes| {
+            let id = id_bytes.to_id();
+            let target_id = target_id_bytes.to_id();
+            if link_cursor.move_to_key_val(&id, target_id_bytes)?.is_none() {
+                orphan_backlinks.push((id, target_id));
+            }
+            Ok(!deadline.expired())
+        })?;
+
+        let mut removed = 0;
+        for (id, target_id) in orphan_links {
+            if link_cursor
+                .move_to_key_val(&id, &target_id.to_id_bytes())?
+                .is_some()
+            {
+                link_cursor.delete_current()?;
+                removed += 1;
+            }
+            if backlink_cursor
+                .move_to_key_val(&target_id, &id.to_id_bytes())?
+                .is_some()
+            {
+                backlink_cursor.delete_current()?;
+                removed += 1;
+            }
+        }
+        for (id, target_id) in orphan_backlinks {
+            if backlink_cursor
+                .move_to_key_val(&target_id, &id.to_id_bytes())?
+                .is_some()
+            {
+                backlink_cursor.delete_current()?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
     pub fn get_size(&self, cursors: &IsarCursors) -> Result<u64> {
         Ok(cursors.db_stat(self.db)?.1)
     }
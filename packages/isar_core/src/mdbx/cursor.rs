@@ -120,6 +120,8 @@ impl<'txn> Cursor<'txn> {
     }
 
     pub fn put<K: Key>(&mut self, key: &K, data: &[u8]) -> Result<()> {
+        #[cfg(feature = "fault-injection")]
+        crate::fault::check_write()?;
         unsafe {
             // make sure that bytes are not dropped before the call to mdbx_cursor_put
             let bytes = &key.as_bytes();
@@ -132,6 +134,8 @@ impl<'txn> Cursor<'txn> {
 
     /// Requires the cursor to have a valid position
     pub fn delete_current(&mut self) -> Result<()> {
+        #[cfg(feature = "fault-injection")]
+        crate::fault::check_write()?;
         unsafe { mdbx_result(ffi::mdbx_cursor_del(self.cursor.cursor, 0))? };
 
         Ok(())
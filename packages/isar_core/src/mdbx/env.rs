@@ -18,6 +18,7 @@ impl Env {
         path: &str,
         max_dbs: u64,
         max_size_mib: usize,
+        max_readers: u32,
         relaxed_durability: bool,
     ) -> Result<Env> {
         let path = str_to_os(path)?;
@@ -29,6 +30,13 @@ impl Env {
                 ffi::MDBX_option_t::MDBX_opt_max_db,
                 max_dbs,
             ))?;
+            if max_readers > 0 {
+                mdbx_result(ffi::mdbx_env_set_option(
+                    env,
+                    ffi::MDBX_option_t::MDBX_opt_max_readers,
+                    max_readers as u64,
+                ))?;
+            }
 
             let mut flags = ffi::MDBX_NOTLS | ffi::MDBX_COALESCE | ffi::MDBX_NOSUBDIR;
             if relaxed_durability {
@@ -82,6 +90,41 @@ impl Env {
         Ok(Txn::new(txn, write))
     }
 
+    /// Clears reader slots of terminated threads / processes and returns their count.
+    pub fn check_readers(&self) -> Result<u32> {
+        let mut dead: libc::c_int = 0;
+        unsafe { mdbx_result(ffi::mdbx_reader_check(self.env, &mut dead))? };
+        Ok(dead.max(0) as u32)
+    }
+
+    /// Flushes buffered writes to disk. Returns `false` if `nonblock` is set and another
+    /// write transaction holds the lock.
+    pub fn sync(&self, force: bool, nonblock: bool) -> Result<bool> {
+        let err_code = unsafe { ffi::mdbx_env_sync_ex(self.env, force, nonblock) };
+        if err_code == ffi::MDBX_BUSY {
+            Ok(false)
+        } else {
+            mdbx_result(err_code)?;
+            Ok(true)
+        }
+    }
+
+    /// Returns the bytes not yet synced to disk, the used bytes and the maximum size of the
+    /// database.
+    pub fn space_info(&self) -> Result<(u64, u64, u64)> {
+        let mut info: ffi::MDBX_envinfo = unsafe { std::mem::zeroed() };
+        unsafe {
+            mdbx_result(ffi::mdbx_env_info_ex(
+                self.env,
+                ptr::null(),
+                &mut info,
+                std::mem::size_of::<ffi::MDBX_envinfo>() as ffi::size_t,
+            ))?;
+        }
+        let used = (info.mi_last_pgno + 1) * info.mi_dxb_pagesize as u64;
+        Ok((info.mi_unsync_volume, used, info.mi_geo.upper))
+    }
+
     pub fn copy(&self, path: &str) -> Result<()> {
         let path = str_to_os(path)?;
         unsafe { mdbx_result(ENV_COPY(self.env, path.as_ptr(), ffi::MDBX_CP_COMPACT)) }
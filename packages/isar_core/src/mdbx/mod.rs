@@ -56,6 +56,14 @@ pub fn mdbx_result(err_code: c_int) -> Result<()> {
     }
 }
 
+/// Errors that may go away when the operation is repeated.
+pub fn is_transient(err_code: c_int) -> bool {
+    matches!(
+        err_code,
+        ffi::MDBX_BUSY | ffi::MDBX_MAP_RESIZED | libc::EAGAIN | libc::EINTR
+    )
+}
+
 pub trait Key {
     fn as_bytes(&self) -> Cow<[u8]>;
 
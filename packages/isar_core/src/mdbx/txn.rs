@@ -19,6 +19,12 @@ impl<'env> Txn<'env> {
     }
 
     pub fn commit(mut self) -> Result<()> {
+        #[cfg(feature = "fault-injection")]
+        if crate::fault::check_commit() {
+            unsafe { ffi::mdbx_txn_abort(self.txn) };
+            self.txn = ptr::null_mut();
+            return mdbx_result(libc::EIO);
+        }
         let result = unsafe { mdbx_result(ffi::mdbx_txn_commit_ex(self.txn, ptr::null_mut())) };
         self.txn = ptr::null_mut();
         result?;
@@ -26,6 +32,12 @@ impl<'env> Txn<'env> {
     }
 
     pub fn abort(self) {}
+
+    /// Releases the snapshot of a read transaction. It still has to be aborted afterwards.
+    pub(crate) fn reset(&self) -> Result<()> {
+        unsafe { mdbx_result(ffi::mdbx_txn_reset(self.txn)) }?;
+        Ok(())
+    }
 }
 
 impl<'a> Drop for Txn<'a> {
@@ -0,0 +1,110 @@
+use crate::error::{illegal_arg, Result};
+use crate::object::data_type::DataType;
+use crate::object::object_builder::ObjectBuilder;
+use crate::object::property::Property;
+
+/// Values of a single property for many objects. Null numbers are represented by the
+/// `IsarObject::NULL_*` constants.
+#[derive(Clone, Copy)]
+pub enum ColumnData<'a> {
+    Bool(&'a [Option<bool>]),
+    Byte(&'a [u8]),
+    Int(&'a [i32]),
+    Float(&'a [f32]),
+    Long(&'a [i64]),
+    Double(&'a [f64]),
+    String(&'a [Option<&'a str>]),
+}
+
+impl<'a> ColumnData<'a> {
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnData::Bool(values) => values.len(),
+            ColumnData::Byte(values) => values.len(),
+            ColumnData::Int(values) => values.len(),
+            ColumnData::Float(values) => values.len(),
+            ColumnData::Long(values) => values.len(),
+            ColumnData::Double(values) => values.len(),
+            ColumnData::String(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            ColumnData::Bool(_) => DataType::Bool,
+            ColumnData::Byte(_) => DataType::Byte,
+            ColumnData::Int(_) => DataType::Int,
+            ColumnData::Float(_) => DataType::Float,
+            ColumnData::Long(_) => DataType::Long,
+            ColumnData::Double(_) => DataType::Double,
+            ColumnData::String(_) => DataType::String,
+        }
+    }
+}
+
+pub struct Columns<'a> {
+    columns: Vec<(Property, ColumnData<'a>)>,
+    missing: Vec<Property>,
+    rows: usize,
+}
+
+impl<'a> Columns<'a> {
+    /// Matches the columns to the given properties. Properties without a column are null.
+    pub(crate) fn new(
+        properties: &[Property],
+        columns: Vec<(&Property, ColumnData<'a>)>,
+    ) -> Result<Self> {
+        let rows = columns.first().map_or(0, |(_, data)| data.len());
+        let mut matched = vec![];
+        for (property, data) in columns {
+            if !properties.contains(property) {
+                return illegal_arg("Property does not belong to this collection.");
+            }
+            if property.data_type != data.data_type() {
+                return Err(property.mismatch(data.data_type()));
+            }
+            if data.len() != rows {
+                return illegal_arg("All columns must have the same length.");
+            }
+            if matched.iter().any(|(p, _)| p == property) {
+                return illegal_arg("Duplicate column.");
+            }
+            matched.push((property.clone(), data));
+        }
+        let missing = properties
+            .iter()
+            .filter(|p| !matched.iter().any(|(m, _)| m == *p))
+            .cloned()
+            .collect();
+        Ok(Columns {
+            columns: matched,
+            missing,
+            rows,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub(crate) fn write_row(&self, row: usize, ob: &mut ObjectBuilder) {
+        for (p, data) in &self.columns {
+            match data {
+                ColumnData::Bool(values) => ob.write_bool(p.offset, values[row]),
+                ColumnData::Byte(values) => ob.write_byte(p.offset, values[row]),
+                ColumnData::Int(values) => ob.write_int(p.offset, values[row]),
+                ColumnData::Float(values) => ob.write_float(p.offset, values[row]),
+                ColumnData::Long(values) => ob.write_long(p.offset, values[row]),
+                ColumnData::Double(values) => ob.write_double(p.offset, values[row]),
+                ColumnData::String(values) => ob.write_string(p.offset, values[row]),
+            }
+        }
+        for p in &self.missing {
+            ob.write_null(p.offset, p.data_type);
+        }
+    }
+}
@@ -1,9 +1,20 @@
+use crate::error::{illegal_arg, Result};
 use crate::object::data_type::DataType;
 use crate::object::object_builder::ObjectBuilder;
+use crate::object::property::Property;
 use byteorder::{ByteOrder, LittleEndian};
 use std::{cmp::Ordering, str::from_utf8_unchecked};
 use xxhash_rust::xxh3::xxh3_64_with_seed;
 
+macro_rules! get_property {
+    ($name:ident, $read:ident, $data_type:ident, $value:ty) => {
+        pub fn $name(&self, property: &Property) -> Result<$value> {
+            self.check_property(property, DataType::$data_type)?;
+            Ok(self.$read(property.offset))
+        }
+    };
+}
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct IsarObject<'a> {
     bytes: &'a [u8],
@@ -51,6 +62,41 @@ impl<'a> IsarObject<'a> {
         }
     }
 
+    /// The checked `get_*` accessors fail if `property` has a different type or an offset
+    /// that is not part of this object instead of returning null.
+    fn check_property(&self, property: &Property, expected: DataType) -> Result<()> {
+        property.check_type(expected)?;
+        let end = property.offset + expected.get_static_size();
+        if property.offset < 2 || (self.contains_offset(property.offset) && end > self.static_size)
+        {
+            illegal_arg(&format!(
+                "Property \"{}\" has an invalid offset {}.",
+                property.name, property.offset
+            ))?;
+        }
+        Ok(())
+    }
+
+    get_property!(get_bool, read_bool, Bool, Option<bool>);
+    get_property!(get_byte, read_byte, Byte, u8);
+    get_property!(get_int, read_int, Int, i32);
+    get_property!(get_float, read_float, Float, f32);
+    get_property!(get_long, read_long, Long, i64);
+    get_property!(get_double, read_double, Double, f64);
+    get_property!(get_byte_list, read_byte_list, ByteList, Option<&'a [u8]>);
+    get_property!(
+        get_string_list,
+        read_string_list,
+        StringList,
+        Option<Vec<Option<&'a str>>>
+    );
+
+    pub fn get_string(&self, property: &Property) -> Result<Option<&'a str>> {
+        self.check_property(property, DataType::String)?;
+        let bytes = self.read_byte_list(property.offset);
+        Ok(bytes.map(|bytes| unsafe { from_utf8_unchecked(bytes) }))
+    }
+
     #[inline]
     pub fn byte_to_bool(value: u8) -> Option<bool> {
         if value == Self::NULL_BOOL {
@@ -337,6 +383,7 @@ impl<'a> IsarObject<'a> {
         other: &IsarObject,
         offset: usize,
         data_type: DataType,
+        case_sensitive: bool,
     ) -> Ordering {
         match data_type {
             DataType::Bool | DataType::Byte => self.read_byte(offset).cmp(&other.read_byte(offset)),
@@ -346,7 +393,14 @@ impl<'a> IsarObject<'a> {
             DataType::Double => self
                 .read_double(offset)
                 .total_cmp(&other.read_double(offset)),
-            DataType::String => self.read_string(offset).cmp(&other.read_string(offset)),
+            DataType::String if case_sensitive => {
+                self.read_string(offset).cmp(&other.read_string(offset))
+            }
+            DataType::String => {
+                let str1 = self.read_string(offset).map(|s| s.to_lowercase());
+                let str2 = other.read_string(offset).map(|s| s.to_lowercase());
+                str1.cmp(&str2)
+            }
             _ => Ordering::Equal,
         }
     }
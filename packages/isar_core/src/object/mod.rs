@@ -1,6 +1,8 @@
+pub mod columns;
 pub mod data_type;
 pub mod id;
 pub mod isar_object;
 pub mod json_encode_decode;
 pub mod object_builder;
 pub mod property;
+pub mod row_format;
@@ -1,8 +1,10 @@
 use byteorder::{ByteOrder, LittleEndian};
 use itertools::Itertools;
 
+use crate::error::{illegal_arg, Result};
 use crate::object::data_type::DataType;
 use crate::object::isar_object::IsarObject;
+use intmap::IntMap;
 use std::slice::from_raw_parts;
 
 use super::property::Property;
@@ -30,8 +32,19 @@ u24 field4_item2_length + 1 or 0
 
 */
 
+macro_rules! set_property {
+    ($name:ident, $write:ident, $data_type:ident, $value:ty) => {
+        pub fn $name(&mut self, property: &Property, value: $value) -> Result<()> {
+            self.check_property(property, DataType::$data_type)?;
+            self.$write(property.offset, value);
+            Ok(())
+        }
+    };
+}
+
 pub struct ObjectBuilder {
     buffer: Vec<u8>,
+    static_size: usize,
     dynamic_offset: usize,
 }
 
@@ -47,6 +60,7 @@ impl ObjectBuilder {
 
         let mut ob = ObjectBuilder {
             buffer,
+            static_size,
             dynamic_offset: static_size,
         };
         ob.write_at(0, &(static_size as u16).to_le_bytes());
@@ -69,6 +83,40 @@ impl ObjectBuilder {
         LittleEndian::write_u24(&mut self.buffer[offset..], value as u32);
     }
 
+    /// The checked `set_*` writers fail if `property` has a different type or an offset outside
+    /// of the static section instead of writing to the wrong place.
+    fn check_property(&self, property: &Property, expected: DataType) -> Result<()> {
+        property.check_type(expected)?;
+        if property.offset < 2 || property.offset + expected.get_static_size() > self.static_size {
+            illegal_arg(&format!(
+                "Property \"{}\" has an invalid offset {}.",
+                property.name, property.offset
+            ))?;
+        }
+        Ok(())
+    }
+
+    set_property!(set_bool, write_bool, Bool, Option<bool>);
+    set_property!(set_byte, write_byte, Byte, u8);
+    set_property!(set_int, write_int, Int, i32);
+    set_property!(set_float, write_float, Float, f32);
+    set_property!(set_long, write_long, Long, i64);
+    set_property!(set_double, write_double, Double, f64);
+    set_property!(set_string, write_string, String, Option<&str>);
+    set_property!(set_byte_list, write_byte_list, ByteList, Option<&[u8]>);
+    set_property!(
+        set_string_list,
+        write_string_list,
+        StringList,
+        Option<&[Option<&str>]>
+    );
+
+    pub fn set_null(&mut self, property: &Property) -> Result<()> {
+        self.check_property(property, property.data_type)?;
+        self.write_null(property.offset, property.data_type);
+        Ok(())
+    }
+
     pub fn write_null(&mut self, offset: usize, data_type: DataType) {
         match data_type {
             DataType::Bool => self.write_bool(offset, None),
@@ -90,6 +138,128 @@ impl ObjectBuilder {
         }
     }
 
+    /// Copies the value of `property` from another object with the same properties.
+    pub fn write_from(&mut self, property: &Property, object: IsarObject) {
+        self.write_from_offset(property.offset, property.data_type, object, property.offset);
+    }
+
+    /// Copies all properties from an object with a compatible schema. Properties are matched by
+    /// name, properties missing in the source are null and properties whose type changed are an
+    /// error. Embedded objects with a different schema are copied the same way.
+    pub fn write_all_from(
+        &mut self,
+        properties: &[Property],
+        embedded_properties: &IntMap<Vec<Property>>,
+        object: IsarObject,
+        source_properties: &[Property],
+        source_embedded_properties: &IntMap<Vec<Property>>,
+    ) -> Result<()> {
+        for property in properties {
+            let source = source_properties.iter().find(|p| p.name == property.name);
+            let source = match source {
+                Some(source) if source.data_type != property.data_type => {
+                    return Err(source.mismatch(property.data_type));
+                }
+                Some(source) => source,
+                None => {
+                    self.write_null(property.offset, property.data_type);
+                    continue;
+                }
+            };
+
+            let embedded = property
+                .target_id
+                .and_then(|id| embedded_properties.get(id));
+            let source_embedded = source
+                .target_id
+                .and_then(|id| source_embedded_properties.get(id));
+            match (embedded, source_embedded) {
+                (Some(embedded), Some(source_embedded))
+                    if property.target_id != source.target_id || embedded != source_embedded =>
+                {
+                    let copy = |object: IsarObject| -> Result<ObjectBuilder> {
+                        let mut ob = ObjectBuilder::new(embedded, None);
+                        ob.write_all_from(
+                            embedded,
+                            embedded_properties,
+                            object,
+                            source_embedded,
+                            source_embedded_properties,
+                        )?;
+                        Ok(ob)
+                    };
+                    if property.data_type == DataType::Object {
+                        let copied = object.read_object(source.offset).map(copy).transpose()?;
+                        self.write_object(property.offset, copied.as_ref().map(|ob| ob.finish()));
+                    } else {
+                        let copied = object
+                            .read_object_list(source.offset)
+                            .map(|list| {
+                                list.into_iter()
+                                    .map(|o| o.map(copy).transpose())
+                                    .collect::<Result<Vec<_>>>()
+                            })
+                            .transpose()?;
+                        let objects = copied.as_ref().map(|list| {
+                            list.iter()
+                                .map(|ob| ob.as_ref().map(|ob| ob.finish()))
+                                .collect_vec()
+                        });
+                        self.write_object_list(property.offset, objects.as_deref());
+                    }
+                }
+                _ => self.write_from_offset(
+                    property.offset,
+                    property.data_type,
+                    object,
+                    source.offset,
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_from_offset(
+        &mut self,
+        offset: usize,
+        data_type: DataType,
+        object: IsarObject,
+        source: usize,
+    ) {
+        match data_type {
+            DataType::Bool => self.write_bool(offset, object.read_bool(source)),
+            DataType::Byte => self.write_byte(offset, object.read_byte(source)),
+            DataType::Int => self.write_int(offset, object.read_int(source)),
+            DataType::Float => self.write_float(offset, object.read_float(source)),
+            DataType::Long => self.write_long(offset, object.read_long(source)),
+            DataType::Double => self.write_double(offset, object.read_double(source)),
+            DataType::String => self.write_string(offset, object.read_string(source)),
+            DataType::Object => self.write_object(offset, object.read_object(source)),
+            DataType::BoolList => {
+                self.write_bool_list(offset, object.read_bool_list(source).as_deref())
+            }
+            DataType::ByteList => self.write_byte_list(offset, object.read_byte_list(source)),
+            DataType::IntList => {
+                self.write_int_list(offset, object.read_int_list(source).as_deref())
+            }
+            DataType::FloatList => {
+                self.write_float_list(offset, object.read_float_list(source).as_deref())
+            }
+            DataType::LongList => {
+                self.write_long_list(offset, object.read_long_list(source).as_deref())
+            }
+            DataType::DoubleList => {
+                self.write_double_list(offset, object.read_double_list(source).as_deref())
+            }
+            DataType::StringList => {
+                self.write_string_list(offset, object.read_string_list(source).as_deref())
+            }
+            DataType::ObjectList => {
+                self.write_object_list(offset, object.read_object_list(source).as_deref())
+            }
+        }
+    }
+
     pub fn bool_to_byte(value: Option<bool>) -> u8 {
         if let Some(value) = value {
             if value {
@@ -229,9 +399,11 @@ impl ObjectBuilder {
 #[cfg(test)]
 mod tests {
     use super::ObjectBuilder;
+    use crate::error::IsarError;
     use crate::object::data_type::DataType::{self, *};
     use crate::object::isar_object::IsarObject;
     use crate::object::property::Property;
+    use intmap::IntMap;
 
     macro_rules! builder {
         ($var:ident, $prop:ident, $type:ident) => {
@@ -438,6 +610,50 @@ mod tests {
         assert_eq!(b.finish().as_bytes(), bytes);
     }
 
+    #[test]
+    pub fn test_write_all_from() {
+        let source_props = vec![
+            Property::new("a", Long, 2, None),
+            Property::new("b", String, 10, None),
+            Property::new("c", Int, 13, None),
+        ];
+        let mut b = ObjectBuilder::new(&source_props, None);
+        b.write_long(2, 42);
+        b.write_string(10, Some("hello"));
+        b.write_int(13, 7);
+        let source = b.finish();
+
+        let props = vec![
+            Property::new("b", String, 2, None),
+            Property::new("d", Long, 5, None),
+            Property::new("a", Long, 13, None),
+        ];
+        let mut b = ObjectBuilder::new(&props, None);
+        b.write_all_from(
+            &props,
+            &IntMap::new(),
+            source,
+            &source_props,
+            &IntMap::new(),
+        )
+        .unwrap();
+        let object = b.finish();
+        assert_eq!(object.read_string(2), Some("hello"));
+        assert_eq!(object.read_long(5), IsarObject::NULL_LONG);
+        assert_eq!(object.read_long(13), 42);
+
+        let props = vec![Property::new("c", Long, 2, None)];
+        let mut b = ObjectBuilder::new(&props, None);
+        let result = b.write_all_from(
+            &props,
+            &IntMap::new(),
+            source,
+            &source_props,
+            &IntMap::new(),
+        );
+        assert!(matches!(result, Err(IsarError::PropertyMismatch { .. })));
+    }
+
     #[test]
     pub fn test_write_byte_list() {
         builder!(b, p, ByteList);
@@ -592,4 +808,43 @@ mod tests {
         bytes.extend_from_slice(&offset_size(0));
         assert_eq!(b.finish().as_bytes(), &bytes);
     }
+
+    #[test]
+    pub fn test_property_mismatch() {
+        use crate::error::IsarError;
+
+        let long = Property::new("long", Long, 2, None);
+        let string = Property::new("string", String, 10, None);
+        let mut b = ObjectBuilder::new(&[long.clone(), string.clone()], None);
+        b.set_long(&long, 5).unwrap();
+        b.set_string(&string, Some("abc")).unwrap();
+
+        let mismatch = || IsarError::PropertyMismatch {
+            property: "long".to_string(),
+            expected: String,
+            actual: Long,
+        };
+        assert_eq!(b.set_string(&long, Some("x")), Err(mismatch()));
+        assert_eq!(b.finish().get_string(&long), Err(mismatch()));
+        assert!(matches!(
+            b.set_int(&long, 1),
+            Err(IsarError::PropertyMismatch { .. })
+        ));
+
+        let outside = Property::new("outside", Long, 13, None);
+        assert!(matches!(
+            b.set_long(&outside, 1),
+            Err(IsarError::IllegalArg { .. })
+        ));
+        let overlapping = Property::new("overlapping", Long, 9, None);
+        assert!(matches!(
+            b.finish().get_long(&overlapping),
+            Err(IsarError::IllegalArg { .. })
+        ));
+
+        let object = b.finish();
+        assert_eq!(object.get_long(&long), Ok(5));
+        assert_eq!(object.get_string(&string), Ok(Some("abc")));
+        assert_eq!(object.get_long(&outside), Ok(IsarObject::NULL_LONG));
+    }
 }
@@ -1,6 +1,7 @@
 use xxhash_rust::xxh3::xxh3_64;
 
 use super::data_type::DataType;
+use crate::error::{IsarError, Result};
 
 #[derive(Clone, Eq, PartialEq)]
 pub struct Property {
@@ -8,6 +9,7 @@ pub struct Property {
     pub data_type: DataType,
     pub offset: usize,
     pub target_id: Option<u64>,
+    pub case_sensitive: bool,
 }
 
 impl Property {
@@ -18,6 +20,24 @@ impl Property {
             data_type,
             offset,
             target_id,
+            case_sensitive: true,
+        }
+    }
+
+    /// Error for using the property as `expected` although its type differs.
+    pub fn mismatch(&self, expected: DataType) -> IsarError {
+        IsarError::PropertyMismatch {
+            property: self.name.clone(),
+            expected,
+            actual: self.data_type,
+        }
+    }
+
+    pub fn check_type(&self, expected: DataType) -> Result<()> {
+        if self.data_type == expected {
+            Ok(())
+        } else {
+            Err(self.mismatch(expected))
         }
     }
 
@@ -27,6 +47,7 @@ impl Property {
             data_type,
             offset,
             target_id: None,
+            case_sensitive: true,
         }
     }
 }
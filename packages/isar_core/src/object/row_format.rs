@@ -0,0 +1,80 @@
+use crate::error::{IsarError, Result};
+use byteorder::{ByteOrder, LittleEndian};
+
+/*
+Version 1 rows are stored back to back. All numbers are little endian:
+
+i64 id
+u32 object_length
+... object ... (object_length bytes in the IsarObject format)
+*/
+
+/// Increased whenever the layout of rows changes.
+pub const ROW_FORMAT_VERSION: u32 = 1;
+
+pub const ROW_HEADER_SIZE: usize = 12;
+
+/// Appends a row to `buffer`.
+pub fn encode_row(buffer: &mut Vec<u8>, id: i64, object: &[u8]) {
+    let mut header = [0; ROW_HEADER_SIZE];
+    LittleEndian::write_i64(&mut header, id);
+    LittleEndian::write_u32(&mut header[8..], object.len() as u32);
+    buffer.extend_from_slice(&header);
+    buffer.extend_from_slice(object);
+}
+
+pub fn encoded_row_size(object: &[u8]) -> usize {
+    ROW_HEADER_SIZE + object.len()
+}
+
+/// Iterates the ids and objects of encoded rows.
+pub fn decode_rows(bytes: &[u8]) -> RowIter<'_> {
+    RowIter { bytes }
+}
+
+pub struct RowIter<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for RowIter<'a> {
+    type Item = Result<(i64, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        if self.bytes.len() < ROW_HEADER_SIZE {
+            self.bytes = &[];
+            return Some(Err(IsarError::InvalidObject {}));
+        }
+        let id = LittleEndian::read_i64(self.bytes);
+        let length = LittleEndian::read_u32(&self.bytes[8..]) as usize;
+        let rest = &self.bytes[ROW_HEADER_SIZE..];
+        if rest.len() < length {
+            self.bytes = &[];
+            return Some(Err(IsarError::InvalidObject {}));
+        }
+        let (object, rest) = rest.split_at(length);
+        self.bytes = rest;
+        Some(Ok((id, object)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_rows() {
+        let mut buffer = vec![];
+        encode_row(&mut buffer, 1, &[1, 2, 3]);
+        encode_row(&mut buffer, -5, &[]);
+        assert_eq!(buffer.len(), encoded_row_size(&[1, 2, 3]) + ROW_HEADER_SIZE);
+
+        let rows: Vec<_> = decode_rows(&buffer).collect::<Result<_>>().unwrap();
+        assert_eq!(rows, vec![(1, &[1, 2, 3][..]), (-5, &[][..])]);
+
+        let truncated: Vec<_> = decode_rows(&buffer[..14]).collect();
+        assert_eq!(truncated, vec![Err(IsarError::InvalidObject {})]);
+    }
+}
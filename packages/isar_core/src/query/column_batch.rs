@@ -0,0 +1,124 @@
+use crate::error::{illegal_arg, Result};
+use crate::object::data_type::DataType;
+use crate::object::isar_object::IsarObject;
+use crate::object::property::Property;
+
+/// Values of one property for all objects of a [ColumnBatch]. Null numbers are represented by
+/// the `IsarObject::NULL_*` constants.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnValues {
+    Bool(Vec<Option<bool>>),
+    Byte(Vec<u8>),
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    Long(Vec<i64>),
+    Double(Vec<f64>),
+    String(Vec<Option<String>>),
+}
+
+impl ColumnValues {
+    fn new(data_type: DataType) -> Result<Self> {
+        let values = match data_type {
+            DataType::Bool => ColumnValues::Bool(vec![]),
+            DataType::Byte => ColumnValues::Byte(vec![]),
+            DataType::Int => ColumnValues::Int(vec![]),
+            DataType::Float => ColumnValues::Float(vec![]),
+            DataType::Long => ColumnValues::Long(vec![]),
+            DataType::Double => ColumnValues::Double(vec![]),
+            DataType::String => ColumnValues::String(vec![]),
+            _ => return illegal_arg("Only primitive and String properties can be columns."),
+        };
+        Ok(values)
+    }
+
+    fn push(&mut self, offset: usize, object: IsarObject) {
+        match self {
+            ColumnValues::Bool(values) => values.push(object.read_bool(offset)),
+            ColumnValues::Byte(values) => values.push(object.read_byte(offset)),
+            ColumnValues::Int(values) => values.push(object.read_int(offset)),
+            ColumnValues::Float(values) => values.push(object.read_float(offset)),
+            ColumnValues::Long(values) => values.push(object.read_long(offset)),
+            ColumnValues::Double(values) => values.push(object.read_double(offset)),
+            ColumnValues::String(values) => {
+                values.push(object.read_string(offset).map(|s| s.to_string()))
+            }
+        }
+    }
+}
+
+/// Query results stored per property instead of per object.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnBatch {
+    pub ids: Vec<i64>,
+    pub columns: Vec<(String, ColumnValues)>,
+    offsets: Vec<usize>,
+}
+
+impl ColumnBatch {
+    pub(crate) fn new(properties: &[&Property]) -> Result<Self> {
+        let mut columns = vec![];
+        for property in properties {
+            let values = ColumnValues::new(property.data_type)?;
+            columns.push((property.name.clone(), values));
+        }
+        Ok(ColumnBatch {
+            ids: vec![],
+            columns,
+            offsets: properties.iter().map(|p| p.offset).collect(),
+        })
+    }
+
+    pub(crate) fn push(&mut self, id: i64, object: IsarObject) {
+        self.ids.push(id);
+        for ((_, values), offset) in self.columns.iter_mut().zip(&self.offsets) {
+            values.push(*offset, object);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::object_builder::ObjectBuilder;
+
+    #[test]
+    fn test_push() {
+        let long = Property::new("long", DataType::Long, 2, None);
+        let string = Property::new("string", DataType::String, 10, None);
+        let properties = [long.clone(), string.clone()];
+        let mut batch = ColumnBatch::new(&[&string, &long]).unwrap();
+
+        for (id, value) in [(1, Some("a")), (2, None)] {
+            let mut ob = ObjectBuilder::new(&properties, None);
+            ob.write_long(long.offset, id * 10);
+            ob.write_string(string.offset, value);
+            batch.push(id, ob.finish());
+        }
+
+        assert_eq!(batch.ids, vec![1, 2]);
+        assert_eq!(
+            batch.columns,
+            vec![
+                (
+                    "string".to_string(),
+                    ColumnValues::String(vec![Some("a".to_string()), None])
+                ),
+                ("long".to_string(), ColumnValues::Long(vec![10, 20])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        let list = Property::new("list", DataType::LongList, 2, None);
+        assert!(ColumnBatch::new(&[&list]).is_err());
+    }
+}
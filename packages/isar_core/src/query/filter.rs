@@ -6,9 +6,12 @@ use crate::object::data_type::DataType;
 use crate::object::isar_object::IsarObject;
 use crate::object::property::Property;
 use crate::query::fast_wild_match::fast_wild_match;
+use crate::query::query_ast::FilterAst;
+use crate::query::string_normalizer::StringNormalizer;
 use enum_dispatch::enum_dispatch;
 use itertools::Itertools;
 use paste::paste;
+use std::sync::Arc;
 
 #[macro_export]
 macro_rules! primitive_create {
@@ -31,7 +34,7 @@ macro_rules! primitive_create {
                     })
                 ))
             } else {
-                illegal_arg("Property does not support this filter.")
+                Err($property.mismatch(DataType::$data_type))
             }
         }
     };
@@ -42,7 +45,8 @@ macro_rules! string_filter_create {
     ($name:ident, $property:expr, $value:expr, $case_sensitive:expr) => {
         paste! {
             {
-                let value = if $case_sensitive {
+                let case_sensitive = $case_sensitive && $property.case_sensitive;
+                let value = if case_sensitive {
                     $value.to_string()
                 } else {
                     $value.to_lowercase()
@@ -51,16 +55,16 @@ macro_rules! string_filter_create {
                     Ok(FilterCond::[<String $name>]([<String $name Cond>] {
                         offset: $property.offset,
                         value,
-                        $case_sensitive,
+                        case_sensitive,
                     }))
                 } else if $property.data_type == DataType::StringList {
                     Ok(FilterCond::[<AnyString $name>]([<AnyString $name Cond>] {
                         offset: $property.offset,
                         value,
-                        $case_sensitive,
+                        case_sensitive,
                     }))
                 } else {
-                    illegal_arg("Property does not support this filter.")
+                    Err($property.mismatch(DataType::String))
                 }?;
                 Ok(Filter(filter_cond))
             }
@@ -97,6 +101,42 @@ impl Filter {
         primitive_create!(Double, property, lower, upper)
     }
 
+    /// Matches values that have the single bit of `flag` set.
+    pub fn has_flag(property: &Property, flag: i64) -> Result<Filter> {
+        if flag.count_ones() != 1 {
+            return illegal_arg("A flag has to have exactly one bit set.");
+        }
+        Self::flags(property, flag, true)
+    }
+
+    pub fn has_any_flag(property: &Property, mask: i64) -> Result<Filter> {
+        Self::flags(property, mask, false)
+    }
+
+    pub fn has_all_flags(property: &Property, mask: i64) -> Result<Filter> {
+        Self::flags(property, mask, true)
+    }
+
+    /// The sign bit of Int and Long properties cannot be used as a flag because it is part of
+    /// the null value.
+    fn flags(property: &Property, mask: i64, all: bool) -> Result<Filter> {
+        let available = match property.data_type {
+            DataType::Byte => u8::MAX as i64,
+            DataType::Int => i32::MAX as i64,
+            DataType::Long => i64::MAX,
+            _ => return Err(property.mismatch(DataType::Long)),
+        };
+        if mask & !available != 0 {
+            return illegal_arg("The flags use bits that are not available for the property.");
+        }
+        Ok(Filter(FilterCond::Flags(FlagsCond {
+            offset: property.offset,
+            data_type: property.data_type,
+            mask,
+            all,
+        })))
+    }
+
     pub fn string_to_bytes(str: Option<&str>, case_sensitive: bool) -> Option<Vec<u8>> {
         if case_sensitive {
             str.map(|s| s.as_bytes().to_vec())
@@ -111,6 +151,7 @@ impl Filter {
         upper: Option<&str>,
         case_sensitive: bool,
     ) -> Result<Filter> {
+        let case_sensitive = case_sensitive && property.case_sensitive;
         Self::byte_string(
             property,
             Self::string_to_bytes(lower, case_sensitive),
@@ -125,6 +166,15 @@ impl Filter {
         upper: Option<Vec<u8>>,
         case_sensitive: bool,
     ) -> Result<Filter> {
+        // NoCase properties compare lowercase bounds regardless of the requested case
+        let (lower, upper) = if case_sensitive && !property.case_sensitive {
+            let lowercase =
+                |bytes: Vec<u8>| String::from_utf8_lossy(&bytes).to_lowercase().into_bytes();
+            (lower.map(lowercase), upper.map(lowercase))
+        } else {
+            (lower, upper)
+        };
+        let case_sensitive = case_sensitive && property.case_sensitive;
         let filter_cond = if property.data_type == DataType::String {
             Ok(FilterCond::StringBetween(StringBetweenCond {
                 offset: property.offset,
@@ -140,7 +190,7 @@ impl Filter {
                 case_sensitive,
             }))
         } else {
-            illegal_arg("Property does not support this filter.")
+            Err(property.mismatch(DataType::String))
         }?;
         Ok(Filter(filter_cond))
     }
@@ -177,6 +227,31 @@ impl Filter {
         string_filter_create!(Matches, property, value, case_sensitive)
     }
 
+    pub fn string_equal(
+        property: &Property,
+        value: &str,
+        mut normalizer: StringNormalizer,
+    ) -> Result<Filter> {
+        normalizer.case_sensitive &= property.case_sensitive;
+        let value = normalizer.normalize(value).into_owned();
+        let filter_cond = if property.data_type == DataType::String {
+            FilterCond::StringEqual(StringEqualCond {
+                offset: property.offset,
+                value,
+                normalizer,
+            })
+        } else if property.data_type == DataType::StringList {
+            FilterCond::AnyStringEqual(AnyStringEqualCond {
+                offset: property.offset,
+                value,
+                normalizer,
+            })
+        } else {
+            return Err(property.mismatch(DataType::String));
+        };
+        Ok(Filter(filter_cond))
+    }
+
     pub fn list_length(property: &Property, lower: usize, upper: usize) -> Result<Filter> {
         let filter_cond = if property.data_type.get_element_type().is_some() {
             Ok(FilterCond::ListLength(ListLengthCond {
@@ -199,13 +274,13 @@ impl Filter {
     }
 
     pub fn and(filters: Vec<Filter>) -> Filter {
-        let filters = filters.into_iter().map(|f| f.0).collect_vec();
+        let filters = dedup_fragments(filters);
         let filter_cond = FilterCond::And(AndCond { filters });
         Filter(filter_cond)
     }
 
     pub fn or(filters: Vec<Filter>) -> Filter {
-        let filters = filters.into_iter().map(|f| f.0).collect_vec();
+        let filters = dedup_fragments(filters);
         let filter_cond = FilterCond::Or(OrCond { filters });
         Filter(filter_cond)
     }
@@ -247,7 +322,7 @@ impl Filter {
                 filter: filter.map(|f| Box::new(f.0)),
             }))
         } else {
-            illegal_arg("Property does not support this filter.")
+            Err(property.mismatch(DataType::Object))
         }?;
         Ok(Filter(filter_cond))
     }
@@ -280,10 +355,397 @@ impl Filter {
     ) -> Result<bool> {
         self.0.evaluate(id, object, cursors)
     }
+
+    /// `properties` are the properties of `collection` or of the embedded object the filter
+    /// is applied to. Fragments are inlined.
+    pub fn to_ast(
+        &self,
+        collection: &IsarCollection,
+        properties: &[Property],
+        collections: &[IsarCollection],
+    ) -> Result<FilterAst> {
+        self.0.to_ast(collection, properties, collections)
+    }
+
+    /// Offsets of the properties the filter reads. Properties of embedded objects and links are
+    /// not included.
+    pub(crate) fn property_offsets(&self) -> Vec<usize> {
+        let mut offsets = vec![];
+        self.0.property_offsets(&mut offsets);
+        offsets
+    }
 }
 
-#[enum_dispatch]
+fn property_name(properties: &[Property], offset: usize) -> Result<String> {
+    if let Some(property) = properties.iter().find(|p| p.offset == offset) {
+        Ok(property.name.clone())
+    } else {
+        illegal_arg("Filter refers to an unknown property.")
+    }
+}
+
+impl FilterCond {
+    fn property_offsets(&self, offsets: &mut Vec<usize>) {
+        match self {
+            FilterCond::ByteBetween(ByteBetweenCond { offset, .. })
+            | FilterCond::IntBetween(IntBetweenCond { offset, .. })
+            | FilterCond::LongBetween(LongBetweenCond { offset, .. })
+            | FilterCond::FloatBetween(FloatBetweenCond { offset, .. })
+            | FilterCond::DoubleBetween(DoubleBetweenCond { offset, .. })
+            | FilterCond::Flags(FlagsCond { offset, .. })
+            | FilterCond::StringBetween(StringBetweenCond { offset, .. })
+            | FilterCond::StringStartsWith(StringStartsWithCond { offset, .. })
+            | FilterCond::StringEndsWith(StringEndsWithCond { offset, .. })
+            | FilterCond::StringContains(StringContainsCond { offset, .. })
+            | FilterCond::StringMatches(StringMatchesCond { offset, .. })
+            | FilterCond::StringEqual(StringEqualCond { offset, .. })
+            | FilterCond::AnyByteBetween(AnyByteBetweenCond { offset, .. })
+            | FilterCond::AnyIntBetween(AnyIntBetweenCond { offset, .. })
+            | FilterCond::AnyLongBetween(AnyLongBetweenCond { offset, .. })
+            | FilterCond::AnyFloatBetween(AnyFloatBetweenCond { offset, .. })
+            | FilterCond::AnyDoubleBetween(AnyDoubleBetweenCond { offset, .. })
+            | FilterCond::AnyStringBetween(AnyStringBetweenCond { offset, .. })
+            | FilterCond::AnyStringStartsWith(AnyStringStartsWithCond { offset, .. })
+            | FilterCond::AnyStringEndsWith(AnyStringEndsWithCond { offset, .. })
+            | FilterCond::AnyStringContains(AnyStringContainsCond { offset, .. })
+            | FilterCond::AnyStringMatches(AnyStringMatchesCond { offset, .. })
+            | FilterCond::AnyStringEqual(AnyStringEqualCond { offset, .. })
+            | FilterCond::ListLength(ListLengthCond { offset, .. })
+            | FilterCond::Null(NullCond { offset, .. }) => offsets.push(*offset),
+            FilterCond::And(AndCond { filters })
+            | FilterCond::Or(OrCond { filters })
+            | FilterCond::Xor(XorCond { filters }) => {
+                for filter in filters {
+                    filter.property_offsets(offsets);
+                }
+            }
+            FilterCond::Not(NotCond { filter }) => filter.property_offsets(offsets),
+            FilterCond::Fragment(FragmentCond { filter }) => filter.property_offsets(offsets),
+            FilterCond::IdBetween(_)
+            | FilterCond::Static(_)
+            | FilterCond::Object(_)
+            | FilterCond::AnyObject(_)
+            | FilterCond::AnyLink(_)
+            | FilterCond::LinkLength(_) => {}
+        }
+    }
+
+    fn to_ast(
+        &self,
+        collection: &IsarCollection,
+        properties: &[Property],
+        collections: &[IsarCollection],
+    ) -> Result<FilterAst> {
+        let name = |offset: usize| property_name(properties, offset);
+        let all_to_ast = |filters: &[FilterCond]| -> Result<Vec<FilterAst>> {
+            filters
+                .iter()
+                .map(|f| f.to_ast(collection, properties, collections))
+                .collect()
+        };
+        let ast = match self {
+            FilterCond::IdBetween(c) => FilterAst::Id {
+                lower: c.lower,
+                upper: c.upper,
+            },
+            FilterCond::ByteBetween(ByteBetweenCond {
+                offset,
+                lower,
+                upper,
+            })
+            | FilterCond::AnyByteBetween(AnyByteBetweenCond {
+                offset,
+                lower,
+                upper,
+            }) => FilterAst::ByteBetween {
+                property: name(*offset)?,
+                lower: *lower,
+                upper: *upper,
+            },
+            FilterCond::IntBetween(IntBetweenCond {
+                offset,
+                lower,
+                upper,
+            })
+            | FilterCond::AnyIntBetween(AnyIntBetweenCond {
+                offset,
+                lower,
+                upper,
+            }) => FilterAst::IntBetween {
+                property: name(*offset)?,
+                lower: *lower,
+                upper: *upper,
+            },
+            FilterCond::LongBetween(LongBetweenCond {
+                offset,
+                lower,
+                upper,
+            })
+            | FilterCond::AnyLongBetween(AnyLongBetweenCond {
+                offset,
+                lower,
+                upper,
+            }) => FilterAst::LongBetween {
+                property: name(*offset)?,
+                lower: *lower,
+                upper: *upper,
+            },
+            FilterCond::FloatBetween(FloatBetweenCond {
+                offset,
+                lower,
+                upper,
+            })
+            | FilterCond::AnyFloatBetween(AnyFloatBetweenCond {
+                offset,
+                lower,
+                upper,
+            }) => FilterAst::FloatBetween {
+                property: name(*offset)?,
+                lower: *lower,
+                upper: *upper,
+            },
+            FilterCond::DoubleBetween(DoubleBetweenCond {
+                offset,
+                lower,
+                upper,
+            })
+            | FilterCond::AnyDoubleBetween(AnyDoubleBetweenCond {
+                offset,
+                lower,
+                upper,
+            }) => FilterAst::DoubleBetween {
+                property: name(*offset)?,
+                lower: *lower,
+                upper: *upper,
+            },
+            FilterCond::Flags(c) => FilterAst::Flags {
+                property: name(c.offset)?,
+                mask: c.mask,
+                all: c.all,
+            },
+            FilterCond::StringBetween(StringBetweenCond {
+                offset,
+                lower,
+                upper,
+                case_sensitive,
+            })
+            | FilterCond::AnyStringBetween(AnyStringBetweenCond {
+                offset,
+                lower,
+                upper,
+                case_sensitive,
+            }) => FilterAst::StringBetween {
+                property: name(*offset)?,
+                lower: lower.clone(),
+                upper: upper.clone(),
+                case_sensitive: *case_sensitive,
+            },
+            FilterCond::StringStartsWith(StringStartsWithCond {
+                offset,
+                value,
+                case_sensitive,
+            })
+            | FilterCond::AnyStringStartsWith(AnyStringStartsWithCond {
+                offset,
+                value,
+                case_sensitive,
+            }) => FilterAst::StringStartsWith {
+                property: name(*offset)?,
+                value: value.clone(),
+                case_sensitive: *case_sensitive,
+            },
+            FilterCond::StringEndsWith(StringEndsWithCond {
+                offset,
+                value,
+                case_sensitive,
+            })
+            | FilterCond::AnyStringEndsWith(AnyStringEndsWithCond {
+                offset,
+                value,
+                case_sensitive,
+            }) => FilterAst::StringEndsWith {
+                property: name(*offset)?,
+                value: value.clone(),
+                case_sensitive: *case_sensitive,
+            },
+            FilterCond::StringContains(StringContainsCond {
+                offset,
+                value,
+                case_sensitive,
+            })
+            | FilterCond::AnyStringContains(AnyStringContainsCond {
+                offset,
+                value,
+                case_sensitive,
+            }) => FilterAst::StringContains {
+                property: name(*offset)?,
+                value: value.clone(),
+                case_sensitive: *case_sensitive,
+            },
+            FilterCond::StringMatches(StringMatchesCond {
+                offset,
+                value,
+                case_sensitive,
+            })
+            | FilterCond::AnyStringMatches(AnyStringMatchesCond {
+                offset,
+                value,
+                case_sensitive,
+            }) => FilterAst::StringMatches {
+                property: name(*offset)?,
+                value: value.clone(),
+                case_sensitive: *case_sensitive,
+            },
+            FilterCond::StringEqual(StringEqualCond {
+                offset,
+                value,
+                normalizer,
+            })
+            | FilterCond::AnyStringEqual(AnyStringEqualCond {
+                offset,
+                value,
+                normalizer,
+            }) => FilterAst::StringEqual {
+                property: name(*offset)?,
+                value: value.clone(),
+                case_sensitive: normalizer.case_sensitive,
+                trim: normalizer.trim,
+                ignore_whitespace: normalizer.ignore_whitespace,
+                ignore_diacritics: normalizer.ignore_diacritics,
+            },
+            FilterCond::ListLength(c) => FilterAst::ListLength {
+                property: name(c.offset)?,
+                lower: c.lower,
+                upper: c.upper,
+            },
+            FilterCond::Null(c) => FilterAst::IsNull {
+                property: name(c.offset)?,
+            },
+            FilterCond::And(c) => FilterAst::And {
+                filters: all_to_ast(&c.filters)?,
+            },
+            FilterCond::Or(c) => FilterAst::Or {
+                filters: all_to_ast(&c.filters)?,
+            },
+            FilterCond::Xor(c) => FilterAst::Xor {
+                filters: all_to_ast(&c.filters)?,
+            },
+            FilterCond::Not(c) => FilterAst::Not {
+                filter: Box::new(c.filter.to_ast(collection, properties, collections)?),
+            },
+            FilterCond::Static(c) => FilterAst::Static { value: c.value },
+            FilterCond::Fragment(c) => c.filter.to_ast(collection, properties, collections)?,
+            FilterCond::Object(ObjectCond { offset, filter }) => {
+                let embedded = Self::embedded_properties(collection, properties, *offset)?;
+                let filter = filter.to_ast(collection, embedded, collections)?;
+                FilterAst::Object {
+                    property: name(*offset)?,
+                    filter: Some(Box::new(filter)),
+                }
+            }
+            FilterCond::AnyObject(AnyObjectCond { offset, filter }) => {
+                let filter = if let Some(filter) = filter {
+                    let embedded = Self::embedded_properties(collection, properties, *offset)?;
+                    Some(Box::new(filter.to_ast(
+                        collection,
+                        embedded,
+                        collections,
+                    )?))
+                } else {
+                    None
+                };
+                FilterAst::Object {
+                    property: name(*offset)?,
+                    filter,
+                }
+            }
+            FilterCond::AnyLink(c) => {
+                let target_db = c.link.target_db();
+                let target = if let Some(target) = collections.iter().find(|c| c.db == target_db) {
+                    target
+                } else {
+                    return illegal_arg("Link target collection does not exist.");
+                };
+                FilterAst::Link {
+                    link_id: c.link.id,
+                    link: c.link.name.clone(),
+                    filter: Box::new(c.filter.to_ast(target, &target.properties, collections)?),
+                }
+            }
+            FilterCond::LinkLength(c) => FilterAst::LinkLength {
+                link_id: c.link.id,
+                link: c.link.name.clone(),
+                lower: c.lower,
+                upper: c.upper,
+            },
+        };
+        Ok(ast)
+    }
+
+    fn embedded_properties<'a>(
+        collection: &'a IsarCollection,
+        properties: &[Property],
+        offset: usize,
+    ) -> Result<&'a [Property]> {
+        let embedded = properties
+            .iter()
+            .find(|p| p.offset == offset)
+            .and_then(|p| p.target_id)
+            .and_then(|id| collection.embedded_properties.get(id));
+        if let Some(embedded) = embedded {
+            Ok(embedded)
+        } else {
+            illegal_arg("Filter refers to an unknown embedded object.")
+        }
+    }
+}
+
+/// Removes fragments that are equal to a previous fragment. Only valid for `and` and `or`.
+fn dedup_fragments(filters: Vec<Filter>) -> Vec<FilterCond> {
+    let mut result: Vec<FilterCond> = vec![];
+    for filter in filters {
+        if let FilterCond::Fragment(fragment) = &filter.0 {
+            let duplicate = result.iter().any(|existing| match existing {
+                FilterCond::Fragment(e) => e.filter == fragment.filter,
+                _ => false,
+            });
+            if duplicate {
+                continue;
+            }
+        }
+        result.push(filter.0);
+    }
+    result
+}
+
+/// A named filter that can be embedded into any number of queries without copying it.
 #[derive(Clone)]
+pub struct FilterFragment {
+    name: String,
+    filter: Arc<FilterCond>,
+}
+
+impl FilterFragment {
+    pub fn new(name: &str, filter: Filter) -> FilterFragment {
+        FilterFragment {
+            name: name.to_string(),
+            filter: Arc::new(filter.0),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn filter(&self) -> Filter {
+        Filter(FilterCond::Fragment(FragmentCond {
+            filter: self.filter.clone(),
+        }))
+    }
+}
+
+#[enum_dispatch]
+#[derive(Clone, PartialEq)]
 enum FilterCond {
     IdBetween(IdBetweenCond),
     ByteBetween(ByteBetweenCond),
@@ -292,11 +754,13 @@ enum FilterCond {
     FloatBetween(FloatBetweenCond),
     DoubleBetween(DoubleBetweenCond),
 
+    Flags(FlagsCond),
     StringBetween(StringBetweenCond),
     StringStartsWith(StringStartsWithCond),
     StringEndsWith(StringEndsWithCond),
     StringContains(StringContainsCond),
     StringMatches(StringMatchesCond),
+    StringEqual(StringEqualCond),
 
     AnyByteBetween(AnyByteBetweenCond),
     AnyIntBetween(AnyIntBetweenCond),
@@ -309,6 +773,7 @@ enum FilterCond {
     AnyStringEndsWith(AnyStringEndsWithCond),
     AnyStringContains(AnyStringContainsCond),
     AnyStringMatches(AnyStringMatchesCond),
+    AnyStringEqual(AnyStringEqualCond),
 
     ListLength(ListLengthCond),
 
@@ -318,6 +783,7 @@ enum FilterCond {
     Xor(XorCond),
     Not(NotCond),
     Static(StaticCond),
+    Fragment(FragmentCond),
 
     Object(ObjectCond),
     AnyObject(AnyObjectCond),
@@ -331,7 +797,7 @@ trait Condition {
     fn evaluate(&self, id: i64, object: IsarObject, cursors: Option<&IsarCursors>) -> Result<bool>;
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct IdBetweenCond {
     lower: i64,
     upper: i64,
@@ -346,7 +812,7 @@ impl Condition for IdBetweenCond {
 #[macro_export]
 macro_rules! filter_between_struct {
     ($name:ident, $data_type:ident, $type:ty) => {
-        #[derive(Clone)]
+        #[derive(Clone, PartialEq)]
         struct $name {
             upper: $type,
             lower: $type,
@@ -470,7 +936,34 @@ float_filter_between_list!(AnyFloatBetweenCond, read_float_list);
 filter_between_struct!(AnyDoubleBetweenCond, Double, f64);
 float_filter_between_list!(AnyDoubleBetweenCond, read_double_list);
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+struct FlagsCond {
+    offset: usize,
+    data_type: DataType,
+    mask: i64,
+    all: bool,
+}
+
+impl Condition for FlagsCond {
+    fn evaluate(&self, _id: i64, object: IsarObject, _: Option<&IsarCursors>) -> Result<bool> {
+        if object.is_null(self.offset, self.data_type) {
+            return Ok(false);
+        }
+        let value = match self.data_type {
+            DataType::Byte => object.read_byte(self.offset) as i64,
+            DataType::Int => object.read_int(self.offset) as u32 as i64,
+            _ => object.read_long(self.offset),
+        };
+        let matching = value & self.mask;
+        if self.all {
+            Ok(matching == self.mask)
+        } else {
+            Ok(matching != 0)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 struct StringBetweenCond {
     offset: usize,
     lower: Option<Vec<u8>>,
@@ -478,7 +971,7 @@ struct StringBetweenCond {
     case_sensitive: bool,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct AnyStringBetweenCond {
     offset: usize,
     lower: Option<Vec<u8>>,
@@ -557,7 +1050,7 @@ impl Condition for AnyStringBetweenCond {
 macro_rules! string_filter_struct {
     ($name:ident) => {
         paste! {
-            #[derive(Clone)]
+            #[derive(Clone, PartialEq)]
             struct [<$name Cond>] {
                 offset: usize,
                 value: String,
@@ -633,7 +1126,44 @@ string_filter!(StringEndsWith);
 string_filter!(StringContains);
 string_filter!(StringMatches);
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+struct StringEqualCond {
+    offset: usize,
+    value: String,
+    normalizer: StringNormalizer,
+}
+
+#[derive(Clone, PartialEq)]
+struct AnyStringEqualCond {
+    offset: usize,
+    value: String,
+    normalizer: StringNormalizer,
+}
+
+impl Condition for StringEqualCond {
+    fn evaluate(&self, _id: i64, object: IsarObject, _: Option<&IsarCursors>) -> Result<bool> {
+        let result = object
+            .read_string(self.offset)
+            .is_some_and(|value| self.normalizer.normalize(value) == self.value);
+        Ok(result)
+    }
+}
+
+impl Condition for AnyStringEqualCond {
+    fn evaluate(&self, _id: i64, object: IsarObject, _: Option<&IsarCursors>) -> Result<bool> {
+        let list = object.read_string_list(self.offset);
+        if let Some(list) = list {
+            for value in list.into_iter().flatten() {
+                if self.normalizer.normalize(value) == self.value {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[derive(Clone, PartialEq)]
 struct ListLengthCond {
     offset: usize,
     lower: usize,
@@ -655,7 +1185,7 @@ impl Condition for ListLengthCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct NullCond {
     offset: usize,
     data_type: DataType,
@@ -672,7 +1202,7 @@ impl Condition for NullCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct AndCond {
     filters: Vec<FilterCond>,
 }
@@ -688,7 +1218,7 @@ impl Condition for AndCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct OrCond {
     filters: Vec<FilterCond>,
 }
@@ -704,7 +1234,7 @@ impl Condition for OrCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct XorCond {
     filters: Vec<FilterCond>,
 }
@@ -725,7 +1255,7 @@ impl Condition for XorCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct NotCond {
     filter: Box<FilterCond>,
 }
@@ -736,7 +1266,7 @@ impl Condition for NotCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct StaticCond {
     value: bool,
 }
@@ -747,7 +1277,18 @@ impl Condition for StaticCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+struct FragmentCond {
+    filter: Arc<FilterCond>,
+}
+
+impl Condition for FragmentCond {
+    fn evaluate(&self, id: i64, object: IsarObject, cursors: Option<&IsarCursors>) -> Result<bool> {
+        self.filter.evaluate(id, object, cursors)
+    }
+}
+
+#[derive(Clone, PartialEq)]
 struct ObjectCond {
     offset: usize,
     filter: Box<FilterCond>,
@@ -768,7 +1309,7 @@ impl Condition for ObjectCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct AnyObjectCond {
     offset: usize,
     filter: Option<Box<FilterCond>>,
@@ -803,7 +1344,7 @@ impl Condition for AnyObjectCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct AnyLinkCond {
     link: IsarLink,
     filter: Box<FilterCond>,
@@ -830,7 +1371,7 @@ impl Condition for AnyLinkCond {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct LinkLengthCond {
     link: IsarLink,
     lower: usize,
@@ -857,3 +1398,116 @@ impl Condition for LinkLengthCond {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::object_builder::ObjectBuilder;
+
+    #[test]
+    fn test_dedup_fragments() {
+        let fragment = FilterFragment::new("active", Filter::id(1, 5));
+        let equal = FilterFragment::new("also_active", Filter::id(1, 5));
+        let different = FilterFragment::new("active", Filter::id(1, 6));
+        let filters = vec![
+            fragment.filter(),
+            Filter::id(2, 3),
+            fragment.filter(),
+            equal.filter(),
+            different.filter(),
+        ];
+        let deduped = dedup_fragments(filters.clone());
+        assert_eq!(deduped.len(), 3);
+
+        if let FilterCond::Xor(xor) = Filter::xor(filters).0 {
+            assert_eq!(xor.filters.len(), 5);
+        } else {
+            panic!("expected xor");
+        }
+    }
+
+    #[test]
+    fn test_flags() {
+        let byte = Property::debug(DataType::Byte, 2);
+        let int = Property::debug(DataType::Int, 3);
+        let long = Property::debug(DataType::Long, 7);
+        let properties = [byte.clone(), int.clone(), long.clone()];
+        let mut ob = ObjectBuilder::new(&properties, None);
+        ob.write_byte(byte.offset, 0b1010_0000);
+        ob.write_int(int.offset, 0b0110);
+        ob.write_long(long.offset, 1 << 62);
+        let object = ob.finish();
+        let matches = |filter: Result<Filter>| filter.unwrap().evaluate(0, object, None).unwrap();
+
+        assert!(matches(Filter::has_flag(&byte, 0b1000_0000)));
+        assert!(!matches(Filter::has_flag(&byte, 0b0100_0000)));
+        assert!(matches(Filter::has_flag(&int, 0b0100)));
+        assert!(!matches(Filter::has_flag(&int, 0b0001)));
+        assert!(matches(Filter::has_flag(&long, 1 << 62)));
+        assert!(Filter::has_flag(&int, 0b0110).is_err());
+        assert!(Filter::has_flag(&int, 0).is_err());
+
+        assert!(matches(Filter::has_any_flag(&int, 0b1100)));
+        assert!(!matches(Filter::has_any_flag(&int, 0b1001)));
+        assert!(matches(Filter::has_all_flags(&int, 0b0110)));
+        assert!(!matches(Filter::has_all_flags(&int, 0b1110)));
+        assert!(matches(Filter::has_all_flags(&byte, 0b1010_0000)));
+        assert!(!matches(Filter::has_any_flag(&long, 1)));
+
+        let string = Property::debug(DataType::String, 2);
+        assert!(Filter::has_flag(&string, 1).is_err());
+    }
+
+    #[test]
+    fn test_flags_sign_bit() {
+        let int = Property::debug(DataType::Int, 2);
+        let long = Property::debug(DataType::Long, 6);
+        let byte = Property::debug(DataType::Byte, 14);
+        assert!(Filter::has_flag(&int, 1 << 31).is_err());
+        assert!(Filter::has_any_flag(&int, 1 << 32).is_err());
+        assert!(Filter::has_all_flags(&int, -1).is_err());
+        assert!(Filter::has_flag(&long, i64::MIN).is_err());
+        assert!(Filter::has_flag(&byte, 1 << 8).is_err());
+        assert!(Filter::has_all_flags(&int, i32::MAX as i64).is_ok());
+
+        let properties = [int.clone(), long.clone(), byte.clone()];
+        let mut ob = ObjectBuilder::new(&properties, None);
+        ob.write_null(int.offset, DataType::Int);
+        ob.write_null(long.offset, DataType::Long);
+        ob.write_byte(byte.offset, 0);
+        let object = ob.finish();
+        let matches = |filter: Result<Filter>| filter.unwrap().evaluate(0, object, None).unwrap();
+        assert!(!matches(Filter::has_all_flags(&int, 0)));
+        assert!(!matches(Filter::has_all_flags(&long, 0)));
+    }
+
+    #[test]
+    fn test_no_case_collation() {
+        let binary = Property::debug(DataType::String, 2);
+        let mut no_case = binary.clone();
+        no_case.case_sensitive = false;
+        let mut ob = ObjectBuilder::new(&[binary.clone()], None);
+        ob.write_string(binary.offset, Some("Hello"));
+        let object = ob.finish();
+        let matches = |filter: Result<Filter>| filter.unwrap().evaluate(0, object, None).unwrap();
+
+        let sensitive = StringNormalizer::new(true, false, false, false);
+        for (property, expected) in [(&binary, false), (&no_case, true)] {
+            let between = Filter::string(property, Some("HELLO"), Some("HELLO"), true);
+            assert_eq!(matches(between), expected);
+            let bytes = Some(b"HELLO".to_vec());
+            let between = Filter::byte_string(property, bytes.clone(), bytes, true);
+            assert_eq!(matches(between), expected);
+            let equal = Filter::string_equal(property, "HELLO", sensitive);
+            assert_eq!(matches(equal), expected);
+            let starts_with = Filter::string_starts_with(property, "HE", true);
+            assert_eq!(matches(starts_with), expected);
+            let ends_with = Filter::string_ends_with(property, "LO", true);
+            assert_eq!(matches(ends_with), expected);
+            let contains = Filter::string_contains(property, "ELL", true);
+            assert_eq!(matches(contains), expected);
+            let wildcard = Filter::string_matches(property, "H*O", true);
+            assert_eq!(matches(wildcard), expected);
+        }
+    }
+}
@@ -3,6 +3,7 @@ use crate::error::Result;
 use crate::mdbx::db::Db;
 use crate::object::id::BytesToId;
 use crate::object::isar_object::IsarObject;
+use crate::query::query_ast::WhereClauseAst;
 use crate::query::Sort;
 use intmap::IntMap;
 
@@ -32,6 +33,65 @@ impl IdWhereClause {
         self.lower <= id && self.upper >= id
     }
 
+    /// Narrows the clause to the ids that come after `id` in iteration order.
+    pub(crate) fn seek_after(&self, id: i64) -> Self {
+        let mut wc = self.clone();
+        if self.sort == Sort::Ascending {
+            if let Some(lower) = id.checked_add(1) {
+                wc.lower = wc.lower.max(lower);
+            } else {
+                wc.upper = i64::MIN;
+                wc.lower = i64::MAX;
+            }
+        } else if let Some(upper) = id.checked_sub(1) {
+            wc.upper = wc.upper.min(upper);
+        } else {
+            wc.upper = i64::MIN;
+            wc.lower = i64::MAX;
+        }
+        wc
+    }
+
+    pub(crate) fn to_ast(&self) -> WhereClauseAst {
+        WhereClauseAst::Id {
+            lower: self.lower,
+            upper: self.upper,
+            descending: self.sort == Sort::Descending,
+        }
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        format!("Id({}..={})", self.lower, self.upper)
+    }
+
+    pub(crate) fn iter_ids<F>(
+        &self,
+        cursors: &IsarCursors,
+        mut result_ids: Option<&mut IntMap<()>>,
+        mut callback: F,
+    ) -> Result<bool>
+    where
+        F: FnMut(i64) -> Result<bool>,
+    {
+        let mut cursor = cursors.get_cursor(self.db)?;
+        cursor.iter_between(
+            &self.lower,
+            &self.upper,
+            false,
+            false,
+            self.sort == Sort::Ascending,
+            |_, id_bytes, _| {
+                let id = id_bytes.to_id();
+                if let Some(result_ids) = result_ids.as_deref_mut() {
+                    if !result_ids.insert_checked(id as u64, ()) {
+                        return Ok(true);
+                    }
+                }
+                callback(id)
+            },
+        )
+    }
+
     pub(crate) fn iter<'txn, 'env, F>(
         &self,
         cursors: &IsarCursors<'txn, 'env>,
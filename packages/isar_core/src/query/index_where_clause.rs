@@ -4,7 +4,9 @@ use crate::index::index_key::IndexKey;
 use crate::index::index_key_builder::IndexKeyBuilder;
 use crate::index::IsarIndex;
 use crate::mdbx::db::Db;
+use crate::mdbx::Key;
 use crate::object::isar_object::IsarObject;
+use crate::query::query_ast::WhereClauseAst;
 use crate::query::Sort;
 use intmap::IntMap;
 
@@ -67,6 +69,20 @@ impl IndexWhereClause {
         )
     }
 
+    pub fn index_name(&self) -> &str {
+        &self.index.name
+    }
+
+    pub fn to_ast(&self) -> WhereClauseAst {
+        WhereClauseAst::Index {
+            index: self.index.name.clone(),
+            lower: self.lower_key.as_bytes().to_vec(),
+            upper: self.upper_key.as_bytes().to_vec(),
+            skip_duplicates: self.skip_duplicates,
+            descending: self.sort == Sort::Descending,
+        }
+    }
+
     pub fn iter<'txn, 'env, F>(
         &self,
         cursors: &IsarCursors<'txn, 'env>,
@@ -2,6 +2,7 @@ use crate::cursor::IsarCursors;
 use crate::error::Result;
 use crate::link::IsarLink;
 use crate::object::isar_object::IsarObject;
+use crate::query::query_ast::WhereClauseAst;
 use intmap::IntMap;
 
 #[derive(Clone)]
@@ -15,6 +16,37 @@ impl LinkWhereClause {
         Ok(LinkWhereClause { link, id })
     }
 
+    pub fn link_name(&self) -> &str {
+        &self.link.name
+    }
+
+    pub fn to_ast(&self) -> WhereClauseAst {
+        WhereClauseAst::Link {
+            link_id: self.link.id,
+            link: self.link.name.clone(),
+            id: self.id,
+        }
+    }
+
+    pub fn iter_ids<F>(
+        &self,
+        cursors: &IsarCursors,
+        mut result_ids: Option<&mut IntMap<()>>,
+        mut callback: F,
+    ) -> Result<bool>
+    where
+        F: FnMut(i64) -> Result<bool>,
+    {
+        self.link.iter_ids(cursors, self.id, |_, id| {
+            if let Some(result_ids) = result_ids.as_deref_mut() {
+                if !result_ids.insert_checked(id as u64, ()) {
+                    return Ok(true);
+                }
+            }
+            callback(id)
+        })
+    }
+
     pub fn iter<'txn, 'env, F>(
         &self,
         cursors: &IsarCursors<'txn, 'env>,
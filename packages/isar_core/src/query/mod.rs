@@ -1,23 +1,40 @@
 use intmap::IntMap;
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::cmp::Ordering;
+use std::mem::size_of;
+use std::sync::Arc;
+use std::time::Instant;
 
 use crate::collection::IsarCollection;
 use crate::cursor::IsarCursors;
-use crate::error::Result;
+use crate::error::{IsarError, Result};
 use crate::object::isar_object::IsarObject;
 use crate::object::json_encode_decode::JsonEncodeDecode;
 use crate::object::property::Property;
 use crate::query::filter::Filter;
+use crate::query::query_ast::{DistinctAst, QueryAst, SortAst};
+use crate::query::result_chunks::ChunkWriter;
+use crate::query::sort_expression::SortExpression;
 use crate::query::where_clause::WhereClause;
+use crate::stats::IndexAdvisor;
 use crate::txn::IsarTxn;
 
-mod fast_wild_match;
+#[cfg(feature = "columnar")]
+pub mod column_batch;
+pub(crate) mod fast_wild_match;
 pub mod filter;
 mod id_where_clause;
 mod index_where_clause;
 mod link_where_clause;
+pub mod query_ast;
 pub mod query_builder;
+#[cfg(feature = "query-log")]
+pub mod query_log;
+pub mod query_parser;
+mod result_chunks;
+pub mod sort_expression;
+pub mod string_normalizer;
 mod where_clause;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -31,16 +48,141 @@ pub enum Case {
     Insensitive,
 }
 
+pub type ResultLimitCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+#[derive(Clone)]
+pub enum ResultLimitAction {
+    Warn(ResultLimitCallback),
+    Error,
+}
+
+#[derive(Clone)]
+pub struct ResultLimit {
+    pub max_rows: Option<usize>,
+    pub max_bytes: Option<usize>,
+    pub action: ResultLimitAction,
+}
+
+struct ResultCounter<'a> {
+    limit: Option<&'a ResultLimit>,
+    rows: usize,
+    bytes: usize,
+    exceeded: bool,
+}
+
+impl<'a> ResultCounter<'a> {
+    fn new(limit: Option<&'a ResultLimit>) -> Self {
+        ResultCounter {
+            limit,
+            rows: 0,
+            bytes: 0,
+            exceeded: false,
+        }
+    }
+
+    fn add(&mut self, object: IsarObject) -> Result<()> {
+        if let Some(limit) = self.limit {
+            self.rows += 1;
+            self.bytes += object.len();
+            let exceeded = limit.max_rows.is_some_and(|max| self.rows > max)
+                || limit.max_bytes.is_some_and(|max| self.bytes > max);
+            if exceeded && !self.exceeded {
+                self.exceeded = true;
+                match &limit.action {
+                    ResultLimitAction::Warn(callback) => callback(self.rows, self.bytes),
+                    ResultLimitAction::Error => {
+                        return Err(IsarError::ResultLimitExceeded {
+                            rows: self.rows,
+                            bytes: self.bytes,
+                        })
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the query deadline every 128 steps to keep `Instant::now()` out of hot loops.
+pub(crate) struct DeadlineCheck {
+    deadline: Option<Instant>,
+    countdown: u32,
+    reached: bool,
+}
+
+impl DeadlineCheck {
+    const INTERVAL: u32 = 128;
+
+    pub fn new(deadline: Option<Instant>) -> Self {
+        DeadlineCheck {
+            deadline,
+            countdown: Self::INTERVAL,
+            reached: false,
+        }
+    }
+
+    pub fn expired(&mut self) -> bool {
+        if let Some(deadline) = self.deadline {
+            self.countdown -= 1;
+            if self.countdown == 0 {
+                self.countdown = Self::INTERVAL;
+                self.reached |= Instant::now() >= deadline;
+            }
+        }
+        self.reached
+    }
+
+    /// Checks the deadline without waiting for the next interval.
+    pub fn expired_now(&mut self) -> bool {
+        if let Some(deadline) = self.deadline {
+            self.reached |= Instant::now() >= deadline;
+        }
+        self.reached
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryExplain {
+    pub where_clauses: Vec<String>,
+    pub filtered: bool,
+    pub sorted_in_memory: bool,
+    pub distinct: bool,
+    pub deduplicated: bool,
+    pub index_only: bool,
+}
+
+/// Result of a profiled query execution. Objects are read directly from the memory map so only
+/// the sort buffer and the copied results are accounted.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryProfile {
+    pub explain: QueryExplain,
+    pub rows: usize,
+    pub duration_micros: u64,
+    pub sort_buffer_bytes: usize,
+    pub result_bytes: usize,
+}
+
+impl QueryProfile {
+    pub fn memory_bytes(&self) -> usize {
+        self.sort_buffer_bytes + self.result_bytes
+    }
+}
+
 #[derive(Clone)]
 pub struct Query {
     instance_id: u64,
     where_clauses: Vec<WhereClause>,
     where_clauses_dup: bool,
     filter: Option<Filter>,
-    sort: Vec<(Property, Sort)>,
+    sort: Vec<(SortExpression, Sort, bool)>,
     distinct: Vec<(Property, bool)>,
     offset: usize,
     limit: usize,
+    seek_after: Option<i64>,
+    index_advisor: Arc<IndexAdvisor>,
+    unindexed_properties: Vec<String>,
 }
 
 impl<'txn> Query {
@@ -49,10 +191,12 @@ impl<'txn> Query {
         instance_id: u64,
         where_clauses: Vec<WhereClause>,
         filter: Option<Filter>,
-        sort: Vec<(Property, Sort)>,
+        sort: Vec<(SortExpression, Sort, bool)>,
         distinct: Vec<(Property, bool)>,
         offset: usize,
         limit: usize,
+        index_advisor: Arc<IndexAdvisor>,
+        unindexed_properties: Vec<String>,
     ) -> Self {
         let where_clauses_dup = Self::check_where_clauses_duplicates(&where_clauses);
         Query {
@@ -64,7 +208,39 @@ impl<'txn> Query {
             distinct,
             offset,
             limit,
+            seek_after: None,
+            index_advisor,
+            unindexed_properties,
+        }
+    }
+
+    /// Returns a query that continues after the result with the given id. Queries that iterate
+    /// a single id where clause are re-planned with a narrower id range. Other queries skip
+    /// results until the id was seen and return nothing if it is not part of the results.
+    pub fn with_seek(&self, after_id: i64) -> Query {
+        let mut query = self.clone();
+        query.offset = 0;
+        match self.where_clauses.as_slice() {
+            [WhereClause::Id(wc)] if self.sort.is_empty() && self.seek_after.is_none() => {
+                query.where_clauses = vec![WhereClause::Id(wc.seek_after(after_id))];
+            }
+            _ => query.seek_after = Some(after_id),
+        }
+        query
+    }
+
+    /// Returns a query that starts `count` results later.
+    pub fn with_skip(&self, count: usize) -> Query {
+        let mut query = self.clone();
+        query.offset = self.offset.saturating_add(count);
+        if self.limit != usize::MAX {
+            query.limit = self.limit.saturating_sub(count);
         }
+        query
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.limit
     }
 
     fn check_where_clauses_duplicates(where_clauses: &[WhereClause]) -> bool {
@@ -81,9 +257,146 @@ impl<'txn> Query {
         false
     }
 
+    /// Whether ids can be produced from the where clauses alone without reading objects.
+    fn is_index_only(&self, ignore_sort: bool) -> bool {
+        self.filter.is_none()
+            && self.distinct.is_empty()
+            && self.seek_after.is_none()
+            && (ignore_sort || self.sort.is_empty())
+    }
+
+    pub fn explain(&self) -> QueryExplain {
+        QueryExplain {
+            where_clauses: self.where_clauses.iter().map(|wc| wc.describe()).collect(),
+            filtered: self.filter.is_some(),
+            sorted_in_memory: !self.sort.is_empty(),
+            distinct: !self.distinct.is_empty(),
+            deduplicated: self.where_clauses_dup,
+            index_only: self.is_index_only(false),
+        }
+    }
+
+    /// Describes the shape of the plan without bound values so queries that only differ in their
+    /// bounds share a signature. Store it to detect planner regressions with [check_plan].
+    ///
+    /// [check_plan]: Self::check_plan
+    pub fn plan_signature(&self) -> String {
+        let explain = self.explain();
+        let mut parts: Vec<String> = self.where_clauses.iter().map(|wc| wc.scan_type()).collect();
+        let flags = [
+            (explain.filtered, "filter"),
+            (explain.sorted_in_memory, "sort"),
+            (explain.distinct, "distinct"),
+            (explain.deduplicated, "dedup"),
+            (explain.index_only, "indexOnly"),
+        ];
+        for (set, flag) in flags {
+            if set {
+                parts.push(flag.to_string());
+            }
+        }
+        parts.join(",")
+    }
+
+    pub fn check_plan(&self, expected: &str) -> Result<()> {
+        let actual = self.plan_signature();
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(IsarError::PlanChanged {
+                expected: expected.to_string(),
+                actual,
+            })
+        }
+    }
+
+    /// Exports the query. `collection` is the collection the query was built for and
+    /// `collections` are all collections of the instance.
+    pub fn to_ast(
+        &self,
+        collection: &IsarCollection,
+        collections: &[IsarCollection],
+    ) -> Result<QueryAst> {
+        let filter = if let Some(filter) = &self.filter {
+            Some(filter.to_ast(collection, &collection.properties, collections)?)
+        } else {
+            None
+        };
+        let sort = self
+            .sort
+            .iter()
+            .map(|(expression, sort, case_sensitive)| SortAst {
+                expression: expression.into(),
+                descending: *sort == Sort::Descending,
+                case_sensitive: *case_sensitive,
+            })
+            .collect();
+        let distinct = self
+            .distinct
+            .iter()
+            .map(|(property, case_sensitive)| DistinctAst {
+                property: property.name.clone(),
+                case_sensitive: *case_sensitive,
+            })
+            .collect();
+        Ok(QueryAst {
+            where_clauses: self.where_clauses.iter().map(|wc| wc.to_ast()).collect(),
+            filter,
+            sort,
+            distinct,
+            offset: self.offset,
+            limit: if self.limit == usize::MAX {
+                None
+            } else {
+                Some(self.limit)
+            },
+            seek_after: self.seek_after,
+        })
+    }
+
+    fn execute_ids<F>(
+        &self,
+        cursors: &IsarCursors,
+        deadline: Option<Instant>,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(i64) -> Result<bool>,
+    {
+        let mut result_ids = if self.where_clauses_dup {
+            Some(IntMap::new())
+        } else {
+            None
+        };
+
+        let offset = self.offset;
+        let max_count = self.limit.saturating_add(offset);
+        let mut count = 0;
+        let mut deadline = DeadlineCheck::new(deadline);
+        for where_clause in &self.where_clauses {
+            let result = where_clause.iter_ids(cursors, result_ids.as_mut(), |id| {
+                count += 1;
+                if deadline.expired() {
+                    return Err(IsarError::Timeout {});
+                }
+                if count > max_count || (count > offset && !callback(id)?) {
+                    Ok(false)
+                } else {
+                    Ok(true)
+                }
+            })?;
+            if !result {
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn execute_raw<'env, F>(
         &self,
         cursors: &IsarCursors<'txn, 'env>,
+        deadline: Option<Instant>,
         mut callback: F,
     ) -> Result<()>
     where
@@ -98,8 +411,12 @@ impl<'txn> Query {
         let static_filter = Filter::stat(true);
         let filter = self.filter.as_ref().unwrap_or(&static_filter);
 
+        let mut deadline = DeadlineCheck::new(deadline);
         for where_clause in &self.where_clauses {
             let result = where_clause.iter(cursors, result_ids.as_mut(), |id, object| {
+                if deadline.expired() {
+                    return Err(IsarError::Timeout {});
+                }
                 if filter.evaluate(id, object, Some(cursors))? {
                     callback(id, object)
                 } else {
@@ -117,6 +434,7 @@ impl<'txn> Query {
     fn execute_unsorted<'env, F>(
         &self,
         cursors: &IsarCursors<'txn, 'env>,
+        deadline: Option<Instant>,
         callback: F,
     ) -> Result<()>
     where
@@ -125,10 +443,10 @@ impl<'txn> Query {
         if !self.distinct.is_empty() {
             let callback = self.add_distinct_unsorted(callback);
             let callback = self.add_offset_limit_unsorted(callback);
-            self.execute_raw(cursors, callback)
+            self.execute_raw(cursors, deadline, callback)
         } else {
             let callback = self.add_offset_limit_unsorted(callback);
-            self.execute_raw(cursors, callback)
+            self.execute_raw(cursors, deadline, callback)
         }
     }
 
@@ -169,7 +487,14 @@ impl<'txn> Query {
         let offset = self.offset;
         let max_count = self.limit.saturating_add(offset);
         let mut count = 0;
+        let mut seek_after = self.seek_after;
         move |id, value| {
+            if let Some(seek_id) = seek_after {
+                if id == seek_id {
+                    seek_after = None;
+                }
+                return Ok(true);
+            }
             count += 1;
             if count > max_count || (count > offset && !callback(id, value)?) {
                 Ok(false)
@@ -182,16 +507,26 @@ impl<'txn> Query {
     fn execute_sorted<'env>(
         &self,
         cursors: &IsarCursors<'txn, 'env>,
+        deadline: Option<Instant>,
+        sort_buffer_bytes: &mut usize,
     ) -> Result<Vec<(i64, IsarObject<'txn>)>> {
         let mut results = vec![];
-        self.execute_raw(cursors, |id, object| {
+        self.execute_raw(cursors, deadline, |id, object| {
             results.push((id, object));
             Ok(true)
         })?;
+        *sort_buffer_bytes = results.capacity() * size_of::<(i64, IsarObject)>();
 
+        let mut deadline = DeadlineCheck::new(deadline);
+        let mut timed_out = false;
         results.sort_unstable_by(|(_, o1), (_, o2)| {
-            for (p, sort) in &self.sort {
-                let ord = o1.compare_property(o2, p.offset, p.data_type);
+            // the comparator cannot fail so the remaining comparisons are skipped instead
+            if timed_out || deadline.expired() {
+                timed_out = true;
+                return Ordering::Equal;
+            }
+            for (expression, sort, case_sensitive) in &self.sort {
+                let ord = expression.compare(o1, o2, *case_sensitive);
                 if ord != Ordering::Equal {
                     return if *sort == Sort::Ascending {
                         ord
@@ -202,6 +537,9 @@ impl<'txn> Query {
             }
             Ordering::Equal
         });
+        if timed_out {
+            return Err(IsarError::Timeout {});
+        }
 
         if !self.distinct.is_empty() {
             Ok(self.add_distinct_sorted(results))
@@ -229,7 +567,18 @@ impl<'txn> Query {
         &self,
         results: Vec<(i64, IsarObject<'txn>)>,
     ) -> impl IntoIterator<Item = (i64, IsarObject<'txn>)> {
-        results.into_iter().skip(self.offset).take(self.limit)
+        let skip = if let Some(seek_id) = self.seek_after {
+            results
+                .iter()
+                .position(|(id, _)| *id == seek_id)
+                .map_or(results.len(), |index| index + 1)
+        } else {
+            0
+        };
+        results
+            .into_iter()
+            .skip(skip.saturating_add(self.offset))
+            .take(self.limit)
     }
 
     pub(crate) fn maybe_matches_wc_filter(&self, id: i64, object: IsarObject) -> bool {
@@ -248,20 +597,116 @@ impl<'txn> Query {
         }
     }
 
-    pub fn find_while<F>(&self, txn: &'txn mut IsarTxn, mut callback: F) -> Result<()>
+    pub fn find_while<F>(&self, txn: &'txn mut IsarTxn, callback: F) -> Result<()>
+    where
+        F: FnMut(i64, IsarObject<'txn>) -> bool,
+    {
+        self.find_while_internal(txn, true, &mut 0, callback)
+    }
+
+    /// Executes the query like [Query::find_while] and measures the time and approximate
+    /// memory it needs to return all results.
+    pub fn profile(&self, txn: &'txn mut IsarTxn) -> Result<QueryProfile> {
+        let start = Instant::now();
+        let mut rows = 0;
+        let mut result_bytes = 0;
+        let mut sort_buffer_bytes = 0;
+        self.find_while_internal(txn, true, &mut sort_buffer_bytes, |_, object| {
+            rows += 1;
+            result_bytes += size_of::<i64>() + object.len();
+            true
+        })?;
+        Ok(QueryProfile {
+            explain: self.explain(),
+            rows,
+            duration_micros: start.elapsed().as_micros() as u64,
+            sort_buffer_bytes,
+            result_bytes,
+        })
+    }
+
+    /// Delivers the results as chunks of at most `max_chunk_size` bytes together with their
+    /// row count. Each row consists of the i64 id, the u32 object length and the object.
+    pub fn find_chunks<F>(
+        &self,
+        txn: &'txn mut IsarTxn,
+        max_chunk_size: usize,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(Vec<u8>, u32) -> bool,
+    {
+        let mut writer = ChunkWriter::new(max_chunk_size);
+        let mut cont = true;
+        self.find_while_internal(txn, true, &mut 0, |id, object| {
+            if let Some((chunk, rows)) = writer.add(id, object.as_bytes()) {
+                cont = callback(chunk, rows);
+            }
+            cont
+        })?;
+        if cont {
+            if let Some((chunk, rows)) = writer.finish() {
+                callback(chunk, rows);
+            }
+        }
+        Ok(())
+    }
+
+    /// Materializes the given properties of all results into columns.
+    #[cfg(feature = "columnar")]
+    pub fn find_columns(
+        &self,
+        txn: &'txn mut IsarTxn,
+        properties: &[&Property],
+    ) -> Result<column_batch::ColumnBatch> {
+        let mut batch = column_batch::ColumnBatch::new(properties)?;
+        self.find_while_internal(txn, true, &mut 0, |id, object| {
+            batch.push(id, object);
+            true
+        })?;
+        Ok(batch)
+    }
+
+    /// Like [Query::find_while] but ignores the result limit of the instance. Use it for
+    /// queries that do not keep the returned objects like aggregations.
+    pub fn scan_while<F>(&self, txn: &'txn mut IsarTxn, callback: F) -> Result<()>
     where
         F: FnMut(i64, IsarObject<'txn>) -> bool,
     {
-        txn.read(self.instance_id, |cursors| {
+        self.find_while_internal(txn, false, &mut 0, callback)
+    }
+
+    fn find_while_internal<F>(
+        &self,
+        txn: &'txn mut IsarTxn,
+        apply_result_limit: bool,
+        sort_buffer_bytes: &mut usize,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(i64, IsarObject<'txn>) -> bool,
+    {
+        for property in &self.unindexed_properties {
+            self.index_advisor.record(property);
+        }
+        txn.read_query(self.instance_id, |cursors, deadline, result_limit| {
+            let result_limit = if apply_result_limit && self.limit == usize::MAX {
+                result_limit
+            } else {
+                None
+            };
+            let mut counter = ResultCounter::new(result_limit);
             if self.sort.is_empty() {
-                self.execute_unsorted(cursors, |id, object| {
+                self.execute_unsorted(cursors, deadline, |id, object| {
+                    counter.add(object)?;
                     let cont = callback(id, object);
                     Ok(cont)
                 })?;
             } else {
-                let results = self.execute_sorted(cursors)?;
+                let results = self.execute_sorted(cursors, deadline, sort_buffer_bytes)?;
                 let results_iter = self.add_offset_limit_sorted(results);
                 for (id, object) in results_iter {
+                    counter.add(object)?;
                     if !callback(id, object) {
                         break;
                     }
@@ -271,6 +716,41 @@ impl<'txn> Query {
         })
     }
 
+    pub fn find_first(&self, txn: &'txn mut IsarTxn) -> Result<Option<(i64, IsarObject<'txn>)>> {
+        let mut result = None;
+        self.find_while(txn, |id, object| {
+            result = Some((id, object));
+            false
+        })?;
+        Ok(result)
+    }
+
+    pub fn find_last(&self, txn: &'txn mut IsarTxn) -> Result<Option<(i64, IsarObject<'txn>)>> {
+        if !self.sort.is_empty()
+            && self.distinct.is_empty()
+            && self.offset == 0
+            && self.limit == usize::MAX
+            && self.seek_after.is_none()
+        {
+            let mut reversed = self.clone();
+            for (_, sort, _) in &mut reversed.sort {
+                *sort = if *sort == Sort::Ascending {
+                    Sort::Descending
+                } else {
+                    Sort::Ascending
+                };
+            }
+            return reversed.find_first(txn);
+        }
+
+        let mut result = None;
+        self.scan_while(txn, |id, object| {
+            result = Some((id, object));
+            true
+        })?;
+        Ok(result)
+    }
+
     pub fn find_all_vec(&self, txn: &'txn mut IsarTxn) -> Result<Vec<(i64, IsarObject<'txn>)>> {
         let mut results = vec![];
         self.find_while(txn, |id, object| {
@@ -280,15 +760,73 @@ impl<'txn> Query {
         Ok(results)
     }
 
+    /// Iterates the ids of the query results. If the query has no filter, sort or distinct
+    /// clause, the objects are not read at all (index-only scan).
+    pub fn find_ids_while<F>(&self, txn: &mut IsarTxn, callback: F) -> Result<()>
+    where
+        F: FnMut(i64) -> bool,
+    {
+        self.find_ids_internal(txn, false, callback)
+    }
+
+    fn find_ids_internal<F>(
+        &self,
+        txn: &mut IsarTxn,
+        ignore_sort: bool,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(i64) -> bool,
+    {
+        if self.is_index_only(ignore_sort) {
+            txn.read_query(self.instance_id, |cursors, deadline, _| {
+                self.execute_ids(cursors, deadline, |id| Ok(callback(id)))
+            })
+        } else {
+            self.scan_while(txn, |id, _| callback(id))
+        }
+    }
+
     pub fn count(&self, txn: &mut IsarTxn) -> Result<u32> {
         let mut counter = 0;
-        self.find_while(txn, |_, _| {
+        self.find_ids_internal(txn, true, |_| {
             counter += 1;
             true
         })?;
         Ok(counter)
     }
 
+    /// Deletes the first `count` results and returns them in the format of
+    /// [Query::find_chunks]. Useful for queues that consume their entries.
+    pub fn pop(
+        &self,
+        txn: &mut IsarTxn,
+        collection: &IsarCollection,
+        count: usize,
+        max_chunk_size: usize,
+    ) -> Result<Vec<(Vec<u8>, u32)>> {
+        let mut writer = ChunkWriter::new(max_chunk_size);
+        let mut chunks = vec![];
+        let mut ids = vec![];
+        self.scan_while(txn, |id, object| {
+            if ids.len() >= count {
+                return false;
+            }
+            ids.push(id);
+            if let Some(chunk) = writer.add(id, object.as_bytes()) {
+                chunks.push(chunk);
+            }
+            ids.len() < count
+        })?;
+        chunks.extend(writer.finish());
+
+        for id in ids {
+            collection.delete(txn, id)?;
+        }
+        Ok(chunks)
+    }
+
+    /// Exports all results. The result limit of the instance does not apply to exports.
     pub fn export_json(
         &self,
         txn: &mut IsarTxn,
@@ -297,7 +835,7 @@ impl<'txn> Query {
         primitive_null: bool,
     ) -> Result<Value> {
         let mut items = vec![];
-        self.find_while(txn, |id, object| {
+        self.scan_while(txn, |id, object| {
             let mut json = JsonEncodeDecode::encode(
                 &collection.properties,
                 &collection.embedded_properties,
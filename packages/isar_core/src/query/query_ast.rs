@@ -0,0 +1,507 @@
+use crate::collection::IsarCollection;
+use crate::error::{illegal_arg, Result};
+use crate::index::index_key::IndexKey;
+use crate::object::data_type::DataType;
+use crate::object::property::Property;
+use crate::query::filter::Filter;
+use crate::query::sort_expression::{ArithmeticOp, SortExpression};
+use crate::query::string_normalizer::StringNormalizer;
+use crate::query::{Query, Sort};
+use serde::{Deserialize, Serialize};
+
+/// Serializable form of a query. Properties, indexes and links are referenced by name so the
+/// structure can be inspected or rewritten without access to the engine.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryAst {
+    pub where_clauses: Vec<WhereClauseAst>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<FilterAst>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sort: Vec<SortAst>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub distinct: Vec<DistinctAst>,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seek_after: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WhereClauseAst {
+    Id {
+        lower: i64,
+        upper: i64,
+        descending: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Index {
+        index: String,
+        lower: Vec<u8>,
+        upper: Vec<u8>,
+        skip_duplicates: bool,
+        descending: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Link { link_id: u64, link: String, id: i64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FilterAst {
+    Id {
+        lower: i64,
+        upper: i64,
+    },
+    ByteBetween {
+        property: String,
+        lower: u8,
+        upper: u8,
+    },
+    IntBetween {
+        property: String,
+        lower: i32,
+        upper: i32,
+    },
+    LongBetween {
+        property: String,
+        lower: i64,
+        upper: i64,
+    },
+    FloatBetween {
+        property: String,
+        lower: f32,
+        upper: f32,
+    },
+    DoubleBetween {
+        property: String,
+        lower: f64,
+        upper: f64,
+    },
+    Flags {
+        property: String,
+        mask: i64,
+        all: bool,
+    },
+    /// Bounds are the encoded bytes of the strings.
+    #[serde(rename_all = "camelCase")]
+    StringBetween {
+        property: String,
+        lower: Option<Vec<u8>>,
+        upper: Option<Vec<u8>>,
+        case_sensitive: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    StringStartsWith {
+        property: String,
+        value: String,
+        case_sensitive: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    StringEndsWith {
+        property: String,
+        value: String,
+        case_sensitive: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    StringContains {
+        property: String,
+        value: String,
+        case_sensitive: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    StringMatches {
+        property: String,
+        value: String,
+        case_sensitive: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    StringEqual {
+        property: String,
+        value: String,
+        case_sensitive: bool,
+        trim: bool,
+        ignore_whitespace: bool,
+        ignore_diacritics: bool,
+    },
+    ListLength {
+        property: String,
+        lower: usize,
+        upper: usize,
+    },
+    IsNull {
+        property: String,
+    },
+    And {
+        filters: Vec<FilterAst>,
+    },
+    Or {
+        filters: Vec<FilterAst>,
+    },
+    Xor {
+        filters: Vec<FilterAst>,
+    },
+    Not {
+        filter: Box<FilterAst>,
+    },
+    Static {
+        value: bool,
+    },
+    /// Matches embedded objects. Without a filter, lists match if they contain null.
+    Object {
+        property: String,
+        filter: Option<Box<FilterAst>>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Link {
+        link_id: u64,
+        link: String,
+        filter: Box<FilterAst>,
+    },
+    #[serde(rename_all = "camelCase")]
+    LinkLength {
+        link_id: u64,
+        link: String,
+        lower: usize,
+        upper: usize,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SortAst {
+    pub expression: SortExpressionAst,
+    pub descending: bool,
+    pub case_sensitive: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SortExpressionAst {
+    Property {
+        property: String,
+    },
+    Arithmetic {
+        op: ArithmeticOp,
+        left: Box<SortExpressionAst>,
+        right: Box<SortExpressionAst>,
+    },
+    Coalesce {
+        expressions: Vec<SortExpressionAst>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DistinctAst {
+    pub property: String,
+    pub case_sensitive: bool,
+}
+
+fn find_property<'a>(properties: &'a [Property], name: &str) -> Result<&'a Property> {
+    if let Some(property) = properties.iter().find(|p| p.name == name) {
+        Ok(property)
+    } else {
+        illegal_arg(&format!("Unknown property \"{}\".", name))
+    }
+}
+
+fn find_link_owner(collections: &[IsarCollection], link_id: u64) -> Result<&IsarCollection> {
+    if let Some(collection) = collections
+        .iter()
+        .find(|c| c.get_link_backlink(link_id).is_ok())
+    {
+        Ok(collection)
+    } else {
+        illegal_arg("IsarLink does not exist")
+    }
+}
+
+impl QueryAst {
+    /// Builds the query for `collection`. `collections` are all collections of the instance and
+    /// are used to resolve links.
+    pub fn build(
+        &self,
+        collection: &IsarCollection,
+        collections: &[IsarCollection],
+    ) -> Result<Query> {
+        let mut qb = collection.new_query_builder();
+        qb.init_where_clauses();
+        for wc in &self.where_clauses {
+            match wc {
+                WhereClauseAst::Id {
+                    lower,
+                    upper,
+                    descending,
+                } => {
+                    if lower > upper {
+                        continue;
+                    } else if *descending {
+                        qb.add_id_where_clause(*upper, *lower)?;
+                    } else {
+                        qb.add_id_where_clause(*lower, *upper)?;
+                    }
+                }
+                WhereClauseAst::Index {
+                    index,
+                    lower,
+                    upper,
+                    skip_duplicates,
+                    descending,
+                } => {
+                    let index =
+                        if let Some(index) = collection.indexes.iter().find(|i| &i.name == index) {
+                            index
+                        } else {
+                            return illegal_arg(&format!("Unknown index \"{}\".", index));
+                        };
+                    let sort = if *descending {
+                        Sort::Descending
+                    } else {
+                        Sort::Ascending
+                    };
+                    qb.add_index_where_clause(
+                        index.id,
+                        IndexKey::from_bytes(lower.clone()),
+                        IndexKey::from_bytes(upper.clone()),
+                        sort,
+                        *skip_duplicates,
+                    )?;
+                }
+                WhereClauseAst::Link { link_id, id, .. } => {
+                    let source = find_link_owner(collections, *link_id)?;
+                    qb.add_link_where_clause(source, *link_id, *id)?;
+                }
+            }
+        }
+
+        if let Some(filter) = &self.filter {
+            let filter = filter.build(collection, &collection.properties, collections)?;
+            qb.set_filter(filter);
+        }
+        for sort in &self.sort {
+            let expression = sort.expression.build(&collection.properties)?;
+            let order = if sort.descending {
+                Sort::Descending
+            } else {
+                Sort::Ascending
+            };
+            qb.add_sort_expression(expression, order, sort.case_sensitive);
+        }
+        for distinct in &self.distinct {
+            let property = find_property(&collection.properties, &distinct.property)?;
+            qb.add_distinct(property, distinct.case_sensitive);
+        }
+        qb.set_offset(self.offset);
+        if let Some(limit) = self.limit {
+            qb.set_limit(limit);
+        }
+
+        let query = qb.build();
+        if let Some(seek_after) = self.seek_after {
+            Ok(query.with_seek(seek_after))
+        } else {
+            Ok(query)
+        }
+    }
+}
+
+impl FilterAst {
+    /// `properties` are the properties of `collection` or of the embedded object the filter
+    /// is applied to.
+    pub fn build(
+        &self,
+        collection: &IsarCollection,
+        properties: &[Property],
+        collections: &[IsarCollection],
+    ) -> Result<Filter> {
+        let property = |name: &str| find_property(properties, name);
+        let build_all = |filters: &[FilterAst]| -> Result<Vec<Filter>> {
+            filters
+                .iter()
+                .map(|f| f.build(collection, properties, collections))
+                .collect()
+        };
+        let filter = match self {
+            FilterAst::Id { lower, upper } => Filter::id(*lower, *upper),
+            FilterAst::ByteBetween {
+                property: name,
+                lower,
+                upper,
+            } => Filter::byte(property(name)?, *lower, *upper)?,
+            FilterAst::IntBetween {
+                property: name,
+                lower,
+                upper,
+            } => Filter::int(property(name)?, *lower, *upper)?,
+            FilterAst::LongBetween {
+                property: name,
+                lower,
+                upper,
+            } => Filter::long(property(name)?, *lower, *upper)?,
+            FilterAst::FloatBetween {
+                property: name,
+                lower,
+                upper,
+            } => Filter::float(property(name)?, *lower, *upper)?,
+            FilterAst::DoubleBetween {
+                property: name,
+                lower,
+                upper,
+            } => Filter::double(property(name)?, *lower, *upper)?,
+            FilterAst::Flags {
+                property: name,
+                mask,
+                all,
+            } => {
+                if *all {
+                    Filter::has_all_flags(property(name)?, *mask)?
+                } else {
+                    Filter::has_any_flag(property(name)?, *mask)?
+                }
+            }
+            FilterAst::StringBetween {
+                property: name,
+                lower,
+                upper,
+                case_sensitive,
+            } => Filter::byte_string(
+                property(name)?,
+                lower.clone(),
+                upper.clone(),
+                *case_sensitive,
+            )?,
+            FilterAst::StringStartsWith {
+                property: name,
+                value,
+                case_sensitive,
+            } => Filter::string_starts_with(property(name)?, value, *case_sensitive)?,
+            FilterAst::StringEndsWith {
+                property: name,
+                value,
+                case_sensitive,
+            } => Filter::string_ends_with(property(name)?, value, *case_sensitive)?,
+            FilterAst::StringContains {
+                property: name,
+                value,
+                case_sensitive,
+            } => Filter::string_contains(property(name)?, value, *case_sensitive)?,
+            FilterAst::StringMatches {
+                property: name,
+                value,
+                case_sensitive,
+            } => Filter::string_matches(property(name)?, value, *case_sensitive)?,
+            FilterAst::StringEqual {
+                property: name,
+                value,
+                case_sensitive,
+                trim,
+                ignore_whitespace,
+                ignore_diacritics,
+            } => {
+                let normalizer = StringNormalizer::new(
+                    *case_sensitive,
+                    *trim,
+                    *ignore_whitespace,
+                    *ignore_diacritics,
+                );
+                Filter::string_equal(property(name)?, value, normalizer)?
+            }
+            FilterAst::ListLength {
+                property: name,
+                lower,
+                upper,
+            } => Filter::list_length(property(name)?, *lower, *upper)?,
+            FilterAst::IsNull { property: name } => Filter::null(property(name)?),
+            FilterAst::And { filters } => Filter::and(build_all(filters)?),
+            FilterAst::Or { filters } => Filter::or(build_all(filters)?),
+            FilterAst::Xor { filters } => Filter::xor(build_all(filters)?),
+            FilterAst::Not { filter } => {
+                Filter::not(filter.build(collection, properties, collections)?)
+            }
+            FilterAst::Static { value } => Filter::stat(*value),
+            FilterAst::Object {
+                property: name,
+                filter,
+            } => {
+                let property = property(name)?;
+                let embedded_properties = property
+                    .target_id
+                    .and_then(|id| collection.embedded_properties.get(id));
+                let filter = match (filter, embedded_properties) {
+                    (Some(filter), Some(embedded)) => {
+                        Some(filter.build(collection, embedded, collections)?)
+                    }
+                    (Some(_), None) => return Err(property.mismatch(DataType::Object)),
+                    (None, _) => None,
+                };
+                Filter::object(property, filter)?
+            }
+            FilterAst::Link {
+                link_id, filter, ..
+            } => {
+                let target = collection.get_link_backlink(*link_id)?.target_db();
+                let target = if let Some(target) = collections.iter().find(|c| c.db == target) {
+                    target
+                } else {
+                    return illegal_arg("Link target collection does not exist.");
+                };
+                let filter = filter.build(target, &target.properties, collections)?;
+                Filter::link(collection, *link_id, filter)?
+            }
+            FilterAst::LinkLength {
+                link_id,
+                lower,
+                upper,
+                ..
+            } => Filter::link_length(collection, *link_id, *lower, *upper)?,
+        };
+        Ok(filter)
+    }
+}
+
+impl SortExpressionAst {
+    pub fn build(&self, properties: &[Property]) -> Result<SortExpression> {
+        match self {
+            SortExpressionAst::Property { property } => {
+                SortExpression::property(find_property(properties, property)?)
+            }
+            SortExpressionAst::Arithmetic { op, left, right } => {
+                SortExpression::arithmetic(*op, left.build(properties)?, right.build(properties)?)
+            }
+            SortExpressionAst::Coalesce { expressions } => {
+                let expressions = expressions
+                    .iter()
+                    .map(|e| e.build(properties))
+                    .collect::<Result<Vec<_>>>()?;
+                SortExpression::coalesce(expressions)
+            }
+        }
+    }
+}
+
+impl From<&SortExpression> for SortExpressionAst {
+    fn from(expression: &SortExpression) -> Self {
+        match expression {
+            SortExpression::Property(property) => SortExpressionAst::Property {
+                property: property.name.clone(),
+            },
+            SortExpression::Arithmetic(op, left, right) => SortExpressionAst::Arithmetic {
+                op: *op,
+                left: Box::new(left.as_ref().into()),
+                right: Box::new(right.as_ref().into()),
+            },
+            SortExpression::Coalesce(expressions) => SortExpressionAst::Coalesce {
+                expressions: expressions.iter().map(|e| e.into()).collect(),
+            },
+        }
+    }
+}
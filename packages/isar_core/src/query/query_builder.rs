@@ -6,6 +6,7 @@ use crate::object::property::Property;
 use crate::query::filter::Filter;
 use crate::query::id_where_clause::IdWhereClause;
 use crate::query::link_where_clause::LinkWhereClause;
+use crate::query::sort_expression::SortExpression;
 use crate::query::where_clause::WhereClause;
 use crate::query::{Query, Sort};
 
@@ -13,7 +14,7 @@ pub struct QueryBuilder<'a> {
     pub collection: &'a IsarCollection,
     where_clauses: Option<Vec<WhereClause>>,
     filter: Option<Filter>,
-    sort: Vec<(Property, Sort)>,
+    sort: Vec<(SortExpression, Sort, bool)>,
     distinct: Vec<(Property, bool)>,
     offset: usize,
     limit: usize,
@@ -32,7 +33,7 @@ impl<'a> QueryBuilder<'a> {
         }
     }
 
-    fn init_where_clauses(&mut self) {
+    pub(crate) fn init_where_clauses(&mut self) {
         if self.where_clauses.is_none() {
             self.where_clauses = Some(vec![]);
         }
@@ -103,14 +104,36 @@ impl<'a> QueryBuilder<'a> {
     }
 
     pub fn add_sort(&mut self, property: &Property, sort: Sort) -> Result<()> {
+        self.add_sort_with_case(property, sort, property.case_sensitive)
+    }
+
+    /// Sorts strings with the given case sensitivity instead of the property collation.
+    pub fn add_sort_with_case(
+        &mut self,
+        property: &Property,
+        sort: Sort,
+        case_sensitive: bool,
+    ) -> Result<()> {
         if property.data_type.is_scalar() {
-            self.sort.push((property.clone(), sort));
+            let expression = SortExpression::Property(property.clone());
+            self.sort.push((expression, sort, case_sensitive));
             Ok(())
         } else {
             illegal_arg("Only scalar types may be used for sorting.")
         }
     }
 
+    /// Sorts by an expression like `price * quantity`. `case_sensitive` applies to string
+    /// expressions.
+    pub fn add_sort_expression(
+        &mut self,
+        expression: SortExpression,
+        sort: Sort,
+        case_sensitive: bool,
+    ) {
+        self.sort.push((expression, sort, case_sensitive));
+    }
+
     pub fn add_distinct(&mut self, property: &Property, case_sensitive: bool) {
         self.distinct.push((property.clone(), case_sensitive));
     }
@@ -123,11 +146,47 @@ impl<'a> QueryBuilder<'a> {
         self.limit = limit;
     }
 
+    /// Filters are only counted for queries that scan the whole collection.
+    fn unindexed_properties(&self) -> Vec<String> {
+        let mut offsets = vec![];
+        let full_scan = self
+            .where_clauses
+            .as_ref()
+            .unwrap()
+            .iter()
+            .all(|wc| matches!(wc, WhereClause::Id(_)));
+        if full_scan {
+            if let Some(filter) = &self.filter {
+                offsets.extend(filter.property_offsets());
+            }
+        }
+        for (expression, _, _) in &self.sort {
+            if let SortExpression::Property(property) = expression {
+                offsets.push(property.offset);
+            }
+        }
+
+        offsets.sort_unstable();
+        offsets.dedup();
+        offsets
+            .into_iter()
+            .filter(|offset| !self.collection.has_index_on(*offset))
+            .filter_map(|offset| {
+                self.collection
+                    .properties
+                    .iter()
+                    .find(|p| p.offset == offset)
+                    .map(|p| p.name.clone())
+            })
+            .collect()
+    }
+
     pub fn build(mut self) -> Query {
         if self.where_clauses.is_none() {
             self.add_id_where_clause(i64::MIN, i64::MAX).unwrap();
         }
-        Query::new(
+        let unindexed_properties = self.unindexed_properties();
+        let query = Query::new(
             self.collection.instance_id,
             self.where_clauses.unwrap(),
             self.filter,
@@ -135,6 +194,11 @@ impl<'a> QueryBuilder<'a> {
             self.distinct,
             self.offset,
             self.limit,
-        )
+            self.collection.index_advisor.clone(),
+            unindexed_properties,
+        );
+        #[cfg(feature = "query-log")]
+        super::query_log::log_query(self.collection, &query);
+        query
     }
 }
@@ -0,0 +1,38 @@
+use crate::collection::IsarCollection;
+use crate::query::query_ast::FilterAst;
+use crate::query::{Query, QueryExplain};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::RwLock;
+
+/// A query that was built. The filter contains the bound values and is `None` if the query has
+/// no filter or the filter cannot be exported.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueryLogEntry {
+    pub collection: String,
+    pub plan: QueryExplain,
+    pub filter: Option<FilterAst>,
+}
+
+pub type QueryLogSink = Box<dyn Fn(&QueryLogEntry) + Send + Sync + 'static>;
+
+static QUERY_LOG_SINK: Lazy<RwLock<Option<QueryLogSink>>> = Lazy::new(|| RwLock::new(None));
+
+/// Registers a sink that receives every query built by any instance.
+pub fn set_query_log_sink(sink: Option<QueryLogSink>) {
+    *QUERY_LOG_SINK.write().unwrap() = sink;
+}
+
+pub(crate) fn log_query(collection: &IsarCollection, query: &Query) {
+    if let Some(sink) = QUERY_LOG_SINK.read().unwrap().as_ref() {
+        let filter = query
+            .filter
+            .as_ref()
+            .and_then(|filter| filter.to_ast(collection, &collection.properties, &[]).ok());
+        sink(&QueryLogEntry {
+            collection: collection.name.clone(),
+            plan: query.explain(),
+            filter,
+        });
+    }
+}
@@ -0,0 +1,612 @@
+use crate::collection::IsarCollection;
+use crate::error::{illegal_arg, Result};
+use crate::object::data_type::DataType;
+use crate::object::isar_object::IsarObject;
+use crate::object::property::Property;
+use crate::query::filter::Filter;
+use crate::query::{Query, Sort};
+
+/// Parses a human readable query like
+/// `name startsWith "a" and (age >= 18 or vip == true) sort by name, age desc limit 10`.
+/// Property names that are also keywords like `and` can be quoted with backticks.
+/// Errors contain the character position of the offending token.
+pub fn parse_query(collection: &IsarCollection, input: &str) -> Result<Query> {
+    let (tokens, positions) = tokenize(input)?.into_iter().unzip();
+    let mut parser = QueryParser {
+        collection,
+        tokens,
+        positions,
+        end: input.chars().count(),
+        pos: 0,
+    };
+    parser.parse()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    QuotedIdent(String),
+    Str(String),
+    Num(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let token = if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c == '(' {
+            i += 1;
+            Token::LParen
+        } else if c == ')' {
+            i += 1;
+            Token::RParen
+        } else if c == ',' {
+            i += 1;
+            Token::Comma
+        } else if c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    Some('"') => break,
+                    Some('\\') => {
+                        match chars.get(i + 1) {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some(escaped) => value.push(*escaped),
+                            None => return unterminated(start),
+                        }
+                        i += 2;
+                    }
+                    Some(c) => {
+                        value.push(*c);
+                        i += 1;
+                    }
+                    None => return unterminated(start),
+                }
+            }
+            i += 1;
+            Token::Str(value)
+        } else if c == '`' {
+            let len = chars[i + 1..].iter().position(|c| *c == '`');
+            let len = match len {
+                Some(len) => len,
+                None => return illegal_arg(&format!("Unterminated identifier at {}.", start)),
+            };
+            i += len + 2;
+            Token::QuotedIdent(chars[start + 1..i - 1].iter().collect())
+        } else if c.is_ascii_digit()
+            || (c == '-' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            i += 1;
+            while chars
+                .get(i)
+                .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+            {
+                i += 1;
+            }
+            if chars.get(i).is_some_and(|c| *c == 'e' || *c == 'E') {
+                let sign = chars.get(i + 1).is_some_and(|c| *c == '+' || *c == '-');
+                let digits = if sign { i + 2 } else { i + 1 };
+                if chars.get(digits).is_some_and(|c| c.is_ascii_digit()) {
+                    i = digits;
+                    while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+                        i += 1;
+                    }
+                }
+            }
+            Token::Num(chars[start..i].iter().collect())
+        } else if c.is_alphanumeric() || c == '_' {
+            while chars
+                .get(i)
+                .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+            {
+                i += 1;
+            }
+            Token::Ident(chars[start..i].iter().collect())
+        } else {
+            let next = chars.get(i + 1).copied();
+            let (op, len) = match (c, next) {
+                ('=', Some('=')) => ("==", 2),
+                ('!', Some('=')) => ("!=", 2),
+                ('>', Some('=')) => (">=", 2),
+                ('<', Some('=')) => ("<=", 2),
+                ('>', _) => (">", 1),
+                ('<', _) => ("<", 1),
+                _ => return illegal_arg(&format!("Unexpected character '{}' at {}.", c, i)),
+            };
+            i += len;
+            Token::Op(op)
+        };
+        tokens.push((token, start));
+    }
+    Ok(tokens)
+}
+
+fn unterminated<T>(start: usize) -> Result<T> {
+    illegal_arg(&format!("Unterminated string at {}.", start))
+}
+
+struct QueryParser<'a> {
+    collection: &'a IsarCollection,
+    tokens: Vec<Token>,
+    positions: Vec<usize>,
+    end: usize,
+    pos: usize,
+}
+
+impl<'a> QueryParser<'a> {
+    fn error<T>(&self, index: usize, message: &str) -> Result<T> {
+        let position = self.positions.get(index).copied().unwrap_or(self.end);
+        illegal_arg(&format!("{} at {}.", message, position))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(self.pos, &format!("Expected '{}'", keyword))
+        }
+    }
+
+    fn parse(&mut self) -> Result<Query> {
+        let mut qb = self.collection.new_query_builder();
+        if self.peek().is_some()
+            && !self.peek_keyword("sort")
+            && !self.peek_keyword("offset")
+            && !self.peek_keyword("limit")
+        {
+            qb.set_filter(self.parse_or()?);
+        }
+
+        if self.peek_keyword("sort") {
+            self.pos += 1;
+            self.expect_keyword("by")?;
+            loop {
+                let property = self.parse_property()?;
+                let sort = if self.peek_keyword("desc") {
+                    self.pos += 1;
+                    Sort::Descending
+                } else {
+                    if self.peek_keyword("asc") {
+                        self.pos += 1;
+                    }
+                    Sort::Ascending
+                };
+                qb.add_sort(property, sort)?;
+                if self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        if self.peek_keyword("offset") {
+            self.pos += 1;
+            qb.set_offset(self.parse_usize()?);
+        }
+
+        if self.peek_keyword("limit") {
+            self.pos += 1;
+            qb.set_limit(self.parse_usize()?);
+        }
+
+        if let Some(token) = self.peek() {
+            return self.error(self.pos, &format!("Unexpected token {:?}", token));
+        }
+        Ok(qb.build())
+    }
+
+    fn parse_usize(&mut self) -> Result<usize> {
+        let index = self.pos;
+        if let Some(Token::Num(num)) = self.next() {
+            if let Ok(num) = num.parse() {
+                return Ok(num);
+            }
+        }
+        self.error(index, "Expected a positive number")
+    }
+
+    fn parse_or(&mut self) -> Result<Filter> {
+        let mut filters = vec![self.parse_and()?];
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            filters.push(self.parse_and()?);
+        }
+        if filters.len() == 1 {
+            Ok(filters.pop().unwrap())
+        } else {
+            Ok(Filter::or(filters))
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Filter> {
+        let mut filters = vec![self.parse_unary()?];
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            filters.push(self.parse_unary()?);
+        }
+        if filters.len() == 1 {
+            Ok(filters.pop().unwrap())
+        } else {
+            Ok(Filter::and(filters))
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            Ok(Filter::not(self.parse_unary()?))
+        } else if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let filter = self.parse_or()?;
+            let index = self.pos;
+            if self.next() != Some(Token::RParen) {
+                return self.error(index, "Expected ')'");
+            }
+            Ok(filter)
+        } else {
+            self.parse_condition()
+        }
+    }
+
+    fn parse_property(&mut self) -> Result<&'a Property> {
+        let index = self.pos;
+        if let Some(Token::Ident(name) | Token::QuotedIdent(name)) = self.next() {
+            let property = self.collection.properties.iter().find(|p| p.name == name);
+            if let Some(property) = property {
+                return Ok(property);
+            }
+            self.error(index, &format!("Unknown property '{}'", name))
+        } else {
+            self.error(index, "Expected a property name")
+        }
+    }
+
+    fn parse_condition(&mut self) -> Result<Filter> {
+        if self.peek_keyword("id") {
+            self.pos += 1;
+            let op = self.parse_operator()?;
+            let value = self.parse_int_value()?;
+            return Ok(match int_range(op, value, i64::MIN, i64::MAX) {
+                Some((lower, upper)) if op != "!=" => Filter::id(lower, upper),
+                Some(_) => Filter::not(Filter::id(value, value)),
+                None => Filter::stat(false),
+            });
+        }
+
+        let property_index = self.pos;
+        let property = self.parse_property()?;
+        if self.peek_keyword("is") {
+            self.pos += 1;
+            let negate = self.peek_keyword("not");
+            if negate {
+                self.pos += 1;
+            }
+            self.expect_keyword("null")?;
+            let filter = Filter::null(property);
+            return Ok(if negate { Filter::not(filter) } else { filter });
+        }
+
+        let op_index = self.pos;
+        let op = self.parse_operator()?;
+        let data_type = property
+            .data_type
+            .get_element_type()
+            .unwrap_or(property.data_type);
+        let filter = match data_type {
+            DataType::String => self.string_condition(property, op, op_index)?,
+            DataType::Bool => {
+                let index = self.pos;
+                let value = match self.next() {
+                    Some(Token::Ident(v)) if v.eq_ignore_ascii_case("true") => {
+                        IsarObject::TRUE_BOOL
+                    }
+                    Some(Token::Ident(v)) if v.eq_ignore_ascii_case("false") => {
+                        IsarObject::FALSE_BOOL
+                    }
+                    _ => return self.error(index, "Expected true or false"),
+                };
+                if op != "==" && op != "!=" {
+                    return self.error(op_index, "Bool properties only support == and !=");
+                }
+                Filter::byte(property, value, value)?
+            }
+            DataType::Byte | DataType::Int | DataType::Long => {
+                let value = self.parse_int_value()?;
+                let op = if op == "!=" { "==" } else { op };
+                let (min, max) = match data_type {
+                    DataType::Byte => (u8::MIN as i64, u8::MAX as i64),
+                    DataType::Int => (i32::MIN as i64, i32::MAX as i64),
+                    _ => (i64::MIN, i64::MAX),
+                };
+                if let Some((lower, upper)) = int_range(op, value, min, max) {
+                    match data_type {
+                        DataType::Byte => Filter::byte(property, lower as u8, upper as u8)?,
+                        DataType::Int => Filter::int(property, lower as i32, upper as i32)?,
+                        _ => Filter::long(property, lower, upper)?,
+                    }
+                } else {
+                    Filter::stat(false)
+                }
+            }
+            DataType::Float | DataType::Double => {
+                let value = self.parse_float_value()?;
+                let op = if op == "!=" { "==" } else { op };
+                let (lower, upper) = match op {
+                    "==" => (value, value),
+                    ">=" => (value, f64::INFINITY),
+                    "<=" => (f64::NEG_INFINITY, value),
+                    ">" => (next_up(value, data_type), f64::INFINITY),
+                    "<" => (f64::NEG_INFINITY, next_down(value, data_type)),
+                    _ => return self.error(op_index, "Operator is not supported for numbers"),
+                };
+                if data_type == DataType::Float {
+                    Filter::float(property, lower as f32, upper as f32)?
+                } else {
+                    Filter::double(property, lower, upper)?
+                }
+            }
+            _ => return self.error(property_index, "Property does not support filters"),
+        };
+
+        if op == "!=" && data_type != DataType::String {
+            Ok(Filter::not(filter))
+        } else {
+            Ok(filter)
+        }
+    }
+
+    fn string_condition(
+        &mut self,
+        property: &Property,
+        op: &str,
+        op_index: usize,
+    ) -> Result<Filter> {
+        let index = self.pos;
+        let value = if let Some(Token::Str(value)) = self.next() {
+            value
+        } else {
+            return self.error(index, "Expected a string");
+        };
+        let cs = property.case_sensitive;
+        let upper_bound = Some(vec![u8::MAX]);
+        let filter = match op {
+            "==" => Filter::string(property, Some(&value), Some(&value), cs)?,
+            "!=" => Filter::not(Filter::string(property, Some(&value), Some(&value), cs)?),
+            ">=" | ">" => {
+                let lower = Filter::string_to_bytes(Some(&value), cs);
+                let filter = Filter::byte_string(property, lower, upper_bound, cs)?;
+                if op == ">" {
+                    let eq = Filter::string(property, Some(&value), Some(&value), cs)?;
+                    Filter::and(vec![filter, Filter::not(eq)])
+                } else {
+                    filter
+                }
+            }
+            "<=" | "<" => {
+                // the empty lower bound excludes null like the other operators
+                let filter = Filter::string(property, Some(""), Some(&value), cs)?;
+                if op == "<" {
+                    let eq = Filter::string(property, Some(&value), Some(&value), cs)?;
+                    Filter::and(vec![filter, Filter::not(eq)])
+                } else {
+                    filter
+                }
+            }
+            "startswith" => Filter::string_starts_with(property, &value, cs)?,
+            "endswith" => Filter::string_ends_with(property, &value, cs)?,
+            "contains" => Filter::string_contains(property, &value, cs)?,
+            "matches" => Filter::string_matches(property, &value, cs)?,
+            _ => return self.error(op_index, "Operator is not supported for strings"),
+        };
+        Ok(filter)
+    }
+
+    fn parse_operator(&mut self) -> Result<&'static str> {
+        let index = self.pos;
+        match self.next() {
+            Some(Token::Op(op)) => Ok(op),
+            Some(Token::Ident(ident)) => match ident.to_lowercase().as_str() {
+                "startswith" => Ok("startswith"),
+                "endswith" => Ok("endswith"),
+                "contains" => Ok("contains"),
+                "matches" => Ok("matches"),
+                _ => self.error(index, &format!("Unknown operator '{}'", ident)),
+            },
+            _ => self.error(index, "Expected an operator"),
+        }
+    }
+
+    fn parse_int_value(&mut self) -> Result<i64> {
+        let index = self.pos;
+        if let Some(Token::Num(num)) = self.next() {
+            if let Ok(num) = num.parse() {
+                return Ok(num);
+            }
+        }
+        self.error(index, "Expected an integer")
+    }
+
+    fn parse_float_value(&mut self) -> Result<f64> {
+        let index = self.pos;
+        if let Some(Token::Num(num)) = self.next() {
+            if let Ok(num) = num.parse() {
+                return Ok(num);
+            }
+        }
+        self.error(index, "Expected a number")
+    }
+}
+
+fn int_range(op: &str, value: i64, min: i64, max: i64) -> Option<(i64, i64)> {
+    let (lower, upper) = match op {
+        "==" | "!=" => (Some(value), Some(value)),
+        ">=" => (Some(value), Some(max)),
+        ">" => (value.checked_add(1), Some(max)),
+        "<=" => (Some(min), Some(value)),
+        "<" => (Some(min), value.checked_sub(1)),
+        _ => (None, None),
+    };
+    let lower = lower?.max(min);
+    let upper = upper?.min(max);
+    if lower <= upper {
+        Some((lower, upper))
+    } else {
+        None
+    }
+}
+
+fn next_up(value: f64, data_type: DataType) -> f64 {
+    if data_type == DataType::Float {
+        let value = value as f32;
+        if value == 0.0 {
+            return f32::from_bits(1) as f64;
+        }
+        let bits = value.to_bits();
+        let next = if value > 0.0 { bits + 1 } else { bits - 1 };
+        f32::from_bits(next) as f64
+    } else {
+        if value == 0.0 {
+            return f64::from_bits(1);
+        }
+        let bits = value.to_bits();
+        let next = if value > 0.0 { bits + 1 } else { bits - 1 };
+        f64::from_bits(next)
+    }
+}
+
+fn next_down(value: f64, data_type: DataType) -> f64 {
+    -next_up(-value, data_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|(t, _)| t)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokens(r#"name == "a \"b\"" and (age >= -18) sort by name, age desc"#);
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Ident("name".to_string()),
+                Token::Op("=="),
+                Token::Str("a \"b\"".to_string()),
+                Token::Ident("and".to_string()),
+                Token::LParen,
+                Token::Ident("age".to_string()),
+                Token::Op(">="),
+                Token::Num("-18".to_string()),
+                Token::RParen,
+                Token::Ident("sort".to_string()),
+                Token::Ident("by".to_string()),
+                Token::Ident("name".to_string()),
+                Token::Comma,
+                Token::Ident("age".to_string()),
+                Token::Ident("desc".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_numbers() {
+        for num in ["1e-5", "2E10", "-1.5e+3", "3.25", "-7"] {
+            assert_eq!(tokens(num), vec![Token::Num(num.to_string())]);
+        }
+        assert_eq!(
+            tokens("1e limit"),
+            vec![
+                Token::Num("1".to_string()),
+                Token::Ident("e".to_string()),
+                Token::Ident("limit".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_positions() {
+        let positions: Vec<usize> = tokenize(r#"a >= "x y" or(b<1)"#)
+            .unwrap()
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect();
+        assert_eq!(positions, vec![0, 2, 5, 11, 13, 14, 15, 16, 17]);
+    }
+
+    #[test]
+    fn test_tokenize_quoted_ident() {
+        assert_eq!(
+            tokens("`and` == 1"),
+            vec![
+                Token::QuotedIdent("and".to_string()),
+                Token::Op("=="),
+                Token::Num("1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_invalid() {
+        assert!(tokenize(r#"name == "abc"#).is_err());
+        assert!(tokenize("`and == 1").is_err());
+        assert!(tokenize("age ~ 5").is_err());
+    }
+
+    #[test]
+    fn test_int_range() {
+        assert_eq!(int_range("==", 5, 0, 255), Some((5, 5)));
+        assert_eq!(int_range(">", 5, 0, 255), Some((6, 255)));
+        assert_eq!(int_range("<", 5, 0, 255), Some((0, 4)));
+        assert_eq!(int_range("<", 0, 0, 255), None);
+        assert_eq!(int_range(">", i64::MAX, i64::MIN, i64::MAX), None);
+        assert_eq!(int_range(">=", 300, 0, 255), None);
+    }
+
+    #[test]
+    fn test_next_up_down() {
+        assert!(next_up(1.0, DataType::Double) > 1.0);
+        assert!(next_down(1.0, DataType::Double) < 1.0);
+        assert!(next_up(0.0, DataType::Double) > 0.0);
+        assert!(next_down(0.0, DataType::Double) < 0.0);
+        assert!(next_up(-1.0, DataType::Float) as f32 > -1.0);
+        assert_eq!(
+            next_up(1.0, DataType::Float) as f32,
+            f32::from_bits(1.0f32.to_bits() + 1)
+        );
+    }
+}
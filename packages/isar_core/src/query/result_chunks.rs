@@ -0,0 +1,77 @@
+use crate::object::row_format::{encode_row, encoded_row_size};
+
+/// Collects rows in the format of [crate::object::row_format].
+pub(crate) struct ChunkWriter {
+    buffer: Vec<u8>,
+    rows: u32,
+    max_size: usize,
+}
+
+impl ChunkWriter {
+    pub fn new(max_size: usize) -> Self {
+        ChunkWriter {
+            buffer: vec![],
+            rows: 0,
+            max_size,
+        }
+    }
+
+    /// Adds a row and returns the previous chunk if the row does not fit into it. Rows that are
+    /// bigger than the maximum size get a chunk on their own.
+    pub fn add(&mut self, id: i64, object: &[u8]) -> Option<(Vec<u8>, u32)> {
+        let row_size = encoded_row_size(object);
+        let full = if self.rows > 0 && self.buffer.len() + row_size > self.max_size {
+            self.take()
+        } else {
+            None
+        };
+
+        encode_row(&mut self.buffer, id, object);
+        self.rows += 1;
+        full
+    }
+
+    pub fn finish(mut self) -> Option<(Vec<u8>, u32)> {
+        self.take()
+    }
+
+    fn take(&mut self) -> Option<(Vec<u8>, u32)> {
+        if self.rows > 0 {
+            let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.max_size));
+            let rows = std::mem::replace(&mut self.rows, 0);
+            Some((chunk, rows))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{ByteOrder, LittleEndian};
+
+    #[test]
+    fn test_chunk_writer() {
+        let mut writer = ChunkWriter::new(30);
+        assert_eq!(writer.add(1, &[1, 2, 3]), None);
+        assert_eq!(writer.add(2, &[4]), None);
+
+        let (chunk, rows) = writer.add(3, &[5; 20]).unwrap();
+        assert_eq!(rows, 2);
+        assert_eq!(chunk.len(), 28);
+        assert_eq!(LittleEndian::read_i64(&chunk), 1);
+        assert_eq!(LittleEndian::read_u32(&chunk[8..]), 3);
+        assert_eq!(&chunk[12..15], &[1, 2, 3]);
+        assert_eq!(LittleEndian::read_i64(&chunk[15..]), 2);
+
+        let (chunk, rows) = writer.finish().unwrap();
+        assert_eq!(rows, 1);
+        assert_eq!(chunk.len(), 32);
+    }
+
+    #[test]
+    fn test_chunk_writer_empty() {
+        assert_eq!(ChunkWriter::new(10).finish(), None);
+    }
+}
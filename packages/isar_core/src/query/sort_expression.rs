@@ -0,0 +1,358 @@
+use crate::error::{illegal_arg, Result};
+use crate::object::data_type::DataType;
+use crate::object::isar_object::IsarObject;
+use crate::object::property::Property;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum ValueType {
+    Integer,
+    Real,
+    String,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+/// Expression over the properties of an object that results can be sorted by.
+#[derive(Clone)]
+pub enum SortExpression {
+    Property(Property),
+    Arithmetic(ArithmeticOp, Box<SortExpression>, Box<SortExpression>),
+    /// The first expression that is not null.
+    Coalesce(Vec<SortExpression>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Value<'a> {
+    Null,
+    Integer(i64),
+    Real(f64),
+    String(&'a str),
+}
+
+impl SortExpression {
+    pub fn property(property: &Property) -> Result<SortExpression> {
+        let expression = SortExpression::Property(property.clone());
+        expression.value_type()?;
+        Ok(expression)
+    }
+
+    pub fn arithmetic(
+        op: ArithmeticOp,
+        left: SortExpression,
+        right: SortExpression,
+    ) -> Result<SortExpression> {
+        let expression = SortExpression::Arithmetic(op, Box::new(left), Box::new(right));
+        expression.value_type()?;
+        Ok(expression)
+    }
+
+    pub fn coalesce(expressions: Vec<SortExpression>) -> Result<SortExpression> {
+        let expression = SortExpression::Coalesce(expressions);
+        expression.value_type()?;
+        Ok(expression)
+    }
+
+    fn value_type(&self) -> Result<ValueType> {
+        match self {
+            SortExpression::Property(property) => match property.data_type {
+                DataType::Bool | DataType::Byte | DataType::Int | DataType::Long => {
+                    Ok(ValueType::Integer)
+                }
+                DataType::Float | DataType::Double => Ok(ValueType::Real),
+                DataType::String => Ok(ValueType::String),
+                _ => illegal_arg("Only scalar types may be used for sorting."),
+            },
+            SortExpression::Arithmetic(_, left, right) => {
+                match (left.value_type()?, right.value_type()?) {
+                    (ValueType::Integer, ValueType::Integer) => Ok(ValueType::Integer),
+                    (ValueType::String, _) | (_, ValueType::String) => {
+                        illegal_arg("Arithmetic is only supported for numeric properties.")
+                    }
+                    _ => Ok(ValueType::Real),
+                }
+            }
+            SortExpression::Coalesce(expressions) => {
+                let mut value_type = None;
+                for expression in expressions {
+                    let expression_type = expression.value_type()?;
+                    value_type = match (value_type, expression_type) {
+                        (None, t) => Some(t),
+                        (Some(a), b) if a == b => Some(a),
+                        (Some(ValueType::String), _) | (_, ValueType::String) => {
+                            return illegal_arg("Coalesce cannot mix strings and numbers.");
+                        }
+                        _ => Some(ValueType::Real),
+                    };
+                }
+                if let Some(value_type) = value_type {
+                    Ok(value_type)
+                } else {
+                    illegal_arg("Coalesce requires at least one expression.")
+                }
+            }
+        }
+    }
+
+    fn evaluate<'a>(&self, object: &'a IsarObject<'a>) -> Value<'a> {
+        match self {
+            SortExpression::Property(p) => {
+                if object.is_null(p.offset, p.data_type) {
+                    return Value::Null;
+                }
+                match p.data_type {
+                    DataType::Bool | DataType::Byte => {
+                        Value::Integer(object.read_byte(p.offset) as i64)
+                    }
+                    DataType::Int => Value::Integer(object.read_int(p.offset) as i64),
+                    DataType::Long => Value::Integer(object.read_long(p.offset)),
+                    DataType::Float => Value::Real(object.read_float(p.offset) as f64),
+                    DataType::Double => Value::Real(object.read_double(p.offset)),
+                    DataType::String => object
+                        .read_string(p.offset)
+                        .map_or(Value::Null, Value::String),
+                    _ => Value::Null,
+                }
+            }
+            SortExpression::Arithmetic(op, left, right) => {
+                Self::apply(*op, left.evaluate(object), right.evaluate(object))
+            }
+            SortExpression::Coalesce(expressions) => expressions
+                .iter()
+                .map(|e| e.evaluate(object))
+                .find(|v| *v != Value::Null)
+                .unwrap_or(Value::Null),
+        }
+    }
+
+    fn apply<'a>(op: ArithmeticOp, left: Value<'a>, right: Value<'a>) -> Value<'a> {
+        match (left, right) {
+            (Value::Integer(a), Value::Integer(b)) => {
+                let result = match op {
+                    ArithmeticOp::Add => a.checked_add(b),
+                    ArithmeticOp::Sub => a.checked_sub(b),
+                    ArithmeticOp::Mul => a.checked_mul(b),
+                };
+                // Overflowing integers continue as reals like in SQL.
+                result.map_or_else(
+                    || Self::apply(op, Value::Real(a as f64), Value::Real(b as f64)),
+                    Value::Integer,
+                )
+            }
+            (Value::Integer(_) | Value::Real(_), Value::Integer(_) | Value::Real(_)) => {
+                let a = Self::as_real(left);
+                let b = Self::as_real(right);
+                Value::Real(match op {
+                    ArithmeticOp::Add => a + b,
+                    ArithmeticOp::Sub => a - b,
+                    ArithmeticOp::Mul => a * b,
+                })
+            }
+            _ => Value::Null,
+        }
+    }
+
+    fn as_real(value: Value) -> f64 {
+        match value {
+            Value::Integer(value) => value as f64,
+            Value::Real(value) => value,
+            _ => f64::NAN,
+        }
+    }
+
+    /// Nulls are ordered before all other values.
+    pub(crate) fn compare(
+        &self,
+        o1: &IsarObject,
+        o2: &IsarObject,
+        case_sensitive: bool,
+    ) -> Ordering {
+        if let SortExpression::Property(p) = self {
+            return o1.compare_property(o2, p.offset, p.data_type, case_sensitive);
+        }
+        match (self.evaluate(o1), self.evaluate(o2)) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Less,
+            (_, Value::Null) => Ordering::Greater,
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(&b),
+            (Value::String(a), Value::String(b)) if case_sensitive => a.cmp(b),
+            (Value::String(a), Value::String(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+            (a, b) => Self::as_real(a).total_cmp(&Self::as_real(b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::object_builder::ObjectBuilder;
+
+    const PRICE: Property = Property::debug(DataType::Long, 2);
+    const QUANTITY: Property = Property::debug(DataType::Int, 10);
+    const NICKNAME: Property = Property::debug(DataType::String, 14);
+    const NAME: Property = Property::debug(DataType::String, 22);
+
+    fn object(price: Option<i64>, quantity: i32, nickname: Option<&str>, name: &str) -> Vec<u8> {
+        let properties = vec![PRICE, QUANTITY, NICKNAME, NAME];
+        let mut builder = ObjectBuilder::new(&properties, None);
+        if let Some(price) = price {
+            builder.write_long(PRICE.offset, price);
+        } else {
+            builder.write_null(PRICE.offset, PRICE.data_type);
+        }
+        builder.write_int(QUANTITY.offset, quantity);
+        builder.write_string(NICKNAME.offset, nickname);
+        builder.write_string(NAME.offset, Some(name));
+        builder.finish().as_bytes().to_vec()
+    }
+
+    fn property(property: Property) -> SortExpression {
+        SortExpression::property(&property).unwrap()
+    }
+
+    #[test]
+    fn test_validation() {
+        let mixed = SortExpression::arithmetic(ArithmeticOp::Mul, property(PRICE), property(NAME));
+        assert!(mixed.is_err());
+        assert!(SortExpression::coalesce(vec![property(NICKNAME), property(PRICE)]).is_err());
+        assert!(SortExpression::coalesce(vec![]).is_err());
+        assert!(SortExpression::coalesce(vec![property(NICKNAME), property(NAME)]).is_ok());
+    }
+
+    #[test]
+    fn test_compare() {
+        let o1 = object(Some(2), 5, None, "b");
+        let o2 = object(Some(3), 3, Some("a"), "c");
+        let o3 = object(None, 3, None, "d");
+        let (o1, o2, o3) = (
+            IsarObject::from_bytes(&o1),
+            IsarObject::from_bytes(&o2),
+            IsarObject::from_bytes(&o3),
+        );
+
+        let total =
+            SortExpression::arithmetic(ArithmeticOp::Mul, property(PRICE), property(QUANTITY))
+                .unwrap();
+        assert_eq!(total.compare(&o1, &o2, true), Ordering::Greater);
+        assert_eq!(total.compare(&o3, &o2, true), Ordering::Less);
+
+        let display_name = SortExpression::coalesce(vec![property(NICKNAME), property(NAME)]);
+        let display_name = display_name.unwrap();
+        assert_eq!(display_name.compare(&o1, &o2, true), Ordering::Greater);
+        assert_eq!(display_name.compare(&o3, &o1, true), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let apply = SortExpression::apply;
+        let (two, three) = (Value::Integer(2), Value::Integer(3));
+        assert_eq!(apply(ArithmeticOp::Add, two, three), Value::Integer(5));
+        assert_eq!(apply(ArithmeticOp::Sub, two, three), Value::Integer(-1));
+        assert_eq!(apply(ArithmeticOp::Mul, two, three), Value::Integer(6));
+        assert_eq!(
+            apply(ArithmeticOp::Add, two, Value::Real(0.5)),
+            Value::Real(2.5)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Mul, Value::Real(1.5), two),
+            Value::Real(3.0)
+        );
+        assert_eq!(apply(ArithmeticOp::Add, Value::Null, two), Value::Null);
+        assert_eq!(apply(ArithmeticOp::Sub, two, Value::Null), Value::Null);
+
+        let o = object(Some(4), 3, None, "a");
+        let o = IsarObject::from_bytes(&o);
+        let total =
+            SortExpression::arithmetic(ArithmeticOp::Sub, property(PRICE), property(QUANTITY));
+        assert_eq!(total.unwrap().evaluate(&o), Value::Integer(1));
+        let missing =
+            SortExpression::arithmetic(ArithmeticOp::Add, property(PRICE), property(NICKNAME));
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let apply = SortExpression::apply;
+        let max = Value::Integer(i64::MAX);
+        let min = Value::Integer(i64::MIN);
+        let one = Value::Integer(1);
+        assert_eq!(
+            apply(ArithmeticOp::Add, max, one),
+            Value::Real(i64::MAX as f64 + 1.0)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Sub, min, one),
+            Value::Real(i64::MIN as f64 - 1.0)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Mul, max, Value::Integer(2)),
+            Value::Real(i64::MAX as f64 * 2.0)
+        );
+        assert_eq!(
+            apply(ArithmeticOp::Sub, max, one),
+            Value::Integer(i64::MAX - 1)
+        );
+
+        let o1 = object(Some(i64::MAX), 2, None, "a");
+        let o2 = object(Some(i64::MAX), 1, None, "b");
+        let (o1, o2) = (IsarObject::from_bytes(&o1), IsarObject::from_bytes(&o2));
+        let total =
+            SortExpression::arithmetic(ArithmeticOp::Mul, property(PRICE), property(QUANTITY))
+                .unwrap();
+        assert_eq!(total.compare(&o1, &o2, true), Ordering::Greater);
+        assert_eq!(total.compare(&o2, &o1, true), Ordering::Less);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let o1 = object(Some(1), 1, Some("nick"), "name");
+        let o2 = object(Some(1), 1, None, "name");
+        let o3 = object(None, 7, None, "name");
+        let (o1, o2, o3) = (
+            IsarObject::from_bytes(&o1),
+            IsarObject::from_bytes(&o2),
+            IsarObject::from_bytes(&o3),
+        );
+
+        let display_name = SortExpression::coalesce(vec![property(NICKNAME), property(NAME)]);
+        let display_name = display_name.unwrap();
+        assert_eq!(display_name.evaluate(&o1), Value::String("nick"));
+        assert_eq!(display_name.evaluate(&o2), Value::String("name"));
+
+        let nick_only = SortExpression::coalesce(vec![property(NICKNAME)]).unwrap();
+        assert_eq!(nick_only.evaluate(&o2), Value::Null);
+
+        let number = SortExpression::coalesce(vec![property(PRICE), property(QUANTITY)]).unwrap();
+        assert_eq!(number.evaluate(&o1), Value::Integer(1));
+        assert_eq!(number.evaluate(&o3), Value::Integer(7));
+    }
+
+    #[test]
+    fn test_null_ordering() {
+        let o1 = object(None, 1, None, "a");
+        let o2 = object(Some(-5), 1, None, "b");
+        let o3 = object(None, 2, None, "c");
+        let (o1, o2, o3) = (
+            IsarObject::from_bytes(&o1),
+            IsarObject::from_bytes(&o2),
+            IsarObject::from_bytes(&o3),
+        );
+
+        let total =
+            SortExpression::arithmetic(ArithmeticOp::Add, property(PRICE), property(QUANTITY))
+                .unwrap();
+        assert_eq!(total.compare(&o1, &o2, true), Ordering::Less);
+        assert_eq!(total.compare(&o2, &o1, true), Ordering::Greater);
+        assert_eq!(total.compare(&o1, &o3, true), Ordering::Equal);
+
+        let nickname = SortExpression::coalesce(vec![property(NICKNAME)]).unwrap();
+        assert_eq!(nickname.compare(&o1, &o2, true), Ordering::Equal);
+    }
+}
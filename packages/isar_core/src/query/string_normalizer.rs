@@ -0,0 +1,122 @@
+use std::borrow::Cow;
+
+/// How strings are normalized before they are compared.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct StringNormalizer {
+    pub case_sensitive: bool,
+    /// Remove leading and trailing whitespace.
+    pub trim: bool,
+    /// Remove all whitespace.
+    pub ignore_whitespace: bool,
+    /// Compare accented latin letters like their base letter.
+    pub ignore_diacritics: bool,
+}
+
+impl StringNormalizer {
+    pub fn new(
+        case_sensitive: bool,
+        trim: bool,
+        ignore_whitespace: bool,
+        ignore_diacritics: bool,
+    ) -> Self {
+        StringNormalizer {
+            case_sensitive,
+            trim,
+            ignore_whitespace,
+            ignore_diacritics,
+        }
+    }
+
+    pub fn normalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let value = if self.trim { value.trim() } else { value };
+        let needs_mapping = self.ignore_whitespace
+            || self.ignore_diacritics
+            || (!self.case_sensitive && value.chars().any(|c| c.is_uppercase()));
+        if !needs_mapping {
+            return Cow::Borrowed(value);
+        }
+
+        let mut normalized = String::with_capacity(value.len());
+        for c in value.chars() {
+            if self.ignore_whitespace && c.is_whitespace() {
+                continue;
+            }
+            let c = if self.ignore_diacritics {
+                fold_diacritic(c)
+            } else {
+                c
+            };
+            if self.case_sensitive {
+                normalized.push(c);
+            } else {
+                normalized.extend(c.to_lowercase());
+            }
+        }
+        Cow::Owned(normalized)
+    }
+}
+
+fn fold_diacritic(c: char) -> char {
+    match c {
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => 'A',
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => 'C',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'Ď' | 'Đ' => 'D',
+        'ď' | 'đ' => 'd',
+        'È'..='Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => 'E',
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => 'G',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'Ĥ' | 'Ħ' => 'H',
+        'ĥ' | 'ħ' => 'h',
+        'Ì'..='Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => 'I',
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'Ĵ' => 'J',
+        'ĵ' => 'j',
+        'Ķ' => 'K',
+        'ķ' => 'k',
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => 'L',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => 'N',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => 'O',
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'Ŕ' | 'Ŗ' | 'Ř' => 'R',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => 'S',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'Ţ' | 'Ť' | 'Ŧ' => 'T',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'Ù'..='Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => 'U',
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'Ŵ' => 'W',
+        'ŵ' => 'w',
+        'Ý' | 'Ŷ' | 'Ÿ' => 'Y',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let exact = StringNormalizer::new(true, false, false, false);
+        assert!(matches!(exact.normalize(" Äb "), Cow::Borrowed(" Äb ")));
+
+        let trim = StringNormalizer::new(false, true, false, false);
+        assert_eq!(trim.normalize("  Hello World "), "hello world");
+
+        let whitespace = StringNormalizer::new(true, false, true, false);
+        assert_eq!(whitespace.normalize(" New\tYork "), "NewYork");
+
+        let diacritics = StringNormalizer::new(false, false, false, true);
+        assert_eq!(diacritics.normalize("Crème Brûlée"), "creme brulee");
+        assert_eq!(diacritics.normalize("Łódź"), "lodz");
+    }
+}
@@ -4,6 +4,7 @@ use crate::object::isar_object::IsarObject;
 use crate::query::id_where_clause::IdWhereClause;
 use crate::query::index_where_clause::IndexWhereClause;
 use crate::query::link_where_clause::LinkWhereClause;
+use crate::query::query_ast::WhereClauseAst;
 use intmap::IntMap;
 
 #[derive(Clone)]
@@ -38,6 +39,54 @@ impl WhereClause {
         }
     }
 
+    /// Iterates the matching ids without reading the objects from the data db.
+    pub fn iter_ids<F>(
+        &self,
+        cursors: &IsarCursors,
+        mut result_ids: Option<&mut IntMap<()>>,
+        mut callback: F,
+    ) -> Result<bool>
+    where
+        F: FnMut(i64) -> Result<bool>,
+    {
+        match self {
+            WhereClause::Id(wc) => wc.iter_ids(cursors, result_ids, callback),
+            WhereClause::Index(wc) => wc.iter_ids(cursors, |id| {
+                if let Some(result_ids) = result_ids.as_deref_mut() {
+                    if !result_ids.insert_checked(id as u64, ()) {
+                        return Ok(true);
+                    }
+                }
+                callback(id)
+            }),
+            WhereClause::Link(wc) => wc.iter_ids(cursors, result_ids, callback),
+        }
+    }
+
+    pub(crate) fn to_ast(&self) -> WhereClauseAst {
+        match self {
+            WhereClause::Id(wc) => wc.to_ast(),
+            WhereClause::Index(wc) => wc.to_ast(),
+            WhereClause::Link(wc) => wc.to_ast(),
+        }
+    }
+
+    /// Like [describe](Self::describe) but without bounds.
+    pub(crate) fn scan_type(&self) -> String {
+        match self {
+            WhereClause::Id(_) => "Id".to_string(),
+            _ => self.describe(),
+        }
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            WhereClause::Id(wc) => wc.describe(),
+            WhereClause::Index(wc) => format!("Index({})", wc.index_name()),
+            WhereClause::Link(wc) => format!("Link({})", wc.link_name()),
+        }
+    }
+
     pub(crate) fn is_overlapping(&self, other: &Self) -> bool {
         match (self, other) {
             (WhereClause::Id(wc1), WhereClause::Id(wc2)) => wc1.is_overlapping(wc2),
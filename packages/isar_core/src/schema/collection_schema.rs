@@ -1,14 +1,31 @@
 use crate::error::{schema_error, IsarError, Result};
 use crate::object::data_type::DataType;
 use crate::object::property::Property;
+use crate::schema::constraints::Constraints;
+use crate::schema::db_name;
 use crate::schema::index_schema::{IndexSchema, IndexType};
 use crate::schema::link_schema::LinkSchema;
-use crate::schema::property_schema::PropertySchema;
+use crate::schema::property_schema::{Collation, PropertySchema};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use super::schema_manager::SchemaManager;
 
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum IdReuse {
+    /// Ids of deleted objects at the end of the id range may be assigned again.
+    #[default]
+    Allow,
+    /// Auto increment ids are never assigned twice, even after the collection is cleared.
+    Never,
+}
+
+impl IdReuse {
+    fn is_allow(&self) -> bool {
+        *self == IdReuse::Allow
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Eq)]
 pub struct CollectionSchema {
     pub(crate) name: String,
@@ -21,6 +38,18 @@ pub struct CollectionSchema {
     pub(crate) links: Vec<LinkSchema>,
     #[serde(default)]
     pub(crate) version: u8,
+    #[serde(default)]
+    #[serde(rename = "modifiedAt")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) modified_at: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "idReuse")]
+    #[serde(skip_serializing_if = "IdReuse::is_allow")]
+    pub(crate) id_reuse: IdReuse,
+    #[serde(default)]
+    #[serde(rename = "appendOnly")]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) append_only: bool,
 }
 
 impl PartialEq for CollectionSchema {
@@ -44,13 +73,31 @@ impl CollectionSchema {
             indexes,
             links,
             version: SchemaManager::ISAR_FILE_VERSION,
+            modified_at: None,
+            id_reuse: IdReuse::Allow,
+            append_only: false,
         }
     }
 
+    pub fn with_modified_at(mut self, property_name: &str) -> CollectionSchema {
+        self.modified_at = Some(property_name.to_string());
+        self
+    }
+
+    pub fn with_id_reuse(mut self, id_reuse: IdReuse) -> CollectionSchema {
+        self.id_reuse = id_reuse;
+        self
+    }
+
+    /// Rejects updates and deletes of existing objects except for explicit purges.
+    pub fn with_append_only(mut self, append_only: bool) -> CollectionSchema {
+        self.append_only = append_only;
+        self
+    }
+
     fn verify_name(name: &str) -> Result<()> {
-        if name.is_empty() {
-            schema_error("Empty names are not allowed.")
-        } else if name.starts_with('_') {
+        db_name::verify_identifier(name)?;
+        if name.starts_with('_') {
             schema_error("Names must not begin with an underscore.")
         } else {
             Ok(())
@@ -64,6 +111,14 @@ impl CollectionSchema {
             schema_error("Embedded objects must not have Links or Indexes.")?;
         }
 
+        if self.embedded && self.id_reuse != IdReuse::Allow {
+            schema_error("Embedded objects do not have ids.")?;
+        }
+
+        if self.embedded && self.append_only {
+            schema_error("Embedded objects cannot be append-only.")?;
+        }
+
         let verify_target_col_exists = |col: &str, embedded: bool| -> Result<()> {
             if !collections
                 .iter()
@@ -79,6 +134,29 @@ impl CollectionSchema {
                 Self::verify_name(name)?;
             }
 
+            if property.collation != Collation::Binary
+                && property.data_type != DataType::String
+                && property.data_type != DataType::StringList
+            {
+                schema_error("Only String and StringList properties may have a collation.")?;
+            }
+
+            if let Some(constraints) = &property.constraints {
+                if self.embedded {
+                    schema_error("Embedded objects must not have constraints.")?;
+                }
+                constraints.verify(property.data_type)?;
+            }
+
+            if property.blob {
+                if property.data_type != DataType::ByteList {
+                    schema_error("Only ByteList properties may reference blobs.")?;
+                }
+                if self.embedded {
+                    schema_error("Embedded objects must not reference blobs.")?;
+                }
+            }
+
             if property.data_type == DataType::Object || property.data_type == DataType::ObjectList
             {
                 if let Some(target_col) = &property.target_col {
@@ -93,6 +171,19 @@ impl CollectionSchema {
             }
         }
 
+        if let Some(modified_at) = &self.modified_at {
+            if self.embedded {
+                schema_error("Embedded objects must not track modifications.")?;
+            }
+            let property = self
+                .properties
+                .iter()
+                .find(|p| p.name.as_ref() == Some(modified_at));
+            if property.map(|p| p.data_type) != Some(DataType::Long) {
+                schema_error("The modifiedAt property must be an existing Long property.")?;
+            }
+        }
+
         for link in &self.links {
             Self::verify_name(&link.name)?;
             verify_target_col_exists(&link.target_col, false)?;
@@ -117,6 +208,7 @@ impl CollectionSchema {
         }
 
         for index in &self.indexes {
+            db_name::verify_identifier(&index.name)?;
             if index.properties.is_empty() {
                 schema_error("At least one property needs to be added to a valid index")?;
             } else if index.properties.len() > 3 {
@@ -187,6 +279,17 @@ impl CollectionSchema {
                 {
                     schema_error("Only String and StringList indexes may be case sensitive.")?;
                 }
+                if property.collation == Collation::NoCase && index_property.case_sensitive {
+                    schema_error("Indexes of NoCase properties must not be case sensitive.")?;
+                }
+                let foldable = match property.data_type {
+                    DataType::String => true,
+                    DataType::StringList => index_property.index_type != IndexType::Hash,
+                    _ => false,
+                };
+                if index_property.fold_diacritics && !foldable {
+                    schema_error("Only string indexes may fold diacritics.")?;
+                }
             }
         }
 
@@ -228,6 +331,32 @@ impl CollectionSchema {
         properties
     }
 
+    pub(crate) fn get_constraints(&self, properties: &[Property]) -> Vec<(Property, Constraints)> {
+        self.properties
+            .iter()
+            .filter_map(|p| {
+                let constraints = p.constraints.clone()?;
+                let property = properties
+                    .iter()
+                    .find(|prop| Some(&prop.name) == p.name.as_ref())?;
+                Some((property.clone(), constraints))
+            })
+            .collect()
+    }
+
+    pub(crate) fn get_blob_properties(&self, properties: &[Property]) -> Vec<Property> {
+        self.properties
+            .iter()
+            .filter(|p| p.blob)
+            .filter_map(|p| {
+                properties
+                    .iter()
+                    .find(|prop| Some(&prop.name) == p.name.as_ref())
+                    .cloned()
+            })
+            .collect()
+    }
+
     pub fn to_json_bytes(&self) -> Result<Vec<u8>> {
         serde_json::to_vec(self).map_err(|_| IsarError::SchemaError {
             message: "Could not serialize schema.".to_string(),
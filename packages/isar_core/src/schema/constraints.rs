@@ -0,0 +1,318 @@
+use crate::error::{schema_error, IsarError, Result};
+use crate::object::data_type::DataType;
+use crate::object::isar_object::IsarObject;
+use crate::object::property::Property;
+use crate::query::fast_wild_match::fast_wild_match;
+use serde::{Deserialize, Serialize};
+
+/// What happens when a String exceeds its max length.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq, Default)]
+pub enum LengthPolicy {
+    #[default]
+    Error,
+    Truncate,
+}
+
+impl LengthPolicy {
+    fn is_error(&self) -> bool {
+        *self == LengthPolicy::Error
+    }
+}
+
+/// Constraints checked for every object written to a collection. Null values always pass.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Constraints {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "LengthPolicy::is_error")]
+    pub length_policy: LengthPolicy,
+    /// Wildcard pattern (`*` and `?`) string values have to match.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+impl Constraints {
+    pub(crate) fn verify(&self, data_type: DataType) -> Result<()> {
+        let element_type = data_type.get_element_type().unwrap_or(data_type);
+        let numeric = matches!(
+            element_type,
+            DataType::Byte | DataType::Int | DataType::Long | DataType::Float | DataType::Double
+        );
+        if (self.min.is_some() || self.max.is_some()) && !numeric {
+            schema_error("Only numeric properties may have a min or max constraint.")?;
+        }
+        if self.min.is_some_and(f64::is_nan) || self.max.is_some_and(f64::is_nan) {
+            schema_error("The min and max constraints must not be NaN.")?;
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                schema_error("The min constraint must not be greater than max.")?;
+            }
+        }
+        if self.max_length.is_some() && data_type != DataType::String && data_type.is_scalar() {
+            schema_error("Only String and list properties may have a max length constraint.")?;
+        }
+        if self.length_policy == LengthPolicy::Truncate
+            && (self.max_length.is_none() || data_type != DataType::String)
+        {
+            schema_error("Only String properties with a max length may be truncated.")?;
+        }
+        if self.pattern.is_some() && element_type != DataType::String {
+            schema_error("Only String and StringList properties may have a pattern constraint.")?;
+        }
+        Ok(())
+    }
+
+    fn violation<T>(property: &Property, message: String) -> Result<T> {
+        Err(IsarError::ValidationFailed {
+            property: property.name.clone(),
+            message,
+        })
+    }
+
+    fn check_number(&self, property: &Property, value: f64) -> Result<()> {
+        if let Some(min) = self.min {
+            if value < min {
+                return Self::violation(property, format!("{} is less than {}.", value, min));
+            }
+        }
+        if let Some(max) = self.max {
+            if value > max {
+                return Self::violation(property, format!("{} is greater than {}.", value, max));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_numbers(&self, property: &Property, values: impl Iterator<Item = f64>) -> Result<()> {
+        for value in values {
+            self.check_number(property, value)?;
+        }
+        Ok(())
+    }
+
+    fn check_string(&self, property: &Property, value: &str) -> Result<()> {
+        if property.data_type == DataType::String {
+            if let Some(max_length) = self.max_length {
+                let length = value.chars().count();
+                if length > max_length {
+                    return Self::violation(
+                        property,
+                        format!("Length {} exceeds {}.", length, max_length),
+                    );
+                }
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            if !fast_wild_match(value, pattern) {
+                return Self::violation(
+                    property,
+                    format!("\"{}\" does not match \"{}\".", value, pattern),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the value cut to the max length if it is too long and may be truncated.
+    pub(crate) fn truncate<'a>(
+        &self,
+        property: &Property,
+        object: &'a IsarObject,
+    ) -> Option<&'a str> {
+        if self.length_policy != LengthPolicy::Truncate || property.data_type != DataType::String {
+            return None;
+        }
+        let max_length = self.max_length?;
+        let value = object.read_string(property.offset)?;
+        let (end, _) = value.char_indices().nth(max_length)?;
+        Some(&value[..end])
+    }
+
+    pub(crate) fn validate(&self, property: &Property, object: IsarObject) -> Result<()> {
+        let offset = property.offset;
+        if !object.contains_offset(offset) || object.is_null(offset, property.data_type) {
+            return Ok(());
+        }
+
+        if let Some(max_length) = self.max_length {
+            if let Some(length) = object.read_length(offset) {
+                if property.data_type != DataType::String && length > max_length {
+                    return Self::violation(
+                        property,
+                        format!("Length {} exceeds {}.", length, max_length),
+                    );
+                }
+            }
+        }
+
+        match property.data_type {
+            DataType::Byte => self.check_number(property, object.read_byte(offset) as f64),
+            DataType::Int => self.check_number(property, object.read_int(offset) as f64),
+            DataType::Long => self.check_number(property, object.read_long(offset) as f64),
+            DataType::Float => self.check_number(property, object.read_float(offset) as f64),
+            DataType::Double => self.check_number(property, object.read_double(offset)),
+            DataType::String => self.check_string(property, object.read_string(offset).unwrap()),
+            DataType::ByteList => {
+                let values = object.read_byte_list(offset).unwrap_or_default();
+                self.check_numbers(property, values.iter().map(|v| *v as f64))
+            }
+            DataType::IntList => {
+                let values = object.read_int_or_null_list(offset).unwrap_or_default();
+                self.check_numbers(property, values.into_iter().flatten().map(|v| v as f64))
+            }
+            DataType::LongList => {
+                let values = object.read_long_or_null_list(offset).unwrap_or_default();
+                self.check_numbers(property, values.into_iter().flatten().map(|v| v as f64))
+            }
+            DataType::FloatList => {
+                let values = object.read_float_or_null_list(offset).unwrap_or_default();
+                self.check_numbers(property, values.into_iter().flatten().map(|v| v as f64))
+            }
+            DataType::DoubleList => {
+                let values = object.read_double_or_null_list(offset).unwrap_or_default();
+                self.check_numbers(property, values.into_iter().flatten())
+            }
+            DataType::StringList => {
+                let values = object.read_string_list(offset).unwrap_or_default();
+                for value in values.into_iter().flatten() {
+                    self.check_string(property, value)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::object_builder::ObjectBuilder;
+
+    #[test]
+    fn test_validate_number() {
+        let p = Property::debug(DataType::Long, 2);
+        let constraints = Constraints {
+            min: Some(0.0),
+            max: Some(10.0),
+            ..Default::default()
+        };
+
+        for (value, valid) in [(0, true), (10, true), (-1, false), (11, false)] {
+            let mut b = ObjectBuilder::new(&[p.clone()], None);
+            b.write_long(p.offset, value);
+            let result = constraints.validate(&p, b.finish());
+            assert_eq!(result.is_ok(), valid);
+        }
+
+        let mut b = ObjectBuilder::new(&[p.clone()], None);
+        b.write_null(p.offset, p.data_type);
+        assert!(constraints.validate(&p, b.finish()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_string() {
+        let p = Property::debug(DataType::String, 2);
+        let constraints = Constraints {
+            max_length: Some(5),
+            pattern: Some("a*".to_string()),
+            ..Default::default()
+        };
+
+        for (value, valid) in [("abc", true), ("abcdef", false), ("bc", false)] {
+            let mut b = ObjectBuilder::new(&[p.clone()], None);
+            b.write_string(p.offset, Some(value));
+            let result = constraints.validate(&p, b.finish());
+            assert_eq!(result.is_ok(), valid);
+        }
+    }
+
+    #[test]
+    fn test_truncate() {
+        let p = Property::debug(DataType::String, 2);
+        let mut constraints = Constraints {
+            max_length: Some(3),
+            ..Default::default()
+        };
+
+        let mut b = ObjectBuilder::new(&[p.clone()], None);
+        b.write_string(p.offset, Some("äbcdef"));
+        let object = b.finish();
+        assert_eq!(constraints.truncate(&p, &object), None);
+
+        constraints.length_policy = LengthPolicy::Truncate;
+        assert_eq!(constraints.truncate(&p, &object), Some("äbc"));
+
+        let mut b = ObjectBuilder::new(&[p.clone()], None);
+        b.write_string(p.offset, Some("abc"));
+        assert_eq!(constraints.truncate(&p, &b.finish()), None);
+    }
+
+    #[test]
+    fn test_verify() {
+        let min = Constraints {
+            min: Some(1.0),
+            ..Default::default()
+        };
+        assert!(min.verify(DataType::Int).is_ok());
+        assert!(min.verify(DataType::DoubleList).is_ok());
+        assert!(min.verify(DataType::String).is_err());
+
+        let range = Constraints {
+            min: Some(1.0),
+            max: Some(1.0),
+            ..Default::default()
+        };
+        assert!(range.verify(DataType::Long).is_ok());
+        let inverted = Constraints {
+            min: Some(2.0),
+            ..range.clone()
+        };
+        assert!(inverted.verify(DataType::Long).is_err());
+        for nan in [
+            Constraints {
+                min: Some(f64::NAN),
+                ..Default::default()
+            },
+            Constraints {
+                max: Some(f64::NAN),
+                ..range
+            },
+        ] {
+            assert!(nan.verify(DataType::Double).is_err());
+        }
+
+        let pattern = Constraints {
+            pattern: Some("*".to_string()),
+            ..Default::default()
+        };
+        assert!(pattern.verify(DataType::StringList).is_ok());
+        assert!(pattern.verify(DataType::Long).is_err());
+
+        let max_length = Constraints {
+            max_length: Some(1),
+            ..Default::default()
+        };
+        assert!(max_length.verify(DataType::IntList).is_ok());
+        assert!(max_length.verify(DataType::Int).is_err());
+
+        let truncate = Constraints {
+            max_length: Some(1),
+            length_policy: LengthPolicy::Truncate,
+            ..Default::default()
+        };
+        assert!(truncate.verify(DataType::String).is_ok());
+        assert!(truncate.verify(DataType::StringList).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::error::{schema_error, Result};
+use crate::schema::collection_schema::CollectionSchema;
+use itertools::Itertools;
+
+/// Names of the internal databases. User names cannot begin with an underscore so they never
+/// clash with these.
+pub(crate) const INFO: &str = "_info";
+pub(crate) const BLOBS: &str = "_blobs";
+pub(crate) const SEQUENCES: &str = "_sequences";
+
+/// Checks that a user provided name can safely be used as part of a database name.
+pub(crate) fn verify_identifier(name: &str) -> Result<()> {
+    if name.is_empty() {
+        schema_error("Empty names are not allowed.")
+    } else if name.chars().any(|c| c.is_control()) {
+        schema_error("Names must not contain control characters.")
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn index(col: &str, index: &str) -> String {
+    format!("_i_{}_{}", col, index)
+}
+
+pub(crate) fn link(col: &str, link: &str) -> String {
+    format!("_l_{}_{}", col, link)
+}
+
+pub(crate) fn backlink(col: &str, link: &str) -> String {
+    format!("_b_{}_{}", col, link)
+}
+
+/// All database names used by a schema including the internal ones.
+fn all(collections: &[CollectionSchema]) -> Vec<String> {
+    let mut names = vec![INFO.to_string(), BLOBS.to_string(), SEQUENCES.to_string()];
+    for col in collections.iter().filter(|c| !c.embedded) {
+        names.push(col.name.clone());
+        for i in &col.indexes {
+            names.push(index(&col.name, &i.name));
+        }
+        for l in &col.links {
+            names.push(link(&col.name, &l.name));
+            names.push(backlink(&col.name, &l.name));
+        }
+    }
+    names
+}
+
+/// Collection, index and link names are joined with underscores so different names may map to
+/// the same database (e.g. collection `a_b` with index `c` and collection `a` with index `b_c`).
+pub(crate) fn verify_unique(collections: &[CollectionSchema]) -> Result<()> {
+    let names = all(collections);
+    if names.iter().unique().count() != names.len() {
+        schema_error("Collection, index and link names must not result in the same database.")
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::data_type::DataType;
+    use crate::schema::index_schema::{IndexPropertySchema, IndexSchema, IndexType};
+    use crate::schema::property_schema::PropertySchema;
+
+    fn col(name: &str, index: &str) -> CollectionSchema {
+        let properties = vec![PropertySchema::new(
+            Some("value".to_string()),
+            DataType::Long,
+            None,
+        )];
+        let indexes = vec![IndexSchema::new(
+            index,
+            vec![IndexPropertySchema::new("value", IndexType::Value, false)],
+            false,
+            false,
+        )];
+        CollectionSchema::new(name, false, properties, indexes, vec![])
+    }
+
+    #[test]
+    fn test_verify_identifier() {
+        assert!(verify_identifier("").is_err());
+        assert!(verify_identifier("name").is_ok());
+        assert!(verify_identifier("näme with spaces").is_ok());
+        for c in (0u8..0x20).chain([0x7f]) {
+            let name = format!("na{}me", c as char);
+            assert!(verify_identifier(&name).is_err());
+        }
+    }
+
+    #[test]
+    fn test_verify_unique() {
+        assert!(verify_unique(&[col("a_b", "c"), col("a", "c")]).is_ok());
+        assert!(verify_unique(&[col("a_b", "c"), col("a", "b_c")]).is_err());
+    }
+}
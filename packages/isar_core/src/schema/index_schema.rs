@@ -18,6 +18,10 @@ pub struct IndexPropertySchema {
     pub(crate) index_type: IndexType,
     #[serde(rename = "caseSensitive")]
     pub(crate) case_sensitive: bool,
+    #[serde(default)]
+    #[serde(rename = "foldDiacritics")]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) fold_diacritics: bool,
 }
 
 impl IndexPropertySchema {
@@ -26,8 +30,15 @@ impl IndexPropertySchema {
             name: name.to_string(),
             index_type,
             case_sensitive,
+            fold_diacritics: false,
         }
     }
+
+    /// Stores accented latin letters like their base letter so lookups ignore diacritics.
+    pub fn with_fold_diacritics(mut self, fold_diacritics: bool) -> IndexPropertySchema {
+        self.fold_diacritics = fold_diacritics;
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq)]
@@ -60,7 +71,12 @@ impl IndexSchema {
             .iter()
             .map(|ip| {
                 let property = properties.iter().find(|p| ip.name == *p.name).unwrap();
-                IndexProperty::new(property.clone(), ip.index_type, ip.case_sensitive)
+                IndexProperty::new(
+                    property.clone(),
+                    ip.index_type,
+                    ip.case_sensitive,
+                    ip.fold_diacritics,
+                )
             })
             .collect_vec();
         IsarIndex::new(&self.name, db, index_properties, self.unique, self.replace)
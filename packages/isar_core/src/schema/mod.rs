@@ -1,4 +1,6 @@
 pub mod collection_schema;
+pub mod constraints;
+pub(crate) mod db_name;
 pub mod index_schema;
 pub mod link_schema;
 pub(crate) mod migrate_v1;
@@ -7,13 +9,22 @@ pub(crate) mod schema_manager;
 
 use crate::error::{schema_error, Result};
 use crate::schema::collection_schema::CollectionSchema;
+use intmap::IntMap;
 use itertools::Itertools;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use xxhash_rust::xxh3::xxh3_64_with_seed;
+use std::sync::Mutex;
+use xxhash_rust::xxh3::{xxh3_64, xxh3_64_with_seed};
+
+/// Verified schemas keyed by the hash of their JSON. Opening an instance again, from another
+/// isolate or after it was closed, reuses the schema and its collection hashes.
+static SCHEMA_CACHE: Lazy<Mutex<IntMap<Schema>>> = Lazy::new(|| Mutex::new(IntMap::new()));
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Schema {
     pub(crate) collections: Vec<CollectionSchema>,
+    #[serde(skip)]
+    collection_hashes: Option<Vec<(String, u64)>>,
 }
 
 impl Schema {
@@ -25,19 +36,39 @@ impl Schema {
         for col in &collections {
             col.verify(&collections)?;
         }
+        db_name::verify_unique(&collections)?;
 
-        let schema = Schema { collections };
+        let schema = Schema {
+            collections,
+            collection_hashes: None,
+        };
         Ok(schema)
     }
 
+    /// Parses and verifies the schema. Schemas with the same JSON are only verified once.
     pub fn from_json(json: &[u8]) -> Result<Schema> {
+        let json_hash = xxh3_64(json);
+        if let Some(schema) = Self::get_cached(json_hash) {
+            return Ok(schema);
+        }
+
         if let Ok(collections) = serde_json::from_slice::<Vec<CollectionSchema>>(json) {
-            Schema::new(collections)
+            let mut schema = Schema::new(collections)?;
+            schema.collection_hashes();
+            SCHEMA_CACHE
+                .lock()
+                .unwrap()
+                .insert(json_hash, schema.clone());
+            Ok(schema)
         } else {
             schema_error("Could not deserialize schema JSON")
         }
     }
 
+    pub(crate) fn get_cached(json_hash: u64) -> Option<Schema> {
+        SCHEMA_CACHE.lock().unwrap().get(json_hash).cloned()
+    }
+
     pub(crate) fn get_collection(&self, name: &str, embedded: bool) -> Option<&CollectionSchema> {
         self.collections
             .iter()
@@ -54,11 +85,80 @@ impl Schema {
         count
     }
 
-    pub(crate) fn hash(&mut self) -> u64 {
+    pub(crate) fn collection_hashes(&mut self) -> Vec<(String, u64)> {
+        if let Some(collection_hashes) = &self.collection_hashes {
+            return collection_hashes.clone();
+        }
         self.collections.sort_by(|a, b| a.name.cmp(&b.name));
-        self.collections
+        let collection_hashes: Vec<_> = self
+            .collections
             .iter()
-            .fold(0, |seed, col| xxh3_64_with_seed(col.name.as_bytes(), seed))
+            .map(|col| {
+                let bytes = serde_json::to_vec(col).unwrap();
+                (col.name.clone(), xxh3_64(&bytes))
+            })
+            .collect();
+        self.collection_hashes = Some(collection_hashes.clone());
+        collection_hashes
+    }
+
+    pub(crate) fn hash_collections(collection_hashes: &[(String, u64)]) -> u64 {
+        collection_hashes.iter().fold(0, |seed, (_, hash)| {
+            xxh3_64_with_seed(&hash.to_le_bytes(), seed)
+        })
+    }
+
+    pub(crate) fn diff_collections(a: &[(String, u64)], b: &[(String, u64)]) -> Vec<String> {
+        a.iter()
+            .chain(b.iter())
+            .filter(|(name, hash)| {
+                let in_a = a.iter().any(|(n, h)| n == name && h == hash);
+                let in_b = b.iter().any(|(n, h)| n == name && h == hash);
+                !in_a || !in_b
+            })
+            .map(|(name, _)| name.clone())
+            .unique()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::IsarInstance;
+    use crate::object::data_type::DataType;
+    use crate::schema::property_schema::PropertySchema;
+
+    fn schema_json(data_type: DataType) -> Vec<u8> {
+        let property = PropertySchema::new(Some("value".to_string()), data_type, None);
+        let col = CollectionSchema::new("col", false, vec![property], vec![], vec![]);
+        serde_json::to_vec(&vec![col]).unwrap()
+    }
+
+    #[test]
+    fn test_schema_cache() {
+        let name = "test_schema_cache";
+        let json = schema_json(DataType::Long);
+        let json_hash = xxh3_64(&json);
+        assert!(Schema::get_cached(json_hash).is_none());
+
+        let schema = Schema::from_json(&json).unwrap();
+        let cached = Schema::get_cached(json_hash).unwrap();
+        assert!(cached.collection_hashes.is_some());
+
+        let isar = IsarInstance::open_test(name, schema);
+        let other_json = schema_json(DataType::Int);
+        let other = Schema::from_json(&other_json).unwrap();
+        let dir = std::env::temp_dir();
+        assert!(IsarInstance::open(name, dir.to_str(), other, 10, 0, false, None, None).is_err());
+        assert!(Schema::from_json(b"[").is_err());
+
+        // The schema stays cached after the instance is closed.
+        isar.close_and_delete();
+        assert!(Schema::get_cached(json_hash).is_some());
+        let again = Schema::from_json(&json).unwrap();
+        let isar = IsarInstance::open_test(name, again);
+        isar.close_and_delete();
     }
 }
 
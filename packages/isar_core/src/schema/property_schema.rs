@@ -1,8 +1,22 @@
 use crate::object::data_type::DataType;
 use crate::object::property::Property;
+use crate::schema::constraints::Constraints;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Eq)]
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Collation {
+    #[default]
+    Binary,
+    NoCase,
+}
+
+impl Collation {
+    fn is_binary(&self) -> bool {
+        *self == Collation::Binary
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct PropertySchema {
     pub(crate) name: Option<String>,
     #[serde(rename = "type")]
@@ -10,6 +24,15 @@ pub struct PropertySchema {
     #[serde(default)]
     #[serde(rename = "target")]
     pub(crate) target_col: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Collation::is_binary")]
+    pub(crate) collation: Collation,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) constraints: Option<Constraints>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) blob: bool,
 }
 
 impl PropertySchema {
@@ -22,12 +45,33 @@ impl PropertySchema {
             name,
             data_type,
             target_col,
+            collation: Collation::Binary,
+            constraints: None,
+            blob: false,
         }
     }
 
+    pub fn with_collation(mut self, collation: Collation) -> PropertySchema {
+        self.collation = collation;
+        self
+    }
+
+    pub fn with_constraints(mut self, constraints: Constraints) -> PropertySchema {
+        self.constraints = Some(constraints);
+        self
+    }
+
+    /// The property stores the key of a blob. Referenced blobs are kept until no object
+    /// references them anymore.
+    pub fn with_blob(mut self, blob: bool) -> PropertySchema {
+        self.blob = blob;
+        self
+    }
+
     pub(crate) fn as_property(&self, offset: usize) -> Option<Property> {
         if let Some(name) = &self.name {
-            let p = Property::new(name, self.data_type, offset, self.target_col.as_deref());
+            let mut p = Property::new(name, self.data_type, offset, self.target_col.as_deref());
+            p.case_sensitive = self.collation == Collation::Binary;
             Some(p)
         } else {
             None
@@ -49,3 +93,5 @@ impl PartialEq for PropertySchema {
         self.name == other.name && type_eq && self.target_col == other.target_col
     }
 }
+
+impl Eq for PropertySchema {}
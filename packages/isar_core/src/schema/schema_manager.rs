@@ -1,7 +1,9 @@
-use super::collection_schema::CollectionSchema;
+use super::collection_schema::{CollectionSchema, IdReuse};
+use super::db_name;
 use super::index_schema::IndexSchema;
 use super::link_schema::LinkSchema;
 use super::Schema;
+use crate::blob::BlobRefs;
 use crate::collection::IsarCollection;
 use crate::cursor::IsarCursors;
 use crate::error::{schema_error, IsarError, Result};
@@ -12,9 +14,11 @@ use crate::mdbx::cursor::{Cursor, UnboundCursor};
 use crate::mdbx::{db::Db, txn::Txn};
 use crate::object::property::Property;
 use crate::schema::migrate_v1::migrate_v1;
+use crate::stats::{MigrationOperation, MigrationRecord};
 use intmap::IntMap;
 use once_cell::sync::Lazy;
 use std::ops::Deref;
+use std::time::Instant;
 use xxhash_rust::xxh3::xxh3_64;
 
 static OLD_INFO_VERSION_KEY: Lazy<IndexKey> = Lazy::new(|| {
@@ -32,23 +36,32 @@ static OLD_INFO_SCHEMA_KEY: Lazy<IndexKey> = Lazy::new(|| {
 pub(crate) struct SchemaManager {
     instance_id: u64,
     info_db: Db,
+    sequence_db: Db,
+    pub blob_db: Db,
     pub schemas: Vec<CollectionSchema>,
+    /// Migrations that have not been reported yet.
+    pub migrations: Vec<MigrationRecord>,
 }
 
 impl SchemaManager {
     pub const ISAR_FILE_VERSION: u8 = 2;
 
     pub fn create(instance_id: u64, txn: &Txn) -> Result<Self> {
-        let info_db = Db::open(txn, Some("_info"), false, false, false)?;
+        let info_db = Db::open(txn, Some(db_name::INFO), false, false, false)?;
         let mut info_cursor = UnboundCursor::new().bind(txn, info_db)?;
 
         Self::migrate_old_info(&mut info_cursor)?;
 
         let schemas = Self::get_schemas(&mut info_cursor)?;
+        let sequence_db = Db::open(txn, Some(db_name::SEQUENCES), true, false, false)?;
+        let blob_db = Db::open(txn, Some(db_name::BLOBS), false, false, false)?;
         let manager = SchemaManager {
             instance_id,
             info_db,
+            sequence_db,
+            blob_db,
             schemas,
+            migrations: vec![],
         };
         Ok(manager)
     }
@@ -113,14 +126,14 @@ impl SchemaManager {
     }
 
     pub fn open_index_db(txn: &Txn, col: &CollectionSchema, index: &IndexSchema) -> Result<Db> {
-        let db_name = format!("_i_{}_{}", col.name, index.name);
+        let db_name = db_name::index(&col.name, &index.name);
         Db::open(txn, Some(&db_name), false, !index.unique, false)
     }
 
     pub fn open_link_dbs(txn: &Txn, col: &CollectionSchema, link: &LinkSchema) -> Result<(Db, Db)> {
-        let link_db_name = format!("_l_{}_{}", col.name, link.name);
+        let link_db_name = db_name::link(&col.name, &link.name);
         let db = Db::open(txn, Some(&link_db_name), true, true, true)?;
-        let backlink_db_name = format!("_b_{}_{}", col.name, link.name);
+        let backlink_db_name = db_name::backlink(&col.name, &link.name);
         let bl_db = Db::open(txn, Some(&backlink_db_name), true, true, true)?;
         Ok((db, bl_db))
     }
@@ -152,14 +165,25 @@ impl SchemaManager {
         txn: &Txn,
         schema: &mut CollectionSchema,
         existing_schema: &CollectionSchema,
+        operations: &mut Vec<MigrationOperation>,
     ) -> Result<Vec<u64>> {
+        for property in &schema.properties {
+            if !existing_schema.properties.contains(property) {
+                let name = property.name.clone().unwrap_or_default();
+                operations.push(MigrationOperation::AddProperty(name));
+            }
+        }
         let removed_properties = schema.merge_properties(existing_schema)?;
+        for name in &removed_properties {
+            operations.push(MigrationOperation::RemoveProperty(name.clone()));
+        }
 
         let mut added_indexes = IntMap::new();
         for index in &schema.indexes {
             if !existing_schema.indexes.contains(index) {
                 let index_id = xxh3_64(index.name.as_bytes());
                 added_indexes.insert(index_id, ());
+                operations.push(MigrationOperation::CreateIndex(index.name.clone()));
             }
         }
 
@@ -172,17 +196,20 @@ impl SchemaManager {
 
             if removed_index || changed_property {
                 Self::delete_index(txn, existing_schema, existing_index)?;
+                operations.push(MigrationOperation::DropIndex(existing_index.name.clone()));
             }
 
             if !removed_index && changed_property {
                 let index_id = xxh3_64(existing_index.name.as_bytes());
                 added_indexes.insert(index_id, ());
+                operations.push(MigrationOperation::CreateIndex(existing_index.name.clone()));
             }
         }
 
         for link in &existing_schema.links {
             if !schema.links.contains(link) {
                 Self::delete_link(txn, existing_schema, link)?;
+                operations.push(MigrationOperation::DropLink(link.name.clone()));
             }
         }
 
@@ -196,6 +223,7 @@ impl SchemaManager {
         schemas: &Schema,
     ) -> Result<IsarCollection> {
         let cursors = IsarCursors::new(txn, vec![]);
+        let start = Instant::now();
 
         let mut existing_schema = self
             .schemas
@@ -203,19 +231,28 @@ impl SchemaManager {
             .position(|s| s.name == schema.name)
             .map(|index| self.schemas.remove(index));
 
+        let old_hash = existing_schema
+            .as_ref()
+            .map(Self::hash_schema)
+            .transpose()?;
+        let mut operations = vec![];
+        let mut rewritten = false;
         let added_indexes = if let Some(existing_schema) = &mut existing_schema {
             if existing_schema.version == 1 {
-                migrate_v1(txn, existing_schema)?
+                migrate_v1(txn, existing_schema)?;
+                operations.push(MigrationOperation::UpgradeFileFormat);
+                rewritten = true;
             } else if existing_schema.version != Self::ISAR_FILE_VERSION {
                 return Err(IsarError::VersionError {});
             }
-            Self::perform_migration(txn, &mut schema, existing_schema)?
+            Self::perform_migration(txn, &mut schema, existing_schema, &mut operations)?
         } else {
             vec![]
         };
         let mut info_cursor = cursors.get_cursor(self.info_db)?;
         schema.version = Self::ISAR_FILE_VERSION;
         Self::save_schema(&mut info_cursor, &schema)?;
+        let new_hash = Self::hash_schema(&schema)?;
         let schema = schema; // no longer mutable beyond this point
 
         let db = Self::open_collection_db(txn, &schema)?;
@@ -227,6 +264,22 @@ impl SchemaManager {
         let indexes = Self::open_indexes(txn, &schema, &properties)?;
         let links = Self::open_links(txn, db, &schema, schemas)?;
         let backlinks = Self::open_backlinks(txn, db, &schema, schemas)?;
+        let modified_at = schema
+            .modified_at
+            .as_ref()
+            .and_then(|name| properties.iter().find(|p| &p.name == name).cloned());
+        let sequence_db = if schema.id_reuse == IdReuse::Never {
+            Some(self.sequence_db)
+        } else {
+            None
+        };
+        let constraints = schema.get_constraints(&properties);
+        let blob_properties = schema.get_blob_properties(&properties);
+        let blob_refs = if !blob_properties.is_empty() {
+            Some(BlobRefs::new(self.blob_db, blob_properties))
+        } else {
+            None
+        };
         let col = IsarCollection::new(
             db,
             self.instance_id,
@@ -236,6 +289,11 @@ impl SchemaManager {
             indexes,
             links,
             backlinks,
+            modified_at,
+            sequence_db,
+            constraints,
+            blob_refs,
+            schema.append_only,
         );
 
         col.init_auto_increment(&cursors)?;
@@ -243,9 +301,31 @@ impl SchemaManager {
             col.fill_indexes(&added_indexes, &cursors)?;
         }
 
+        if let Some(old_hash) = old_hash {
+            if old_hash != new_hash {
+                let rows_touched = if rewritten || !added_indexes.is_empty() {
+                    cursors.db_stat(db)?.0
+                } else {
+                    0
+                };
+                self.migrations.push(MigrationRecord {
+                    collection: schema.name.clone(),
+                    old_hash,
+                    new_hash,
+                    operations,
+                    duration_micros: start.elapsed().as_micros() as u64,
+                    rows_touched,
+                });
+            }
+        }
+
         Ok(col)
     }
 
+    fn hash_schema(schema: &CollectionSchema) -> Result<u64> {
+        Ok(xxh3_64(&schema.to_json_bytes()?))
+    }
+
     fn get_embedded_properties(
         schemas: &Schema,
         properties: &[Property],
@@ -0,0 +1,315 @@
+use crate::error::{IsarError, Result};
+use crate::instance::CompactCondition;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Writes committed to a collection since the instance was opened.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct WriteStats {
+    pub puts: u64,
+    pub deletes: u64,
+    pub bytes: u64,
+    /// Strings that were cut to their max length.
+    pub truncated: u64,
+}
+
+impl WriteStats {
+    pub(crate) fn put(bytes: usize) -> Self {
+        WriteStats {
+            puts: 1,
+            deletes: 0,
+            bytes: bytes as u64,
+            truncated: 0,
+        }
+    }
+
+    pub(crate) fn delete() -> Self {
+        WriteStats {
+            puts: 0,
+            deletes: 1,
+            bytes: 0,
+            truncated: 0,
+        }
+    }
+
+    pub(crate) fn add(&mut self, other: WriteStats) {
+        self.puts += other.puts;
+        self.deletes += other.deletes;
+        self.bytes += other.bytes;
+        self.truncated += other.truncated;
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct WriteCounter {
+    puts: AtomicU64,
+    deletes: AtomicU64,
+    bytes: AtomicU64,
+    truncated: AtomicU64,
+}
+
+impl WriteCounter {
+    pub fn add(&self, stats: WriteStats) {
+        self.puts.fetch_add(stats.puts, Ordering::Relaxed);
+        self.deletes.fetch_add(stats.deletes, Ordering::Relaxed);
+        self.bytes.fetch_add(stats.bytes, Ordering::Relaxed);
+        self.truncated.fetch_add(stats.truncated, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> WriteStats {
+        WriteStats {
+            puts: self.puts.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            truncated: self.truncated.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counts how often properties were filtered or sorted by without an index.
+#[derive(Default)]
+pub(crate) struct IndexAdvisor {
+    hits: Mutex<HashMap<String, u64>>,
+}
+
+impl IndexAdvisor {
+    pub fn record(&self, property: &str) {
+        let mut hits = self.hits.lock().unwrap();
+        *hits.entry(property.to_string()).or_insert(0) += 1;
+    }
+
+    /// Properties ordered by their hits starting with the most used one.
+    pub fn advice(&self) -> Vec<IndexAdvice> {
+        let hits = self.hits.lock().unwrap();
+        let mut advice: Vec<_> = hits
+            .iter()
+            .map(|(property, hits)| IndexAdvice {
+                property: property.clone(),
+                hits: *hits,
+            })
+            .collect();
+        advice.sort_by(|a, b| {
+            b.hits
+                .cmp(&a.hits)
+                .then_with(|| a.property.cmp(&b.property))
+        });
+        advice
+    }
+
+    pub fn reset(&self) {
+        self.hits.lock().unwrap().clear();
+    }
+}
+
+/// A property that queries used without an index.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct IndexAdvice {
+    pub property: String,
+    pub hits: u64,
+}
+
+/// Load of the writer. Producers can throttle themselves when writes queue up or the database
+/// approaches its maximum size.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritePressure {
+    /// Write transactions that are running or waiting for the write lock.
+    pub pending_write_txns: u32,
+    /// Committed bytes that have not been synced to disk yet.
+    pub unsynced_bytes: u64,
+    pub used_bytes: u64,
+    pub max_bytes: u64,
+}
+
+/// Counts a write transaction as pending until it is dropped.
+pub(crate) struct PendingWrite(Arc<AtomicU32>);
+
+impl PendingWrite {
+    pub fn new(counter: &Arc<AtomicU32>) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        PendingWrite(counter.clone())
+    }
+}
+
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A single change applied by a schema migration.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", content = "name", rename_all = "camelCase")]
+pub enum MigrationOperation {
+    UpgradeFileFormat,
+    AddProperty(String),
+    RemoveProperty(String),
+    CreateIndex(String),
+    DropIndex(String),
+    DropLink(String),
+}
+
+/// Reported once the migration of a collection has been committed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationRecord {
+    pub collection: String,
+    pub old_hash: u64,
+    pub new_hash: u64,
+    pub operations: Vec<MigrationOperation>,
+    pub duration_micros: u64,
+    /// Objects that had to be read or rewritten.
+    pub rows_touched: u64,
+}
+
+/// Entries removed by an orphan sweep.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepReport {
+    pub index_entries: u64,
+    pub links: u64,
+}
+
+impl SweepReport {
+    pub(crate) fn add(&mut self, other: SweepReport) {
+        self.index_entries += other.index_entries;
+        self.links += other.links;
+    }
+}
+
+/// Result of cross-checking an index with the objects of its collection. Only sampled objects
+/// are looked up in the index but the expected number of entries covers all objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexConsistency {
+    pub index: String,
+    pub entries: u64,
+    pub expected_entries: u64,
+    pub sampled_objects: u64,
+    pub missing_entries: Vec<i64>,
+    pub orphaned_entries: Vec<i64>,
+}
+
+impl IndexConsistency {
+    pub fn is_consistent(&self) -> bool {
+        self.entries == self.expected_entries
+            && self.missing_entries.is_empty()
+            && self.orphaned_entries.is_empty()
+    }
+}
+
+/// Result of an import that skips invalid objects.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: u64,
+    pub skipped: u64,
+    pub errors: Vec<ImportError>,
+}
+
+/// Why the object at position `line` of the imported array was skipped.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ImportError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ImportReport {
+    /// Errors that are not caused by the object itself are returned and abort the import.
+    pub(crate) fn add<T>(&mut self, line: usize, result: Result<T>) -> Result<()> {
+        match result {
+            Ok(_) => self.imported += 1,
+            Err(
+                e @ (IsarError::UniqueViolated {}
+                | IsarError::InvalidObjectId {}
+                | IsarError::InvalidObject {}
+                | IsarError::IllegalArg { .. }
+                | IsarError::InvalidJson {}
+                | IsarError::AppendOnly {}
+                | IsarError::ValidationFailed { .. }),
+            ) => {
+                self.skipped += 1;
+                self.errors.push(ImportError {
+                    line,
+                    message: e.to_string(),
+                });
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+}
+
+/// Page usage of a collection. `allocated_bytes` covers the pages of the object db and
+/// `payload_bytes` the ids and objects stored in them.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionFragmentation {
+    pub name: String,
+    pub objects: u64,
+    pub allocated_bytes: u64,
+    pub payload_bytes: u64,
+    pub index_bytes: u64,
+    pub link_bytes: u64,
+}
+
+impl CollectionFragmentation {
+    /// Estimate of the bytes lost to partially filled pages.
+    pub fn unused_bytes(&self) -> u64 {
+        self.allocated_bytes.saturating_sub(self.payload_bytes)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FragmentationReport {
+    pub file_size: u64,
+    pub used_bytes: u64,
+    pub collections: Vec<CollectionFragmentation>,
+}
+
+impl FragmentationReport {
+    /// Bytes of the file that are not used by any collection and could be reclaimed.
+    pub fn free_bytes(&self) -> u64 {
+        self.file_size.saturating_sub(self.used_bytes)
+    }
+
+    pub fn should_compact(&self, condition: &CompactCondition) -> bool {
+        let ratio = if self.used_bytes == 0 {
+            f64::INFINITY
+        } else {
+            (self.file_size as f64) / (self.used_bytes as f64)
+        };
+        self.file_size >= condition.min_file_size
+            && self.free_bytes() >= condition.min_bytes
+            && ratio >= condition.min_ratio
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_compact() {
+        let report = FragmentationReport {
+            file_size: 1000,
+            used_bytes: 400,
+            collections: vec![],
+        };
+        assert_eq!(report.free_bytes(), 600);
+
+        let condition = |min_file_size, min_bytes, min_ratio| CompactCondition {
+            min_file_size,
+            min_bytes,
+            min_ratio,
+            temp_dir: None,
+        };
+        assert!(report.should_compact(&condition(1000, 600, 2.5)));
+        assert!(!report.should_compact(&condition(1001, 0, 0.0)));
+        assert!(!report.should_compact(&condition(0, 601, 0.0)));
+        assert!(!report.should_compact(&condition(0, 0, 2.6)));
+    }
+}
@@ -1,32 +1,94 @@
+use crate::clock::Clock;
 use crate::cursor::IsarCursors;
 use crate::error::{IsarError, Result};
 use crate::mdbx::cursor::UnboundCursor;
 use crate::mdbx::db::Db;
 use crate::mdbx::txn::Txn;
+use crate::query::ResultLimit;
+use crate::stats::{PendingWrite, WriteCounter, WriteStats};
 use crate::watch::change_set::ChangeSet;
-use std::cell::RefCell;
+use crate::watch::NotifyMode;
+use crate::watchdog::TxnLease;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub struct IsarTxn<'env> {
     instance_id: u64,
+    lease: Option<TxnLease>,
     txn: Txn<'env>,
+    started: Instant,
     write: bool,
     change_set: RefCell<Option<ChangeSet<'env>>>,
     unbound_cursors: RefCell<Option<Vec<UnboundCursor>>>,
+    time_budget: Option<Duration>,
+    time_spent: Cell<Duration>,
+    result_limit: Option<ResultLimit>,
+    write_stats: RefCell<Vec<(Arc<WriteCounter>, WriteStats)>>,
+    notify_mode: NotifyMode,
+    clock: Arc<dyn Clock>,
+    _pending_write: Option<PendingWrite>,
+    auto_increments: AutoIncrementRollback,
+}
+
+/// Restores the auto increment counters of all collections unless the transaction commits.
+struct AutoIncrementRollback(Vec<(Arc<AtomicI64>, i64)>);
+
+impl AutoIncrementRollback {
+    fn new(counters: Vec<Arc<AtomicI64>>) -> Self {
+        let saved = counters
+            .into_iter()
+            .map(|counter| {
+                let value = counter.load(Ordering::SeqCst);
+                (counter, value)
+            })
+            .collect();
+        AutoIncrementRollback(saved)
+    }
+
+    fn commit(mut self) {
+        self.0.clear();
+    }
+}
+
+impl Drop for AutoIncrementRollback {
+    fn drop(&mut self) {
+        for (counter, value) in &self.0 {
+            counter.store(*value, Ordering::SeqCst);
+        }
+    }
 }
 
 impl<'env> IsarTxn<'env> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         instance_id: u64,
         txn: Txn<'env>,
         write: bool,
         change_set: Option<ChangeSet<'env>>,
+        result_limit: Option<ResultLimit>,
+        clock: Arc<dyn Clock>,
+        pending_write: Option<PendingWrite>,
+        lease: Option<TxnLease>,
+        auto_increments: Vec<Arc<AtomicI64>>,
     ) -> Result<Self> {
         Ok(IsarTxn {
             instance_id,
+            lease,
             txn,
+            started: Instant::now(),
             write,
             change_set: RefCell::new(change_set),
             unbound_cursors: RefCell::new(Some(vec![])),
+            time_budget: None,
+            time_spent: Cell::new(Duration::ZERO),
+            result_limit,
+            write_stats: RefCell::new(vec![]),
+            notify_mode: NotifyMode::Normal,
+            clock,
+            _pending_write: pending_write,
+            auto_increments: AutoIncrementRollback::new(auto_increments),
         })
     }
 
@@ -34,6 +96,30 @@ impl<'env> IsarTxn<'env> {
         self.unbound_cursors.borrow().is_some()
     }
 
+    pub fn age(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Cumulative time queries of this transaction may take before they fail with
+    /// [IsarError::Timeout].
+    pub fn set_time_budget(&mut self, budget: Option<Duration>) {
+        self.time_budget = budget;
+    }
+
+    /// Changes how subsequent writes of this transaction are reported to watchers. Writes made
+    /// before the mode changed keep their notifications. Has no effect for silent transactions.
+    pub fn set_notify_mode(&mut self, mode: NotifyMode) {
+        self.notify_mode = mode;
+        if let Some(change_set) = self.change_set.get_mut() {
+            change_set.set_collapsed(mode == NotifyMode::Collapsed);
+        }
+    }
+
+    /// Current time of the instance clock in milliseconds since the unix epoch.
+    pub fn now(&self) -> i64 {
+        self.clock.now_millis()
+    }
+
     fn verify_instance_id(&self, instance_id: u64) -> Result<()> {
         if self.instance_id != instance_id {
             Err(IsarError::InstanceMismatch {})
@@ -42,11 +128,19 @@ impl<'env> IsarTxn<'env> {
         }
     }
 
+    fn check_lease(&self) -> Result<()> {
+        if let Some(lease) = &self.lease {
+            lease.check(&self.txn)?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn read<'txn, T, F>(&'txn mut self, instance_id: u64, job: F) -> Result<T>
     where
         F: FnOnce(&IsarCursors<'txn, 'env>) -> Result<T>,
     {
         self.verify_instance_id(instance_id)?;
+        self.check_lease()?;
         if let Some(unbound_cursors) = self.unbound_cursors.take() {
             let cursors = IsarCursors::new(&self.txn, unbound_cursors);
             let result = job(&cursors);
@@ -57,6 +151,34 @@ impl<'env> IsarTxn<'env> {
         }
     }
 
+    pub(crate) fn read_query<'txn, T, F>(&'txn mut self, instance_id: u64, job: F) -> Result<T>
+    where
+        F: FnOnce(&IsarCursors<'txn, 'env>, Option<Instant>, Option<&ResultLimit>) -> Result<T>,
+    {
+        self.verify_instance_id(instance_id)?;
+        self.check_lease()?;
+        let start = Instant::now();
+        let deadline = if let Some(budget) = self.time_budget {
+            let remaining = budget.saturating_sub(self.time_spent.get());
+            if remaining.is_zero() {
+                return Err(IsarError::Timeout {});
+            }
+            Some(start + remaining)
+        } else {
+            None
+        };
+
+        if let Some(unbound_cursors) = self.unbound_cursors.take() {
+            let cursors = IsarCursors::new(&self.txn, unbound_cursors);
+            let result = job(&cursors, deadline, self.result_limit.as_ref());
+            self.unbound_cursors.borrow_mut().replace(cursors.close());
+            self.time_spent.set(self.time_spent.get() + start.elapsed());
+            result
+        } else {
+            Err(IsarError::TransactionClosed {})
+        }
+    }
+
     pub(crate) fn write<'txn, T, F>(&'txn mut self, instance_id: u64, job: F) -> Result<T>
     where
         F: FnOnce(&IsarCursors<'txn, 'env>, Option<&mut ChangeSet<'_>>) -> Result<T>,
@@ -68,7 +190,12 @@ impl<'env> IsarTxn<'env> {
         if let Some(unbound_cursors) = self.unbound_cursors.take() {
             let mut change_set = self.change_set.take();
             let cursors = IsarCursors::new(&self.txn, unbound_cursors);
-            let result = job(&cursors, change_set.as_mut());
+            let job_change_set = if self.notify_mode != NotifyMode::Silent {
+                change_set.as_mut()
+            } else {
+                None
+            };
+            let result = job(&cursors, job_change_set);
             let unbounded_cursors = cursors.close();
             if result.is_ok() {
                 self.unbound_cursors.borrow_mut().replace(unbounded_cursors);
@@ -82,6 +209,19 @@ impl<'env> IsarTxn<'env> {
         }
     }
 
+    /// Stats are only applied to the collection counters once the transaction commits.
+    pub(crate) fn record_write(&self, counter: &Arc<WriteCounter>, stats: WriteStats) {
+        let mut write_stats = self.write_stats.borrow_mut();
+        if let Some((_, pending)) = write_stats
+            .iter_mut()
+            .find(|(c, _)| Arc::ptr_eq(c, counter))
+        {
+            pending.add(stats);
+        } else {
+            write_stats.push((counter.clone(), stats));
+        }
+    }
+
     pub fn commit(self) -> Result<()> {
         if !self.is_active() {
             return Err(IsarError::TransactionClosed {});
@@ -89,6 +229,10 @@ impl<'env> IsarTxn<'env> {
 
         if self.write {
             self.txn.commit()?;
+            self.auto_increments.commit();
+            for (counter, stats) in self.write_stats.take() {
+                counter.add(stats);
+            }
             if let Some(change_set) = self.change_set.take() {
                 change_set.notify_watchers();
             }
@@ -101,6 +245,7 @@ impl<'env> IsarTxn<'env> {
     }
 
     pub(crate) fn db_names(&mut self) -> Result<Vec<String>> {
+        self.check_lease()?;
         let unnamed_db = Db::open(&self.txn, None, false, false, false)?;
         let cursor = UnboundCursor::new();
         let mut cursor = cursor.bind(&self.txn, unnamed_db)?;
@@ -7,6 +7,8 @@ use std::sync::{Arc, MutexGuard};
 pub(crate) struct ChangeSet<'a> {
     watchers: MutexGuard<'a, IsarWatchers>,
     changed_watchers: IntMap<Arc<Watcher>>,
+    collapsed: bool,
+    collapsed_cols: IntMap<()>,
 }
 
 impl<'a> ChangeSet<'a> {
@@ -14,6 +16,8 @@ impl<'a> ChangeSet<'a> {
         ChangeSet {
             watchers,
             changed_watchers: IntMap::new(),
+            collapsed: false,
+            collapsed_cols: IntMap::new(),
         }
     }
 
@@ -28,7 +32,18 @@ impl<'a> ChangeSet<'a> {
         }
     }
 
+    pub fn set_collapsed(&mut self, collapsed: bool) {
+        self.collapsed = collapsed;
+    }
+
     pub fn register_change(&mut self, col_id: u64, id: i64, object: IsarObject) {
+        if self.collapsed {
+            if self.collapsed_cols.insert_checked(col_id, ()) {
+                self.register_all(col_id);
+            }
+            return;
+        }
+
         let cw = self.watchers.get_col_watchers(col_id);
         Self::register_watchers(&mut self.changed_watchers, &cw.watchers);
         if let Some(object_watchers) = cw.object_watchers.get(id as u64) {
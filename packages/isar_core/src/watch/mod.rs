@@ -2,6 +2,17 @@ pub(crate) mod change_set;
 pub(crate) mod isar_watchers;
 pub(crate) mod watcher;
 
+/// Controls how changes made by a write transaction are reported to watchers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NotifyMode {
+    /// Notify every watcher matching a changed object.
+    Normal,
+    /// Notify all watchers of a changed collection once without matching objects.
+    Collapsed,
+    /// Do not register changes at all.
+    Silent,
+}
+
 pub struct WatchHandle {
     stop_callback: Option<Box<dyn FnOnce()>>,
 }
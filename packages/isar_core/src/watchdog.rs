@@ -0,0 +1,149 @@
+use crate::error::{IsarError, Result};
+use crate::mdbx::txn::Txn;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Called with the age of every read transaction the watchdog aborted.
+pub type StaleTxnCallback = Box<dyn Fn(Duration) + Send + Sync + 'static>;
+
+struct LeaseState {
+    started: Instant,
+    expired: AtomicBool,
+}
+
+impl LeaseState {
+    /// Returns `true` if the lease was not expired before.
+    fn expire(&self) -> bool {
+        !self.expired.swap(true, Ordering::SeqCst)
+    }
+}
+
+/// Registration of a read transaction with the watchdog.
+///
+/// The watchdog only marks the lease as expired. Objects read from the transaction borrow its
+/// memory map, so the snapshot is released by the owner the next time it accesses the
+/// transaction, which cannot happen while such borrows are alive.
+pub(crate) struct TxnLease {
+    state: Arc<LeaseState>,
+    released: Cell<bool>,
+}
+
+impl TxnLease {
+    /// Fails and releases the snapshot of `txn` if the watchdog expired the lease.
+    pub fn check(&self, txn: &Txn) -> Result<()> {
+        if !self.state.expired.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        if !self.released.replace(true) {
+            txn.reset()?;
+        }
+        Err(IsarError::TransactionExpired {})
+    }
+}
+
+struct WatchdogConfig {
+    /// `None` once the watchdog thread was asked to stop.
+    max_age: Option<Duration>,
+    callback: Arc<StaleTxnCallback>,
+}
+
+/// Expires read transactions that are held open longer than allowed so forgotten transactions
+/// cannot pin old pages and bloat the database file.
+pub(crate) struct TxnWatchdog {
+    leases: Mutex<Vec<Weak<LeaseState>>>,
+    /// `Some` while the watchdog thread is running.
+    config: Mutex<Option<WatchdogConfig>>,
+}
+
+impl TxnWatchdog {
+    pub fn new() -> Self {
+        TxnWatchdog {
+            leases: Mutex::new(vec![]),
+            config: Mutex::new(None),
+        }
+    }
+
+    pub fn start(self: &Arc<Self>, max_age: Duration, callback: StaleTxnCallback) {
+        let mut config = self.config.lock().unwrap();
+        let running = config.is_some();
+        config.replace(WatchdogConfig {
+            max_age: Some(max_age),
+            callback: Arc::new(callback),
+        });
+        if !running {
+            let watchdog = Arc::downgrade(self);
+            thread::spawn(move || Self::run(watchdog));
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(config) = self.config.lock().unwrap().as_mut() {
+            config.max_age = None;
+        }
+    }
+
+    /// Returns `None` if the watchdog is not running.
+    pub fn lease(&self) -> Option<TxnLease> {
+        let config = self.config.lock().unwrap();
+        config.as_ref()?.max_age?;
+        let state = Arc::new(LeaseState {
+            started: Instant::now(),
+            expired: AtomicBool::new(false),
+        });
+        let mut leases = self.leases.lock().unwrap();
+        leases.retain(|lease| lease.strong_count() > 0);
+        leases.push(Arc::downgrade(&state));
+        Some(TxnLease {
+            state,
+            released: Cell::new(false),
+        })
+    }
+
+    fn run(watchdog: Weak<TxnWatchdog>) {
+        loop {
+            let (max_age, callback) = if let Some(watchdog) = watchdog.upgrade() {
+                let mut config = watchdog.config.lock().unwrap();
+                let current = config.as_ref().unwrap();
+                if let Some(max_age) = current.max_age {
+                    (max_age, current.callback.clone())
+                } else {
+                    config.take();
+                    return;
+                }
+            } else {
+                return;
+            };
+
+            if let Some(watchdog) = watchdog.upgrade() {
+                for age in watchdog.expire_stale(max_age) {
+                    callback(age);
+                }
+            }
+            thread::sleep((max_age / 4).clamp(Duration::from_millis(10), Duration::from_secs(1)));
+        }
+    }
+
+    fn expire_stale(&self, max_age: Duration) -> Vec<Duration> {
+        let leases: Vec<_> = self
+            .leases
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        leases
+            .iter()
+            .filter_map(|lease| {
+                let age = lease.started.elapsed();
+                if age >= max_age && lease.expire() {
+                    Some(age)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+
+use isar_core::instance::IsarInstance;
+use isar_core::object::data_type::DataType;
+use isar_core::schema::collection_schema::CollectionSchema;
+use isar_core::schema::index_schema::{IndexPropertySchema, IndexSchema, IndexType};
+use isar_core::schema::property_schema::PropertySchema;
+use isar_core::schema::Schema;
+use std::sync::Arc;
+
+/// Collection `col` with a single long property `value`.
+pub fn value_collection(indexes: Vec<IndexSchema>) -> CollectionSchema {
+    let properties = vec![PropertySchema::new(
+        Some("value".to_string()),
+        DataType::Long,
+        None,
+    )];
+    CollectionSchema::new("col", false, properties, indexes, vec![])
+}
+
+pub fn value_index(unique: bool) -> IndexSchema {
+    IndexSchema::new(
+        "value",
+        vec![IndexPropertySchema::new("value", IndexType::Value, false)],
+        unique,
+        false,
+    )
+}
+
+pub fn value_schema() -> Schema {
+    Schema::new(vec![value_collection(vec![])]).unwrap()
+}
+
+pub fn open_instance_in(
+    name: &str,
+    dir: &str,
+    schema: Schema,
+    relaxed_durability: bool,
+) -> Arc<IsarInstance> {
+    IsarInstance::open(
+        name,
+        Some(dir),
+        schema,
+        10,
+        0,
+        relaxed_durability,
+        None,
+        None,
+    )
+    .unwrap()
+}
+
+/// Opens the instance in the temp directory.
+pub fn open_instance(name: &str, schema: Schema) -> Arc<IsarInstance> {
+    let dir = std::env::temp_dir();
+    open_instance_in(name, dir.to_str().unwrap(), schema, false)
+}
+
+pub fn open_instance_with(name: &str, col: CollectionSchema) -> Arc<IsarInstance> {
+    open_instance(name, Schema::new(vec![col]).unwrap())
+}
@@ -0,0 +1,844 @@
+mod common;
+
+use common::{open_instance, open_instance_with, value_collection, value_index};
+use isar_core::archive::{attach_archive, detach_to_archive, Archive};
+use isar_core::clock::{Clock, ManualClock, SystemClock};
+use isar_core::collection::IsarCollection;
+use isar_core::error::IsarError;
+use isar_core::instance::IsarInstance;
+use isar_core::object::columns::ColumnData;
+use isar_core::object::data_type::DataType;
+use isar_core::schema::collection_schema::{CollectionSchema, IdReuse};
+use isar_core::schema::constraints::Constraints;
+use isar_core::schema::link_schema::LinkSchema;
+use isar_core::schema::property_schema::PropertySchema;
+use isar_core::schema::Schema;
+use isar_core::stats::WriteStats;
+use isar_core::txn::IsarTxn;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+#[test]
+fn test_append_only() {
+    let col = value_collection(vec![]).with_append_only(true);
+    let isar = open_instance_with("test_append_only", col);
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let ob = col.new_object_builder(None);
+    let object = ob.finish();
+    let id = col.put(&mut txn, None, object).unwrap();
+    col.put(&mut txn, None, object).unwrap();
+
+    assert_eq!(col.delete(&mut txn, id), Err(IsarError::AppendOnly {}));
+    assert_eq!(col.clear(&mut txn), Err(IsarError::AppendOnly {}));
+    assert_eq!(col.count(&mut txn).unwrap(), 2);
+
+    assert_eq!(col.purge(&mut txn, &[id, 100]).unwrap(), 1);
+    assert_eq!(col.count(&mut txn).unwrap(), 1);
+
+    let id = col.max_id(&mut txn).unwrap().unwrap();
+    assert_eq!(
+        col.put(&mut txn, Some(id), object),
+        Err(IsarError::AppendOnly {})
+    );
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_get_json() {
+    let isar = open_instance_with("test_get_json", value_collection(vec![]));
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(col.properties[0].offset, 5);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_null(col.properties[0].offset, DataType::Long);
+    col.put(&mut txn, Some(2), ob.finish()).unwrap();
+
+    let json = col.get_json(&mut txn, 1, Some("id"), true).unwrap();
+    assert_eq!(json, Some(json!({"id": 1, "value": 5})));
+    let json = col.get_json(&mut txn, 2, None, true).unwrap();
+    assert_eq!(json, Some(json!({ "value": null })));
+    assert_eq!(col.get_json(&mut txn, 3, Some("id"), true).unwrap(), None);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_modified_at() {
+    let col = value_collection(vec![]).with_modified_at("missing");
+    assert!(Schema::new(vec![col]).is_err());
+
+    let col = value_collection(vec![]).with_modified_at("value");
+    let isar = open_instance_with("test_modified_at", col);
+    let col = &isar.collections[0];
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(col.properties[0].offset, 5);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    let object = col.get(&mut txn, 1).unwrap().unwrap();
+    assert!(object.read_long(col.properties[0].offset) >= now.as_millis() as i64);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_exists() {
+    let isar = open_instance_with("test_exists", value_collection(vec![]));
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for id in [1, 3] {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, id);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+
+    assert!(col.exists(&mut txn, 1).unwrap());
+    assert!(!col.exists(&mut txn, 2).unwrap());
+    assert_eq!(
+        col.exists_many(&mut txn, &[3, 2, 1, 4]).unwrap(),
+        vec![true, false, true, false]
+    );
+    assert!(col.exists_many(&mut txn, &[]).unwrap().is_empty());
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_id_range() {
+    let isar = open_instance_with("test_id_range", value_collection(vec![]));
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    assert_eq!(col.min_id(&mut txn).unwrap(), None);
+    assert_eq!(col.max_id(&mut txn).unwrap(), None);
+    assert_eq!(col.next_auto_increment(&mut txn), Some(1));
+
+    for id in [5, -3, 10] {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, id);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+    assert_eq!(col.min_id(&mut txn).unwrap(), Some(-3));
+    assert_eq!(col.max_id(&mut txn).unwrap(), Some(10));
+
+    // peeking does not consume the id
+    assert_eq!(col.next_auto_increment(&mut txn), Some(11));
+    assert_eq!(col.next_auto_increment(&mut txn), Some(11));
+    let ob = col.new_object_builder(None);
+    assert_eq!(col.put(&mut txn, None, ob.finish()).unwrap(), 11);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_auto_increment_abort() {
+    let isar = open_instance_with("test_auto_increment_abort", value_collection(vec![]));
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let ob = col.new_object_builder(None);
+    assert_eq!(col.put(&mut txn, None, ob.finish()).unwrap(), 1);
+    txn.commit().unwrap();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for expected in 2..=3 {
+        let ob = col.new_object_builder(None);
+        assert_eq!(col.put(&mut txn, None, ob.finish()).unwrap(), expected);
+    }
+    assert_eq!(col.auto_increment(&mut txn).unwrap(), 4);
+    txn.abort();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    assert_eq!(col.next_auto_increment(&mut txn), Some(2));
+    let ob = col.new_object_builder(None);
+    assert_eq!(col.put(&mut txn, None, ob.finish()).unwrap(), 2);
+    txn.commit().unwrap();
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.next_auto_increment(&mut txn), Some(3));
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_write_stats() {
+    let isar = open_instance_with("test_write_stats", value_collection(vec![]));
+    let col = &isar.collections[0];
+    let put_and_delete = |txn: &mut IsarTxn| {
+        for id in [1, 2] {
+            let mut ob = col.new_object_builder(None);
+            ob.write_long(col.properties[0].offset, id);
+            col.put(txn, Some(id), ob.finish()).unwrap();
+        }
+        col.delete(txn, 1).unwrap();
+    };
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put_and_delete(&mut txn);
+    txn.abort();
+    assert_eq!(col.get_write_stats(), WriteStats::default());
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put_and_delete(&mut txn);
+    txn.commit().unwrap();
+    let stats = col.get_write_stats();
+    assert_eq!(stats.puts, 2);
+    assert_eq!(stats.deletes, 1);
+    assert!(stats.bytes > 0);
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_import_json_partial() {
+    let isar = open_instance_with(
+        "test_import_json_partial",
+        value_collection(vec![value_index(true)]),
+    );
+    let col = &isar.collections[0];
+
+    let json = json!([
+        {"id": 1, "value": 1},
+        {"id": 2, "value": "two"},
+        {"id": 3, "value": 1},
+        {"id": 4, "value": 4},
+    ]);
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let report = col.import_json_partial(&mut txn, Some("id"), json).unwrap();
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.skipped, 2);
+    let lines: Vec<_> = report.errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, vec![1, 2]);
+    assert_eq!(
+        report.errors[1].message,
+        IsarError::UniqueViolated {}.to_string()
+    );
+    assert_eq!(col.count(&mut txn).unwrap(), 2);
+    txn.commit().unwrap();
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_archive() {
+    let isar = open_instance_with("test_archive", value_collection(vec![]));
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for (id, value) in [(1, 10), (2, 20)] {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, value);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let path = std::env::temp_dir().join("test_archive.archive");
+    let path = path.to_str().unwrap();
+    assert_eq!(detach_to_archive(&isar, col, path).unwrap(), 2);
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 0);
+    txn.abort();
+
+    let archive = Archive::open(path).unwrap();
+    assert_eq!(archive.collection, "col");
+    let values: Vec<_> = archive
+        .objects()
+        .map(|o| {
+            let (id, object) = o.unwrap();
+            (id, object.read_long(col.properties[0].offset))
+        })
+        .collect();
+    assert_eq!(values, vec![(1, 10), (2, 20)]);
+
+    assert_eq!(attach_archive(&isar, col, &archive).unwrap(), 2);
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 2);
+    txn.abort();
+
+    std::fs::remove_file(path).unwrap();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_archive_links() {
+    let updated = PropertySchema::new(Some("updated".to_string()), DataType::Long, None);
+    let col = CollectionSchema::new(
+        "col",
+        false,
+        vec![updated],
+        vec![],
+        vec![LinkSchema::new("next", "col")],
+    )
+    .with_modified_at("updated");
+    let other = CollectionSchema::new(
+        "other",
+        false,
+        vec![],
+        vec![],
+        vec![LinkSchema::new("items", "col")],
+    );
+    let schema = Schema::new(vec![col, other]).unwrap();
+    let dir = std::env::temp_dir();
+    let isar = open_instance("test_archive_links", schema);
+    let col = isar.collections.iter().find(|c| c.name == "col").unwrap();
+    let other = isar.collections.iter().find(|c| c.name == "other").unwrap();
+    let next_id = xxh3_64_with_seed(b"next", xxh3_64_with_seed(b"col", 0));
+    let items_id = xxh3_64_with_seed(b"items", xxh3_64_with_seed(b"other", 0));
+    let offset = col.properties[0].offset;
+
+    let clock = Arc::new(ManualClock::new(1000));
+    isar.set_clock(clock.clone());
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for id in 1..=3 {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(offset, 0);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+    let ob = other.new_object_builder(None);
+    other.put(&mut txn, Some(10), ob.finish()).unwrap();
+    col.link_many(&mut txn, next_id, &[(1, 2), (2, 3)], false)
+        .unwrap();
+    other
+        .link_many(&mut txn, items_id, &[(10, 1), (10, 3)], false)
+        .unwrap();
+    txn.commit().unwrap();
+
+    let linked = |txn: &mut IsarTxn, source: &IsarCollection, link_id: u64, id: i64| {
+        let mut qb = col.new_query_builder();
+        qb.add_link_where_clause(source, link_id, id).unwrap();
+        let mut ids = vec![];
+        qb.build()
+            .find_while(txn, |id, _| {
+                ids.push(id);
+                true
+            })
+            .unwrap();
+        ids
+    };
+
+    let path = dir.join("test_archive_links.archive");
+    let path = path.to_str().unwrap();
+    assert_eq!(detach_to_archive(&isar, col, path).unwrap(), 3);
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert!(linked(&mut txn, other, items_id, 10).is_empty());
+    txn.abort();
+
+    clock.set(5000);
+    let archive = Archive::open(path).unwrap();
+    assert_eq!(attach_archive(&isar, col, &archive).unwrap(), 3);
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(
+        col.get(&mut txn, 2).unwrap().unwrap().read_long(offset),
+        1000
+    );
+    assert_eq!(linked(&mut txn, col, next_id, 1), vec![2]);
+    assert_eq!(linked(&mut txn, col, next_id, 2), vec![3]);
+    assert_eq!(linked(&mut txn, other, items_id, 10), vec![1, 3]);
+    txn.abort();
+
+    std::fs::remove_file(path).unwrap();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_archive_failures() {
+    let isar = open_instance_with("test_archive_failures", value_collection(vec![]));
+    let other_isar = open_instance_with("test_archive_failures_other", value_collection(vec![]));
+    let col = &isar.collections[0];
+    let other_col = &other_isar.collections[0];
+    fn is_illegal_arg<T>(result: Result<T, IsarError>) -> bool {
+        matches!(result, Err(IsarError::IllegalArg { .. }))
+    }
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(col.properties[0].offset, 10);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    txn.commit().unwrap();
+
+    let dir = std::env::temp_dir();
+    let missing = dir.join("test_archive_failures_missing/col.archive");
+    let result = detach_to_archive(&isar, col, missing.to_str().unwrap());
+    assert!(matches!(result, Err(IsarError::PathError {})));
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 1);
+    txn.abort();
+
+    let path = dir.join("test_archive_failures.archive");
+    let path = path.to_str().unwrap();
+    assert!(is_illegal_arg(detach_to_archive(&isar, other_col, path)));
+    assert!(!std::path::Path::new(path).exists());
+    assert_eq!(detach_to_archive(&isar, col, path).unwrap(), 1);
+
+    let archive = Archive::open(path).unwrap();
+    assert!(is_illegal_arg(attach_archive(&isar, other_col, &archive)));
+    let mut txn = other_isar.begin_txn(false, false).unwrap();
+    assert_eq!(other_col.count(&mut txn).unwrap(), 0);
+    txn.abort();
+
+    let bytes = std::fs::read(path).unwrap();
+    let corrupt = dir.join("test_archive_failures_corrupt.archive");
+    let corrupt = corrupt.to_str().unwrap();
+    for bytes in [&bytes[..bytes.len() - 1], &[&bytes[..], &[0]].concat()] {
+        std::fs::write(corrupt, bytes).unwrap();
+        assert!(matches!(
+            Archive::open(corrupt),
+            Err(IsarError::InvalidObject {})
+        ));
+    }
+    std::fs::write(corrupt, b"not an archive").unwrap();
+    assert!(is_illegal_arg(Archive::open(corrupt)));
+    std::fs::remove_file(corrupt).unwrap();
+    let result = Archive::open(missing.to_str().unwrap());
+    assert!(matches!(result, Err(IsarError::PathError {})));
+
+    std::fs::remove_file(path).unwrap();
+    isar.close_and_delete();
+    other_isar.close_and_delete();
+}
+
+#[test]
+fn test_link_many() {
+    let links = vec![LinkSchema::new("links", "col")];
+    let col = CollectionSchema::new("col", false, vec![], vec![], links);
+    let isar = open_instance_with("test_link_many", col);
+    let col = &isar.collections[0];
+    let link_id = xxh3_64_with_seed(b"links", xxh3_64_with_seed(b"col", 0));
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for id in 1..=3 {
+        let ob = col.new_object_builder(None);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+    let count_links = |txn: &mut IsarTxn, id| {
+        let mut qb = col.new_query_builder();
+        qb.add_link_where_clause(col, link_id, id).unwrap();
+        qb.build().count(txn).unwrap()
+    };
+
+    let links = [(1, 2), (1, 3), (2, 3), (2, 99)];
+    assert_eq!(col.link_many(&mut txn, link_id, &links, false).unwrap(), 3);
+    assert_eq!(count_links(&mut txn, 1), 2);
+    assert_eq!(count_links(&mut txn, 2), 1);
+
+    assert_eq!(
+        col.link_many(&mut txn, link_id, &[(1, 1)], true).unwrap(),
+        1
+    );
+    assert_eq!(count_links(&mut txn, 1), 1);
+
+    col.unlink_all_many(&mut txn, link_id, &[1, 2]).unwrap();
+    assert_eq!(count_links(&mut txn, 1), 0);
+    assert_eq!(count_links(&mut txn, 2), 0);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_check_index_consistency() {
+    let col = value_collection(vec![value_index(false)]);
+    let isar = open_instance_with("test_check_index_consistency", col);
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for value in 0..5 {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, value % 2);
+        col.put(&mut txn, None, ob.finish()).unwrap();
+    }
+
+    let report = col.check_index_consistency(&mut txn, 1).unwrap();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].index, "value");
+    assert_eq!(report[0].entries, 5);
+    assert_eq!(report[0].expected_entries, 5);
+    assert_eq!(report[0].sampled_objects, 5);
+    assert!(report[0].is_consistent());
+
+    let report = col.check_index_consistency(&mut txn, 2).unwrap();
+    assert_eq!(report[0].sampled_objects, 3);
+    assert!(report[0].is_consistent());
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+fn blob_collection() -> CollectionSchema {
+    let properties = vec![
+        PropertySchema::new(Some("blob".to_string()), DataType::ByteList, None).with_blob(true),
+    ];
+    CollectionSchema::new("col", false, properties, vec![], vec![])
+}
+
+#[test]
+fn test_blob_put_get() {
+    let isar = open_instance_with("test_blob_put_get", blob_collection());
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let key1 = isar.blobs.put(&mut txn, b"hello").unwrap();
+    let key2 = isar.blobs.put(&mut txn, b"world").unwrap();
+    assert_ne!(key1, key2);
+    assert_eq!(isar.blobs.put(&mut txn, b"hello").unwrap(), key1);
+
+    assert_eq!(isar.blobs.get(&mut txn, key1).unwrap(), Some(&b"hello"[..]));
+    assert_eq!(isar.blobs.get(&mut txn, key2).unwrap(), Some(&b"world"[..]));
+    assert_eq!(isar.blobs.get(&mut txn, key1 ^ key2).unwrap(), None);
+    assert_eq!(isar.blobs.ref_count(&mut txn, key1).unwrap(), 0);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_blob_refs() {
+    let isar = open_instance_with("test_blob_refs", blob_collection());
+    let col = &isar.collections[0];
+    let offset = col.properties[0].offset;
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let key1 = isar.blobs.put(&mut txn, b"hello").unwrap();
+    let key2 = isar.blobs.put(&mut txn, b"world").unwrap();
+    let put_ref = |txn: &mut IsarTxn, id: i64, key: u128| {
+        let mut ob = col.new_object_builder(None);
+        ob.write_byte_list(offset, Some(&key.to_be_bytes()));
+        col.put(txn, Some(id), ob.finish())
+    };
+
+    put_ref(&mut txn, 1, key1).unwrap();
+    put_ref(&mut txn, 2, key1).unwrap();
+    assert_eq!(isar.blobs.ref_count(&mut txn, key1).unwrap(), 2);
+
+    put_ref(&mut txn, 2, key2).unwrap();
+    assert_eq!(isar.blobs.ref_count(&mut txn, key1).unwrap(), 1);
+    assert_eq!(isar.blobs.ref_count(&mut txn, key2).unwrap(), 1);
+
+    col.delete(&mut txn, 1).unwrap();
+    assert_eq!(isar.blobs.ref_count(&mut txn, key1).unwrap(), 0);
+    assert_eq!(isar.blobs.collect_garbage(&mut txn).unwrap(), 1);
+    assert_eq!(isar.blobs.get(&mut txn, key1).unwrap(), None);
+    assert_eq!(isar.blobs.get(&mut txn, key2).unwrap(), Some(&b"world"[..]));
+
+    col.clear(&mut txn).unwrap();
+    assert_eq!(isar.blobs.ref_count(&mut txn, key2).unwrap(), 0);
+    txn.commit().unwrap();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    assert!(matches!(
+        put_ref(&mut txn, 3, key1),
+        Err(IsarError::IllegalArg { .. })
+    ));
+    txn.abort();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_byte_list(offset, Some(b"short"));
+    assert!(matches!(
+        col.put(&mut txn, Some(3), ob.finish()),
+        Err(IsarError::IllegalArg { .. })
+    ));
+    txn.abort();
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_id_reuse() {
+    fn put_auto(isar: &IsarInstance) -> i64 {
+        let col = &isar.collections[0];
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let ob = col.new_object_builder(None);
+        let id = col.put(&mut txn, None, ob.finish()).unwrap();
+        txn.commit().unwrap();
+        id
+    }
+
+    fn delete(isar: &IsarInstance, id: i64) {
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        isar.collections[0].delete(&mut txn, id).unwrap();
+        txn.commit().unwrap();
+    }
+
+    fn clear(isar: &IsarInstance) {
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        isar.collections[0].clear(&mut txn).unwrap();
+        txn.commit().unwrap();
+    }
+
+    for id_reuse in [IdReuse::Allow, IdReuse::Never] {
+        let name = format!("test_id_reuse_{:?}", id_reuse);
+        let col = value_collection(vec![]).with_id_reuse(id_reuse);
+        let isar = open_instance_with(&name, col.clone());
+        for id in 1..=3 {
+            assert_eq!(put_auto(&isar), id);
+        }
+        delete(&isar, 3);
+        assert_eq!(put_auto(&isar), 4);
+        delete(&isar, 4);
+        isar.close();
+
+        let isar = open_instance_with(&name, col.clone());
+        let expected = if id_reuse == IdReuse::Allow { 3 } else { 5 };
+        assert_eq!(put_auto(&isar), expected);
+
+        clear(&isar);
+        let expected = if id_reuse == IdReuse::Allow { 1 } else { 6 };
+        assert_eq!(put_auto(&isar), expected);
+        isar.close_and_delete();
+    }
+}
+
+#[test]
+fn test_id_reuse_embedded() {
+    let col =
+        CollectionSchema::new("emb", true, vec![], vec![], vec![]).with_id_reuse(IdReuse::Never);
+    assert!(matches!(
+        Schema::new(vec![col]),
+        Err(IsarError::SchemaError { .. })
+    ));
+}
+
+#[test]
+fn test_embedded_constraints() {
+    let constraints = Constraints {
+        max: Some(10.0),
+        ..Default::default()
+    };
+    let embedded_property = PropertySchema::new(Some("value".to_string()), DataType::Long, None)
+        .with_constraints(constraints);
+    let embedded = CollectionSchema::new("emb", true, vec![embedded_property], vec![], vec![]);
+    let property = PropertySchema::new(
+        Some("emb".to_string()),
+        DataType::Object,
+        Some("emb".to_string()),
+    );
+    let col = CollectionSchema::new("col", false, vec![property], vec![], vec![]);
+    assert!(matches!(
+        Schema::new(vec![col.clone(), embedded]),
+        Err(IsarError::SchemaError { .. })
+    ));
+
+    let embedded_property = PropertySchema::new(Some("value".to_string()), DataType::Long, None);
+    let embedded = CollectionSchema::new("emb", true, vec![embedded_property], vec![], vec![]);
+    assert!(Schema::new(vec![col, embedded]).is_ok());
+}
+
+#[test]
+fn test_put_columns() {
+    let properties = vec![
+        PropertySchema::new(Some("flag".to_string()), DataType::Bool, None),
+        PropertySchema::new(Some("name".to_string()), DataType::String, None),
+        PropertySchema::new(Some("value".to_string()), DataType::Long, None),
+    ];
+    let col = CollectionSchema::new("col", false, properties, vec![], vec![]);
+    let isar = open_instance_with("test_put_columns", col);
+    let col = &isar.collections[0];
+    let flag = &col.properties[0];
+    let name = &col.properties[1];
+    let value = &col.properties[2];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let names = [Some("a"), None, Some("c")];
+    let columns = vec![
+        (value, ColumnData::Long(&[1, 2, 3])),
+        (name, ColumnData::String(&names)),
+    ];
+    assert_eq!(
+        col.put_columns(&mut txn, None, columns).unwrap(),
+        vec![1, 2, 3]
+    );
+
+    let object = col.get(&mut txn, 3).unwrap().unwrap();
+    assert_eq!(object.read_long(value.offset), 3);
+    assert_eq!(object.read_string(name.offset), Some("c"));
+    assert!(object.is_null(flag.offset, flag.data_type));
+    let object = col.get(&mut txn, 2).unwrap().unwrap();
+    assert!(object.is_null(name.offset, name.data_type));
+
+    let columns = vec![(flag, ColumnData::Bool(&[Some(true), Some(false)]))];
+    assert_eq!(
+        col.put_columns(&mut txn, Some(&[10, 1]), columns).unwrap(),
+        vec![10, 1]
+    );
+    let object = col.get(&mut txn, 1).unwrap().unwrap();
+    assert_eq!(object.read_bool(flag.offset), Some(false));
+    assert!(object.is_null(value.offset, value.data_type));
+    assert_eq!(col.count(&mut txn).unwrap(), 4);
+
+    let invalid = [
+        (Some(&[1][..]), vec![(value, ColumnData::Long(&[1, 2]))]),
+        (
+            None,
+            vec![
+                (value, ColumnData::Long(&[1])),
+                (flag, ColumnData::Bool(&[None, None])),
+            ],
+        ),
+        (
+            None,
+            vec![
+                (value, ColumnData::Long(&[1])),
+                (value, ColumnData::Long(&[2])),
+            ],
+        ),
+    ];
+    for (ids, columns) in invalid {
+        assert!(matches!(
+            col.put_columns(&mut txn, ids, columns),
+            Err(IsarError::IllegalArg { .. })
+        ));
+    }
+    assert!(matches!(
+        col.put_columns(&mut txn, None, vec![(value, ColumnData::Int(&[1]))]),
+        Err(IsarError::PropertyMismatch { .. })
+    ));
+    assert_eq!(col.count(&mut txn).unwrap(), 4);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_export_json_snapshot() {
+    let other = CollectionSchema::new("other", false, vec![], vec![], vec![]);
+    let schema = Schema::new(vec![value_collection(vec![]), other]).unwrap();
+    let isar = open_instance("test_export_json_snapshot", schema);
+    let col = isar.collections.iter().find(|c| c.name == "col").unwrap();
+
+    let put_value = |id: i64, value: i64| {
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, value);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+        txn.commit().unwrap();
+    };
+    put_value(1, 5);
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    std::thread::scope(|s| {
+        s.spawn(|| put_value(2, 6)).join().unwrap();
+    });
+    let json = isar.export_json(&mut txn, Some("id"), false).unwrap();
+    assert_eq!(json, json!({"col": [{"id": 1, "value": 5}], "other": []}));
+    txn.abort();
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    let json = isar.export_json(&mut txn, None, false).unwrap();
+    assert_eq!(
+        json,
+        json!({"col": [{"value": 5}, {"value": 6}], "other": []})
+    );
+    txn.abort();
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_clock() {
+    let col = value_collection(vec![]).with_modified_at("value");
+    let isar = open_instance_with("test_clock", col);
+    let col = &isar.collections[0];
+    let offset = col.properties[0].offset;
+    let put_and_read = |txn: &mut IsarTxn| {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(offset, 0);
+        col.put(txn, Some(1), ob.finish()).unwrap();
+        col.get(txn, 1).unwrap().unwrap().read_long(offset)
+    };
+
+    let clock = Arc::new(ManualClock::new(1000));
+    let old_txn = isar.begin_txn(false, false).unwrap();
+    isar.set_clock(clock.clone());
+    assert_ne!(old_txn.now(), 1000);
+    old_txn.abort();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    assert_eq!(put_and_read(&mut txn), 1000);
+    clock.advance(500);
+    assert_eq!(txn.now(), 1500);
+    assert_eq!(put_and_read(&mut txn), 1500);
+    clock.set(42);
+    assert_eq!(put_and_read(&mut txn), 42);
+    txn.commit().unwrap();
+
+    let skewed = SystemClock::new(-60_000);
+    let now = SystemClock::default().now_millis();
+    assert!(skewed.now_millis() <= now - 60_000);
+    skewed.set_offset(60_000);
+    assert!(skewed.now_millis() >= now + 60_000);
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_copy_object_embedded() {
+    let long = |name: &str| PropertySchema::new(Some(name.to_string()), DataType::Long, None);
+    let string = |name: &str| PropertySchema::new(Some(name.to_string()), DataType::String, None);
+    let embedded = |name: &str, data_type: DataType, target: &str| {
+        PropertySchema::new(Some(name.to_string()), data_type, Some(target.to_string()))
+    };
+    let source_emb = CollectionSchema::new("SourceEmb", true, vec![long("x")], vec![], vec![]);
+    let target_emb = CollectionSchema::new(
+        "TargetEmb",
+        true,
+        vec![string("name"), long("x")],
+        vec![],
+        vec![],
+    );
+    let properties = vec![
+        embedded("emb", DataType::Object, "SourceEmb"),
+        embedded("list", DataType::ObjectList, "SourceEmb"),
+    ];
+    let source = CollectionSchema::new("source", false, properties, vec![], vec![]);
+    let properties = vec![
+        embedded("emb", DataType::Object, "TargetEmb"),
+        embedded("list", DataType::ObjectList, "TargetEmb"),
+    ];
+    let target = CollectionSchema::new("target", false, properties, vec![], vec![]);
+    let schema = Schema::new(vec![source, target, source_emb, target_emb]).unwrap();
+    let isar = open_instance("test_copy_object_embedded", schema);
+    let source = isar
+        .collections
+        .iter()
+        .find(|c| c.name == "source")
+        .unwrap();
+    let target = isar
+        .collections
+        .iter()
+        .find(|c| c.name == "target")
+        .unwrap();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let json = json!([{"id": 1, "emb": {"x": 5}, "list": [{"x": 6}, null, {"x": 7}]}]);
+    source.import_json(&mut txn, Some("id"), json).unwrap();
+    assert_eq!(
+        source.copy_object(&mut txn, 1, target, Some(2)).unwrap(),
+        Some(2)
+    );
+    assert_eq!(source.copy_object(&mut txn, 3, target, None).unwrap(), None);
+
+    let copy = target.get_json(&mut txn, 2, Some("id"), true).unwrap();
+    let expected = json!({
+        "id": 2,
+        "emb": {"name": null, "x": 5},
+        "list": [{"name": null, "x": 6}, null, {"name": null, "x": 7}],
+    });
+    assert_eq!(copy, Some(expected));
+    txn.abort();
+    isar.close_and_delete();
+}
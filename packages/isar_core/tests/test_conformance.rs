@@ -0,0 +1,11 @@
+#![cfg(feature = "conformance")]
+
+mod common;
+
+use common::open_instance;
+use isar_core::conformance::run_conformance_suite;
+
+#[test]
+fn test_native_conformance() {
+    run_conformance_suite(&|name, schema| open_instance(name, schema));
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "fault-injection")]
+
+mod common;
+
+use common::{open_instance_in, value_schema};
+use isar_core::error::IsarError;
+use isar_core::fault::{assert_invariants, inject, reopen, Fault};
+use isar_core::instance::{IsarInstance, RetryPolicy};
+use isar_core::txn::IsarTxn;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn open(name: &str, dir: &str) -> Arc<IsarInstance> {
+    open_instance_in(name, dir, value_schema(), true)
+}
+
+fn put(isar: &IsarInstance, id: i64, fault: Option<Fault>) -> Result<(), IsarError> {
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false)?;
+    if let Some(fault) = fault {
+        inject(fault, 0);
+    }
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(col.properties[0].offset, id);
+    col.put(&mut txn, Some(id), ob.finish())?;
+    txn.commit()
+}
+
+#[test]
+fn test_crash_at_commit() {
+    let dir = std::env::temp_dir();
+    let isar = open("test_crash_at_commit", dir.to_str().unwrap());
+    put(&isar, 1, None).unwrap();
+    assert!(put(&isar, 2, Some(Fault::CrashAtCommit)).is_err());
+
+    let isar = reopen(isar, open);
+    assert_invariants(&isar);
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert!(isar.collections[0].get(&mut txn, 1).unwrap().is_some());
+    assert!(isar.collections[0].get(&mut txn, 2).unwrap().is_none());
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_write_faults() {
+    let dir = std::env::temp_dir();
+    let isar = open("test_write_faults", dir.to_str().unwrap());
+    assert!(matches!(
+        put(&isar, 1, Some(Fault::DiskFull)),
+        Err(IsarError::DbFull {})
+    ));
+    assert!(matches!(
+        put(&isar, 1, Some(Fault::IoError)),
+        Err(IsarError::MdbxError { .. })
+    ));
+    put(&isar, 1, None).unwrap();
+
+    let isar = reopen(isar, open);
+    assert_invariants(&isar);
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(isar.collections[0].count(&mut txn).unwrap(), 1);
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_retry_policy() {
+    let dir = std::env::temp_dir();
+    let isar = open("test_retry_policy", dir.to_str().unwrap());
+    let col = &isar.collections[0];
+    let put = |txn: &mut IsarTxn, id: i64| {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, id);
+        col.put(txn, Some(id), ob.finish())
+    };
+    let count = || {
+        let mut txn = isar.begin_txn(false, false).unwrap();
+        let count = col.count(&mut txn).unwrap();
+        txn.abort();
+        count
+    };
+
+    let mut runs = 0;
+    let result = isar.run_txn(true, false, |txn| {
+        runs += 1;
+        inject(Fault::Busy, 0);
+        put(txn, 1)
+    });
+    assert!(matches!(result, Err(IsarError::MdbxError { .. })));
+    assert_eq!((runs, isar.get_retry_count(), count()), (1, 0, 0));
+
+    isar.set_retry_policy(Some(RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(4),
+    }));
+    let mut runs = 0;
+    let result = isar.run_txn(true, false, |txn| {
+        runs += 1;
+        put(txn, 1)?;
+        if runs == 1 {
+            inject(Fault::Busy, 0);
+        }
+        put(txn, 2)?;
+        Ok(runs)
+    });
+    assert_eq!(result.unwrap(), 2);
+    assert_eq!((isar.get_retry_count(), count()), (1, 2));
+
+    // The backoff is capped instead of overflowing.
+    isar.set_retry_policy(Some(RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::MAX,
+        max_backoff: Duration::from_millis(1),
+    }));
+    let mut runs = 0;
+    let result = isar.run_txn(true, false, |txn| {
+        runs += 1;
+        inject(Fault::Busy, 0);
+        put(txn, 3)
+    });
+    assert!(matches!(result, Err(IsarError::MdbxError { .. })));
+    assert_eq!((runs, isar.get_retry_count(), count()), (3, 3, 2));
+
+    isar.close_and_delete();
+}
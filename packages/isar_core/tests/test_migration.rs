@@ -0,0 +1,65 @@
+mod common;
+
+use common::{value_collection, value_index};
+use isar_core::instance::{IsarInstance, MigrationListener};
+use isar_core::schema::Schema;
+use isar_core::stats::{MigrationOperation, MigrationRecord};
+use std::sync::{Arc, Mutex};
+
+fn schema(indexed: bool) -> Schema {
+    let indexes = if indexed {
+        vec![value_index(false)]
+    } else {
+        vec![]
+    };
+    Schema::new(vec![value_collection(indexes)]).unwrap()
+}
+
+#[test]
+fn test_migration_listener() {
+    let name = "test_migration_listener";
+    let records: Arc<Mutex<Vec<MigrationRecord>>> = Arc::new(Mutex::new(vec![]));
+
+    let listener = || -> MigrationListener {
+        let records = records.clone();
+        Box::new(move |record| records.lock().unwrap().push(record.clone()))
+    };
+    let open = |schema| {
+        let dir = std::env::temp_dir();
+        IsarInstance::open(
+            name,
+            dir.to_str(),
+            schema,
+            10,
+            0,
+            false,
+            None,
+            Some(listener()),
+        )
+        .unwrap()
+    };
+
+    let isar = open(schema(false));
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(col.properties[0].offset, 5);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    txn.commit().unwrap();
+    isar.close();
+    assert!(records.lock().unwrap().is_empty());
+
+    let isar = open(schema(true));
+
+    let records = records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(record.collection, "col");
+    assert_ne!(record.old_hash, record.new_hash);
+    assert_eq!(
+        record.operations,
+        vec![MigrationOperation::CreateIndex("value".to_string())]
+    );
+    assert_eq!(record.rows_touched, 1);
+    isar.close_and_delete();
+}
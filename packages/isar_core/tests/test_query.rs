@@ -0,0 +1,717 @@
+mod common;
+
+use common::{open_instance, open_instance_with, value_schema};
+use isar_core::batch::delete_chunked;
+use isar_core::collection::IsarCollection;
+use isar_core::error::IsarError;
+use isar_core::index::index_key::IndexKey;
+use isar_core::instance::IsarInstance;
+use isar_core::object::data_type::DataType;
+use isar_core::query::filter::Filter;
+use isar_core::query::query_ast::{FilterAst, QueryAst};
+use isar_core::query::query_parser::parse_query;
+use isar_core::query::sort_expression::{ArithmeticOp, SortExpression};
+use isar_core::query::{Query, ResultLimit, ResultLimitAction, Sort};
+use isar_core::schema::collection_schema::CollectionSchema;
+use isar_core::schema::index_schema::{IndexPropertySchema, IndexSchema, IndexType};
+use isar_core::schema::property_schema::PropertySchema;
+use isar_core::txn::IsarTxn;
+use std::sync::Arc;
+use std::time::Duration;
+use xxhash_rust::xxh3::xxh3_64;
+
+fn put_values(col: &IsarCollection, txn: &mut IsarTxn, values: &[(i64, i64)]) {
+    for (id, value) in values {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, *value);
+        col.put(txn, Some(*id), ob.finish()).unwrap();
+    }
+}
+
+fn ids(query: &Query, txn: &mut IsarTxn) -> Vec<i64> {
+    let mut ids = vec![];
+    query
+        .find_while(txn, |id, _| {
+            ids.push(id);
+            true
+        })
+        .unwrap();
+    ids
+}
+
+#[test]
+fn test_seek_and_skip() {
+    let isar = open_instance("test_seek_and_skip", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put_values(
+        col,
+        &mut txn,
+        &[(1, 30), (2, 10), (3, 50), (4, 20), (5, 40)],
+    );
+
+    let query = col.new_query_builder().build();
+    assert_eq!(ids(&query.with_seek(2), &mut txn), vec![3, 4, 5]);
+    assert_eq!(ids(&query.with_seek(i64::MAX), &mut txn), Vec::<i64>::new());
+    assert_eq!(ids(&query.with_skip(3), &mut txn), vec![4, 5]);
+    assert_eq!(query.with_seek(2).count(&mut txn).unwrap(), 3);
+
+    let mut qb = col.new_query_builder();
+    qb.add_id_where_clause(5, 1).unwrap();
+    qb.set_limit(3);
+    let query = qb.build();
+    assert_eq!(ids(&query.with_seek(4), &mut txn), vec![3, 2, 1]);
+    assert_eq!(ids(&query.with_skip(1), &mut txn), vec![4, 3]);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort(&col.properties[0], Sort::Ascending).unwrap();
+    let query = qb.build();
+    assert_eq!(ids(&query, &mut txn), vec![2, 4, 1, 5, 3]);
+    assert_eq!(ids(&query.with_seek(1), &mut txn), vec![5, 3]);
+    assert_eq!(ids(&query.with_seek(1).with_skip(1), &mut txn), vec![3]);
+    assert_eq!(query.with_seek(1).count(&mut txn).unwrap(), 2);
+    assert_eq!(ids(&query.with_seek(6), &mut txn), Vec::<i64>::new());
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_pop() {
+    let isar = open_instance("test_pop", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put_values(col, &mut txn, &[(1, 30), (2, 10), (3, 20)]);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort(&col.properties[0], Sort::Ascending).unwrap();
+    let query = qb.build();
+
+    let chunks = query.pop(&mut txn, col, 2, 1024).unwrap();
+    assert_eq!(chunks.len(), 1);
+    let (chunk, rows) = &chunks[0];
+    assert_eq!(*rows, 2);
+    assert_eq!(i64::from_le_bytes(chunk[..8].try_into().unwrap()), 2);
+    assert_eq!(ids(&query, &mut txn), vec![1]);
+
+    assert_eq!(query.pop(&mut txn, col, 5, 1024).unwrap().len(), 1);
+    assert!(query.pop(&mut txn, col, 5, 1024).unwrap().is_empty());
+    assert_eq!(col.count(&mut txn).unwrap(), 0);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_ast_round_trip() {
+    let isar = open_instance("test_ast_round_trip", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put_values(col, &mut txn, &[(1, 30), (2, 10), (3, 50), (4, 20)]);
+
+    let value = &col.properties[0];
+    let mut qb = col.new_query_builder();
+    qb.add_id_where_clause(1, 3).unwrap();
+    qb.set_filter(Filter::not(Filter::long(value, 40, 100).unwrap()));
+    qb.add_sort(value, Sort::Descending).unwrap();
+    qb.set_limit(5);
+    let query = qb.build();
+
+    let ast = query.to_ast(col, &isar.collections).unwrap();
+    assert_eq!(
+        ast.filter,
+        Some(FilterAst::Not {
+            filter: Box::new(FilterAst::LongBetween {
+                property: "value".to_string(),
+                lower: 40,
+                upper: 100,
+            })
+        })
+    );
+
+    let json = serde_json::to_string(&ast).unwrap();
+    let parsed: QueryAst = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, ast);
+    let rebuilt = parsed.build(col, &isar.collections).unwrap();
+    assert_eq!(ids(&rebuilt, &mut txn), vec![1, 2]);
+    assert_eq!(ids(&query, &mut txn), ids(&rebuilt, &mut txn));
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_delete_chunked() {
+    let isar = open_instance("test_delete_chunked", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let values = (1..=10).map(|id| (id, id * 10)).collect::<Vec<_>>();
+    put_values(col, &mut txn, &values);
+    txn.commit().unwrap();
+
+    let mut qb = col.new_query_builder();
+    qb.set_filter(Filter::long(&col.properties[0], 30, i64::MAX).unwrap());
+    qb.set_limit(7);
+    let query = qb.build();
+
+    let mut progress = vec![];
+    let deleted = delete_chunked(&isar, col, &query, 3, |n| progress.push(n)).unwrap();
+    assert_eq!(deleted, 7);
+    assert_eq!(progress, vec![3, 6, 7]);
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    let all = col.new_query_builder().build();
+    assert_eq!(ids(&all, &mut txn), vec![1, 2, 10]);
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_fold_diacritics_index() {
+    let properties = vec![PropertySchema::new(
+        Some("name".to_string()),
+        DataType::String,
+        None,
+    )];
+    let index_property =
+        IndexPropertySchema::new("name", IndexType::Value, false).with_fold_diacritics(true);
+    let index = IndexSchema::new("name", vec![index_property], false, false);
+    let col = CollectionSchema::new("col", false, properties, vec![index], vec![]);
+    let isar = open_instance_with("test_fold_index", col);
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for (id, name) in [(1, "Crème"), (2, "creme"), (3, "Cream")] {
+        let mut ob = col.new_object_builder(None);
+        ob.write_string(col.properties[0].offset, Some(name));
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+
+    let mut key = IndexKey::new();
+    key.add_folded_string(Some("CRÈME"), false);
+    let mut qb = col.new_query_builder();
+    let index_id = xxh3_64("name".as_bytes());
+    qb.add_index_where_clause(index_id, key.clone(), key, Sort::Ascending, false)
+        .unwrap();
+    assert_eq!(ids(&qb.build(), &mut txn), vec![1, 2]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_fold_diacritics_hash_index() {
+    let properties = vec![PropertySchema::new(
+        Some("name".to_string()),
+        DataType::String,
+        None,
+    )];
+    let index_property =
+        IndexPropertySchema::new("name", IndexType::Hash, false).with_fold_diacritics(true);
+    let index = IndexSchema::new("name", vec![index_property], false, false);
+    let col = CollectionSchema::new("col", false, properties, vec![index], vec![]);
+    let isar = open_instance_with("test_fold_hash_index", col);
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for (id, name) in [(1, "Crème"), (2, "creme"), (3, "Cream")] {
+        let mut ob = col.new_object_builder(None);
+        ob.write_string(col.properties[0].offset, Some(name));
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+
+    let mut key = IndexKey::new();
+    key.add_folded_string_hash(Some("CRÈME"), false);
+    let mut qb = col.new_query_builder();
+    let index_id = xxh3_64("name".as_bytes());
+    qb.add_index_where_clause(index_id, key.clone(), key, Sort::Ascending, false)
+        .unwrap();
+    assert_eq!(ids(&qb.build(), &mut txn), vec![1, 2]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_profile() {
+    let isar = open_instance("test_profile", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put_values(col, &mut txn, &[(1, 30), (2, 10), (3, 20)]);
+
+    let profile = col.new_query_builder().build().profile(&mut txn).unwrap();
+    assert_eq!(profile.rows, 3);
+    assert_eq!(profile.sort_buffer_bytes, 0);
+    assert!(profile.result_bytes > 3 * 8);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort(&col.properties[0], Sort::Ascending).unwrap();
+    let sorted = qb.build().profile(&mut txn).unwrap();
+    assert!(sorted.explain.sorted_in_memory);
+    assert!(sorted.sort_buffer_bytes > 0);
+    assert_eq!(sorted.result_bytes, profile.result_bytes);
+    assert_eq!(
+        sorted.memory_bytes(),
+        sorted.sort_buffer_bytes + sorted.result_bytes
+    );
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_index_advice() {
+    let isar = open_instance("test_index_advice", value_schema());
+    let col = &isar.collections[0];
+    let value = &col.properties[0];
+
+    col.new_query_builder().build();
+    assert!(col.get_index_advice().is_empty());
+
+    let mut qb = col.new_query_builder();
+    qb.set_filter(Filter::long(value, 1, 5).unwrap());
+    qb.add_sort(value, Sort::Ascending).unwrap();
+    let query = qb.build();
+    assert!(col.get_index_advice().is_empty());
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    for _ in 0..2 {
+        query.find_all_vec(&mut txn).unwrap();
+    }
+    txn.abort();
+    let advice = col.get_index_advice();
+    assert_eq!(advice.len(), 1);
+    assert_eq!(advice[0].property, "value");
+    assert_eq!(advice[0].hits, 2);
+
+    col.reset_index_advice();
+    assert!(col.get_index_advice().is_empty());
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_plan_signature() {
+    let isar = open_instance("test_plan_signature", value_schema());
+    let col = &isar.collections[0];
+    let value = &col.properties[0];
+
+    let query = col.new_query_builder().build();
+    assert_eq!(query.plan_signature(), "Id,indexOnly");
+
+    let mut qb = col.new_query_builder();
+    qb.add_id_where_clause(5, 10).unwrap();
+    qb.set_filter(Filter::long(value, 1, 5).unwrap());
+    let query = qb.build();
+    assert_eq!(query.plan_signature(), "Id,filter");
+    assert_eq!(query.check_plan("Id,filter"), Ok(()));
+    assert_eq!(
+        query.check_plan("Id,indexOnly"),
+        Err(IsarError::PlanChanged {
+            expected: "Id,indexOnly".to_string(),
+            actual: "Id,filter".to_string(),
+        })
+    );
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_time_budget() {
+    let isar = open_instance("test_time_budget", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let values: Vec<_> = (0..100).map(|i| (i, i * 37 % 100)).collect();
+    put_values(col, &mut txn, &values);
+    txn.commit().unwrap();
+
+    // too few objects to reach a deadline check while scanning
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    txn.set_time_budget(Some(Duration::from_nanos(1)));
+    let query = col.new_query_builder().build();
+    assert_eq!(ids(&query, &mut txn).len(), 100);
+    assert_eq!(query.count(&mut txn), Err(IsarError::Timeout {}));
+    txn.abort();
+
+    // but enough comparisons to check it while sorting
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    txn.set_time_budget(Some(Duration::from_nanos(1)));
+    let mut qb = col.new_query_builder();
+    qb.add_sort(&col.properties[0], Sort::Ascending).unwrap();
+    let result = qb.build().find_while(&mut txn, |_, _| true);
+    assert_eq!(result, Err(IsarError::Timeout {}));
+    txn.abort();
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_result_limit() {
+    let isar = open_instance("test_result_limit", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put_values(col, &mut txn, &[(1, 1), (2, 2), (3, 3)]);
+    txn.commit().unwrap();
+
+    isar.set_result_limit(Some(ResultLimit {
+        max_rows: Some(2),
+        max_bytes: None,
+        action: ResultLimitAction::Error,
+    }));
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    let query = col.new_query_builder().build();
+    assert!(matches!(
+        query.find_all_vec(&mut txn),
+        Err(IsarError::ResultLimitExceeded { rows: 3, .. })
+    ));
+    assert_eq!(query.count(&mut txn).unwrap(), 3);
+
+    let mut qb = col.new_query_builder();
+    qb.set_limit(2);
+    assert_eq!(ids(&qb.build(), &mut txn), vec![1, 2]);
+
+    let json = query.export_json(&mut txn, col, Some("id"), true).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 3);
+    let json = isar.export_json(&mut txn, Some("id"), true).unwrap();
+    assert_eq!(json["col"].as_array().unwrap().len(), 3);
+    txn.abort();
+
+    isar.set_result_limit(None);
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_find_first_last() {
+    let isar = open_instance("test_find_first_last", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+
+    let query = col.new_query_builder().build();
+    assert!(query.find_first(&mut txn).unwrap().is_none());
+    assert!(query.find_last(&mut txn).unwrap().is_none());
+
+    put_values(col, &mut txn, &[(1, 30), (2, 10), (3, 50), (4, 20)]);
+    let first_last = |query: &Query, txn: &mut IsarTxn| {
+        let first = query.find_first(txn).unwrap().unwrap().0;
+        let last = query.find_last(txn).unwrap().unwrap().0;
+        (first, last)
+    };
+    assert_eq!(first_last(&query, &mut txn), (1, 4));
+
+    // sorted without limit reverses the sort
+    let mut qb = col.new_query_builder();
+    qb.add_sort(&col.properties[0], Sort::Ascending).unwrap();
+    assert_eq!(first_last(&qb.build(), &mut txn), (2, 3));
+
+    // with a limit the last result of the scan is kept
+    let mut qb = col.new_query_builder();
+    qb.add_sort(&col.properties[0], Sort::Ascending).unwrap();
+    qb.set_limit(3);
+    assert_eq!(first_last(&qb.build(), &mut txn), (2, 1));
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_index_only_ids() {
+    let isar = open_instance("test_index_only_ids", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    put_values(
+        col,
+        &mut txn,
+        &[(1, 10), (2, 20), (3, 30), (4, 40), (5, 50)],
+    );
+
+    let find_ids = |query: &Query, txn: &mut IsarTxn| {
+        let mut ids = vec![];
+        query
+            .find_ids_while(txn, |id| {
+                ids.push(id);
+                true
+            })
+            .unwrap();
+        ids
+    };
+
+    let mut qb = col.new_query_builder();
+    qb.set_offset(1);
+    qb.set_limit(3);
+    let query = qb.build();
+    assert!(query.explain().index_only);
+    assert_eq!(find_ids(&query, &mut txn), vec![2, 3, 4]);
+    assert_eq!(query.count(&mut txn).unwrap(), 3);
+
+    let mut qb = col.new_query_builder();
+    qb.set_filter(Filter::long(&col.properties[0], 20, 40).unwrap());
+    let query = qb.build();
+    let explain = query.explain();
+    assert!(!explain.index_only);
+    assert!(explain.filtered);
+    assert_eq!(find_ids(&query, &mut txn), vec![2, 3, 4]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_sort_with_case() {
+    let properties = vec![
+        PropertySchema::new(Some("name".to_string()), DataType::String, None),
+        PropertySchema::new(Some("code".to_string()), DataType::String, None),
+    ];
+    let col = CollectionSchema::new("col", false, properties, vec![], vec![]);
+    let isar = open_instance_with("test_sort_with_case", col);
+    let col = &isar.collections[0];
+    let code = &col.properties[0];
+    let name = &col.properties[1];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for (id, n, c) in [(1, "b", "X"), (2, "B", "a"), (3, "a", "Y"), (4, "A", "x")] {
+        let mut ob = col.new_object_builder(None);
+        ob.write_string(code.offset, Some(c));
+        ob.write_string(name.offset, Some(n));
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort(name, Sort::Ascending).unwrap();
+    assert_eq!(ids(&qb.build(), &mut txn), vec![4, 2, 3, 1]);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort_with_case(name, Sort::Ascending, false).unwrap();
+    qb.add_sort_with_case(code, Sort::Ascending, true).unwrap();
+    assert_eq!(ids(&qb.build(), &mut txn), vec![3, 4, 1, 2]);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort_with_case(name, Sort::Ascending, false).unwrap();
+    qb.add_sort_with_case(code, Sort::Ascending, false).unwrap();
+    assert_eq!(ids(&qb.build(), &mut txn), vec![4, 3, 2, 1]);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort_with_case(name, Sort::Descending, false)
+        .unwrap();
+    qb.add_sort_with_case(code, Sort::Ascending, true).unwrap();
+    assert_eq!(ids(&qb.build(), &mut txn), vec![1, 2, 3, 4]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_sort_expression_nulls() {
+    let properties = vec![
+        PropertySchema::new(Some("a".to_string()), DataType::Long, None),
+        PropertySchema::new(Some("b".to_string()), DataType::Long, None),
+    ];
+    let col = CollectionSchema::new("col", false, properties, vec![], vec![]);
+    let isar = open_instance_with("test_sort_expression_nulls", col);
+    let col = &isar.collections[0];
+    let (a, b) = (&col.properties[0], &col.properties[1]);
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for (id, value) in [(1, Some(3)), (2, None), (3, Some(-1)), (4, Some(i64::MAX))] {
+        let mut ob = col.new_object_builder(None);
+        if let Some(value) = value {
+            ob.write_long(a.offset, value);
+        } else {
+            ob.write_null(a.offset, DataType::Long);
+        }
+        ob.write_long(b.offset, 2);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+
+    let sum = || {
+        let a = SortExpression::property(a).unwrap();
+        let b = SortExpression::property(b).unwrap();
+        SortExpression::arithmetic(ArithmeticOp::Add, a, b).unwrap()
+    };
+    let mut qb = col.new_query_builder();
+    qb.add_sort_expression(sum(), Sort::Ascending, true);
+    assert_eq!(ids(&qb.build(), &mut txn), vec![2, 3, 1, 4]);
+
+    let mut qb = col.new_query_builder();
+    qb.add_sort_expression(sum(), Sort::Descending, true);
+    assert_eq!(ids(&qb.build(), &mut txn), vec![4, 1, 3, 2]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+fn open_people(name: &str) -> Arc<IsarInstance> {
+    let properties = vec![
+        PropertySchema::new(Some("age".to_string()), DataType::Long, None),
+        PropertySchema::new(Some("name".to_string()), DataType::String, None),
+        PropertySchema::new(Some("score".to_string()), DataType::Double, None),
+        PropertySchema::new(Some("vip".to_string()), DataType::Bool, None),
+    ];
+    let col = CollectionSchema::new("col", false, properties, vec![], vec![]);
+    let isar = open_instance_with(name, col);
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let people = [
+        (1, 17, "Ann \"A\"", 0.00001, true),
+        (2, 30, "Bob", 2e10, false),
+        (3, 45, "Cid", -1.5e3, true),
+        (4, 18, "Dan", 0.5, false),
+    ];
+    for (id, age, name, score, vip) in people {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, age);
+        ob.write_string(col.properties[1].offset, Some(name));
+        ob.write_double(col.properties[2].offset, score);
+        ob.write_bool(col.properties[3].offset, Some(vip));
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+    txn.commit().unwrap();
+    isar
+}
+
+fn parse_ids(isar: &IsarInstance, txn: &mut IsarTxn, input: &str) -> Vec<i64> {
+    let query = parse_query(&isar.collections[0], input).unwrap();
+    ids(&query, txn)
+}
+
+fn parse_error(isar: &IsarInstance, input: &str) -> String {
+    match parse_query(&isar.collections[0], input) {
+        Err(IsarError::IllegalArg { message }) => message,
+        _ => panic!("Expected an error for {}", input),
+    }
+}
+
+#[test]
+fn test_parse_query_literals() {
+    let isar = open_people("test_parse_query_literals");
+    let mut txn = isar.begin_txn(false, false).unwrap();
+
+    assert_eq!(parse_ids(&isar, &mut txn, "age >= 18"), vec![2, 3, 4]);
+    assert_eq!(parse_ids(&isar, &mut txn, "age < -1"), Vec::<i64>::new());
+    assert_eq!(
+        parse_ids(&isar, &mut txn, r#"name == "Ann \"A\"""#),
+        vec![1]
+    );
+    assert_eq!(parse_ids(&isar, &mut txn, "score == 1e-5"), vec![1]);
+    assert_eq!(parse_ids(&isar, &mut txn, "score >= 2E10"), vec![2]);
+    assert_eq!(
+        parse_ids(&isar, &mut txn, "score < -1.5e+3"),
+        Vec::<i64>::new()
+    );
+    assert_eq!(parse_ids(&isar, &mut txn, "score <= -1.5e+3"), vec![3]);
+    assert_eq!(parse_ids(&isar, &mut txn, "vip == TRUE"), vec![1, 3]);
+    assert_eq!(parse_ids(&isar, &mut txn, "vip != true"), vec![2, 4]);
+    assert_eq!(parse_ids(&isar, &mut txn, "id > 2"), vec![3, 4]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_parse_query_precedence() {
+    let isar = open_people("test_parse_query_precedence");
+    let mut txn = isar.begin_txn(false, false).unwrap();
+
+    let and_first = "vip == true or age > 20 and age < 40";
+    assert_eq!(parse_ids(&isar, &mut txn, and_first), vec![1, 2, 3]);
+    let grouped = "(vip == true or age > 20) and age < 40";
+    assert_eq!(parse_ids(&isar, &mut txn, grouped), vec![1, 2]);
+    let not = "not vip == true and age > 20";
+    assert_eq!(parse_ids(&isar, &mut txn, not), vec![2]);
+    let sorted = "age > 17 sort by vip desc, age asc offset 1 limit 1";
+    assert_eq!(parse_ids(&isar, &mut txn, sorted), vec![4]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_parse_query_keyword_properties() {
+    let properties = vec![
+        PropertySchema::new(Some("and".to_string()), DataType::Long, None),
+        PropertySchema::new(Some("not".to_string()), DataType::Long, None),
+    ];
+    let col = CollectionSchema::new("col", false, properties, vec![], vec![]);
+    let isar = open_instance_with("test_parse_query_keyword_properties", col);
+    let col = &isar.collections[0];
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for (id, value) in [(1, 1), (2, 2)] {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, value);
+        ob.write_long(col.properties[1].offset, value * 10);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+
+    assert_eq!(parse_ids(&isar, &mut txn, "`and` == 1"), vec![1]);
+    let both = "`not` > 10 and `and` >= 2 sort by `and` desc";
+    assert_eq!(parse_ids(&isar, &mut txn, both), vec![2]);
+    assert_eq!(parse_ids(&isar, &mut txn, "not `not` == 10"), vec![2]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_parse_query_string_null() {
+    let isar = open_people("test_parse_query_string_null");
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_string(col.properties[1].offset, None);
+    col.put(&mut txn, Some(5), ob.finish()).unwrap();
+
+    assert_eq!(parse_ids(&isar, &mut txn, r#"name <= "Bob""#), vec![1, 2]);
+    assert_eq!(parse_ids(&isar, &mut txn, r#"name < "Bob""#), vec![1]);
+    assert_eq!(
+        parse_ids(&isar, &mut txn, r#"name >= "Bob""#),
+        vec![2, 3, 4]
+    );
+    assert_eq!(parse_ids(&isar, &mut txn, "name is null"), vec![5]);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_parse_query_errors() {
+    let isar = open_people("test_parse_query_errors");
+
+    assert_eq!(
+        parse_error(&isar, "agex > 5"),
+        "Unknown property 'agex' at 0."
+    );
+    assert_eq!(
+        parse_error(&isar, "age ~ 5"),
+        "Unexpected character '~' at 4."
+    );
+    assert_eq!(
+        parse_error(&isar, "age >= \"x\""),
+        "Expected an integer at 7."
+    );
+    assert_eq!(parse_error(&isar, "(age > 5"), "Expected ')' at 8.");
+    assert_eq!(
+        parse_error(&isar, "age near 5"),
+        "Unknown operator 'near' at 4."
+    );
+    assert_eq!(
+        parse_error(&isar, "vip > true"),
+        "Bool properties only support == and != at 4."
+    );
+    assert_eq!(
+        parse_error(&isar, r#"name == "abc"#),
+        "Unterminated string at 8."
+    );
+    assert_eq!(
+        parse_error(&isar, "age > 5 sort name"),
+        "Expected 'by' at 13."
+    );
+    assert_eq!(
+        parse_error(&isar, "`age > 5"),
+        "Unterminated identifier at 0."
+    );
+    assert_eq!(
+        parse_error(&isar, "age > 5 limit 1 x"),
+        "Unexpected token Ident(\"x\") at 16."
+    );
+
+    isar.close_and_delete();
+}
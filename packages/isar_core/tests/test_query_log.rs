@@ -0,0 +1,40 @@
+#![cfg(feature = "query-log")]
+
+mod common;
+
+use common::{open_instance, value_schema};
+use isar_core::query::filter::Filter;
+use isar_core::query::query_ast::FilterAst;
+use isar_core::query::query_log::{set_query_log_sink, QueryLogEntry};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_query_log() {
+    let isar = open_instance("test_query_log", value_schema());
+    let col = &isar.collections[0];
+
+    let entries: Arc<Mutex<Vec<QueryLogEntry>>> = Arc::new(Mutex::new(vec![]));
+    let sink_entries = entries.clone();
+    set_query_log_sink(Some(Box::new(move |entry| {
+        sink_entries.lock().unwrap().push(entry.clone());
+    })));
+
+    let mut qb = col.new_query_builder();
+    qb.set_filter(Filter::long(&col.properties[0], 1, 5).unwrap());
+    qb.build();
+    set_query_log_sink(None);
+
+    let entries = entries.lock().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].collection, "col");
+    assert!(entries[0].plan.filtered);
+    assert_eq!(
+        entries[0].filter,
+        Some(FilterAst::LongBetween {
+            property: "value".to_string(),
+            lower: 1,
+            upper: 5,
+        })
+    );
+    isar.close_and_delete();
+}
@@ -0,0 +1,618 @@
+mod common;
+
+use common::{
+    open_instance, open_instance_in, open_instance_with, value_collection, value_index,
+    value_schema,
+};
+use isar_core::batch::{AutoFlush, WriteBatch};
+use isar_core::collection::IsarCollection;
+use isar_core::error::{IsarError, Result};
+use isar_core::instance::{CompactCondition, IsarInstance, MaintenanceReport};
+use isar_core::object::data_type::DataType;
+use isar_core::object::property::Property;
+use isar_core::query::filter::Filter;
+use isar_core::schema::collection_schema::CollectionSchema;
+use isar_core::schema::property_schema::PropertySchema;
+use isar_core::schema::Schema;
+use isar_core::txn::IsarTxn;
+use isar_core::watch::NotifyMode;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn count_value(col: &IsarCollection, txn: &mut IsarTxn, property: &Property, value: i64) -> u32 {
+    let mut qb = col.new_query_builder();
+    qb.set_filter(Filter::long(property, value, value).unwrap());
+    qb.build().count(txn).unwrap()
+}
+
+#[test]
+fn test_read_your_writes() {
+    let isar = open_instance("test_read_your_writes", value_schema());
+    let col = &isar.collections[0];
+    let value = col.properties[0].clone();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(value.offset, 5);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    assert!(col.get(&mut txn, 1).unwrap().is_some());
+    assert_eq!(count_value(col, &mut txn, &value, 5), 1);
+
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(value.offset, 6);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    assert_eq!(count_value(col, &mut txn, &value, 5), 0);
+    assert_eq!(count_value(col, &mut txn, &value, 6), 1);
+
+    col.delete(&mut txn, 1).unwrap();
+    assert!(col.get(&mut txn, 1).unwrap().is_none());
+    assert_eq!(count_value(col, &mut txn, &value, 6), 0);
+    assert_eq!(col.count(&mut txn).unwrap(), 0);
+
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_watchdog_aborts_stale_read_txn() {
+    let isar = open_instance("test_watchdog_aborts_stale_read_txn", value_schema());
+    let col = &isar.collections[0];
+    let expired = Arc::new(AtomicU32::new(0));
+
+    let counter = expired.clone();
+    isar.start_txn_watchdog(
+        Duration::from_millis(50),
+        Box::new(move |age| {
+            assert!(age >= Duration::from_millis(50));
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 0);
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(txn.age() >= Duration::from_millis(200));
+    let result = col.count(&mut txn);
+    assert!(matches!(result, Err(IsarError::TransactionExpired {})));
+    txn.abort();
+    assert_eq!(expired.load(Ordering::SeqCst), 1);
+
+    isar.stop_txn_watchdog();
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(col.count(&mut txn).unwrap(), 0);
+    txn.abort();
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_watchdog_keeps_borrowed_objects() {
+    let isar = open_instance("test_watchdog_keeps_borrowed_objects", value_schema());
+    let col = &isar.collections[0];
+    let value = col.properties[0].clone();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(value.offset, 7);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    txn.commit().unwrap();
+
+    isar.start_txn_watchdog(Duration::from_millis(50), Box::new(|_| {}));
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    let object = col.get(&mut txn, 1).unwrap().unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut write_txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(value.offset, 8);
+    col.put(&mut write_txn, Some(1), ob.finish()).unwrap();
+    write_txn.commit().unwrap();
+
+    assert_eq!(object.read_long(value.offset), 7);
+    let result = col.count(&mut txn);
+    assert!(matches!(result, Err(IsarError::TransactionExpired {})));
+    assert!(matches!(
+        col.get(&mut txn, 1),
+        Err(IsarError::TransactionExpired {})
+    ));
+    txn.abort();
+
+    isar.stop_txn_watchdog();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_open_copy_of() {
+    let isar = open_instance("test_open_copy_of", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let ob = col.new_object_builder(None);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    txn.commit().unwrap();
+
+    let path = std::env::temp_dir().join("test_open_copy_of.isar");
+    let copy = IsarInstance::open_copy_of(path.to_str().unwrap(), value_schema(), 10).unwrap();
+    let copy_col = &copy.collections[0];
+    let mut txn = copy.begin_txn(true, false).unwrap();
+    assert_eq!(copy_col.count(&mut txn).unwrap(), 1);
+    copy_col.delete(&mut txn, 1).unwrap();
+    txn.commit().unwrap();
+
+    let copy_path = Path::new(&copy.dir).join(format!("{}.isar", copy.name));
+    assert!(copy_path.exists());
+    assert!(copy.close());
+    assert!(!copy_path.exists());
+    assert!(!copy_path.with_extension("isar.lock").exists());
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 1);
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_read_only() {
+    let isar = open_instance("test_read_only", value_schema());
+    isar.set_read_only(true);
+    assert!(matches!(
+        isar.begin_txn(true, false),
+        Err(IsarError::ReadOnly {})
+    ));
+    isar.begin_txn(false, false).unwrap().abort();
+
+    isar.set_read_only(false);
+    isar.begin_txn(true, false).unwrap().abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_max_readers() {
+    let dir = std::env::temp_dir();
+    let isar = IsarInstance::open(
+        "test_max_readers",
+        dir.to_str(),
+        value_schema(),
+        10,
+        4,
+        false,
+        None,
+        None,
+    )
+    .unwrap();
+    let col = &isar.collections[0];
+
+    let mut txns: Vec<IsarTxn> = (0..3)
+        .map(|_| isar.begin_txn(false, false).unwrap())
+        .collect();
+    for txn in &mut txns {
+        assert_eq!(col.count(txn).unwrap(), 0);
+    }
+    assert_eq!(isar.check_readers().unwrap(), 0);
+    for txn in txns {
+        txn.abort();
+    }
+    isar.close_and_delete();
+}
+
+fn put_value(batch: &mut WriteBatch, col: &IsarCollection, id: i64, value: i64) -> Result<()> {
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(col.properties[0].offset, value);
+    batch.put(col, Some(id), ob.finish())
+}
+
+#[test]
+fn test_write_batch_auto_flush() {
+    let isar = open_instance("test_write_batch_auto_flush", value_schema());
+    let col = &isar.collections[0];
+    let auto_flush = AutoFlush {
+        max_ops: Some(2),
+        ..Default::default()
+    };
+    let mut batch = WriteBatch::new(isar.clone(), auto_flush);
+
+    put_value(&mut batch, col, 1, 1).unwrap();
+    assert_eq!(batch.len(), 1);
+    put_value(&mut batch, col, 2, 2).unwrap();
+    assert!(batch.is_empty());
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 2);
+    txn.abort();
+
+    drop(batch);
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_write_batch_flush() {
+    let isar = open_instance("test_write_batch_flush", value_schema());
+    let col = &isar.collections[0];
+
+    let mut batch = WriteBatch::new(isar.clone(), AutoFlush::default());
+    put_value(&mut batch, col, 1, 1).unwrap();
+    batch.delete(col, 1).unwrap();
+    put_value(&mut batch, col, 2, 2).unwrap();
+    assert_eq!(batch.flush().unwrap(), 3);
+    drop(batch);
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert!(col.get(&mut txn, 1).unwrap().is_none());
+    assert!(col.get(&mut txn, 2).unwrap().is_some());
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_write_batch_discard() {
+    let isar = open_instance("test_write_batch_discard", value_schema());
+    let col = &isar.collections[0];
+
+    let mut batch = WriteBatch::new(isar.clone(), AutoFlush::default());
+    put_value(&mut batch, col, 1, 1).unwrap();
+    put_value(&mut batch, col, 2, 2).unwrap();
+    assert_eq!(batch.discard(), 2);
+    assert!(batch.is_empty());
+    assert_eq!(batch.flush().unwrap(), 0);
+    drop(batch);
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 0);
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_write_batch_drops_ops_on_permanent_failure() {
+    let isar = open_instance_with(
+        "test_write_batch_drops_ops_on_permanent_failure",
+        value_collection(vec![value_index(true)]),
+    );
+    let col = &isar.collections[0];
+    let mut batch = WriteBatch::new(isar.clone(), AutoFlush::default());
+
+    put_value(&mut batch, col, 1, 1).unwrap();
+    put_value(&mut batch, col, 2, 1).unwrap();
+    assert!(matches!(batch.flush(), Err(IsarError::UniqueViolated {})));
+    assert!(batch.is_empty());
+
+    put_value(&mut batch, col, 3, 3).unwrap();
+    assert_eq!(batch.flush().unwrap(), 1);
+    drop(batch);
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 1);
+    txn.abort();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_write_batch_keeps_ops_on_failure() {
+    let isar = open_instance("test_write_batch_keeps_ops_on_failure", value_schema());
+    let col = &isar.collections[0];
+    let auto_flush = AutoFlush {
+        max_ops: Some(1),
+        ..Default::default()
+    };
+    let mut batch = WriteBatch::new(isar.clone(), auto_flush);
+
+    isar.set_read_only(true);
+    assert!(matches!(
+        put_value(&mut batch, col, 1, 1),
+        Err(IsarError::ReadOnly {})
+    ));
+    assert_eq!(batch.len(), 1);
+
+    isar.set_read_only(false);
+    assert_eq!(batch.flush().unwrap(), 1);
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert!(col.get(&mut txn, 1).unwrap().is_some());
+    txn.abort();
+
+    drop(batch);
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_instance_conflict() {
+    let isar = open_instance("test_instance_conflict", value_schema());
+    let dir = std::env::temp_dir().join("test_instance_conflict_dir");
+    std::fs::create_dir_all(&dir).unwrap();
+    let result = IsarInstance::open(
+        "test_instance_conflict",
+        dir.to_str(),
+        value_schema(),
+        10,
+        0,
+        false,
+        None,
+        None,
+    );
+    assert!(matches!(result, Err(IsarError::InstanceConflict { .. })));
+
+    #[cfg(unix)]
+    {
+        let link = dir.join("test_instance_conflict_link.isar");
+        let target = std::env::temp_dir().join("test_instance_conflict.isar");
+        std::os::unix::fs::symlink(target, &link).unwrap();
+        let result = IsarInstance::open(
+            "test_instance_conflict_link",
+            dir.to_str(),
+            value_schema(),
+            10,
+            0,
+            false,
+            None,
+            None,
+        );
+        assert!(matches!(result, Err(IsarError::InstanceConflict { .. })));
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_schema_mismatch() {
+    let isar = open_instance("test_schema_mismatch", value_schema());
+    let same = open_instance("test_schema_mismatch", value_schema());
+    assert!(Arc::ptr_eq(&isar, &same));
+
+    let properties = vec![PropertySchema::new(
+        Some("other".to_string()),
+        DataType::Long,
+        None,
+    )];
+    let col = CollectionSchema::new("col", false, properties, vec![], vec![]);
+    let col2 = CollectionSchema::new("col2", false, vec![], vec![], vec![]);
+    let schema = Schema::new(vec![col2, col]).unwrap();
+    let dir = std::env::temp_dir();
+    let result = IsarInstance::open(
+        "test_schema_mismatch",
+        dir.to_str(),
+        schema,
+        10,
+        0,
+        false,
+        None,
+        None,
+    );
+    if let Err(IsarError::SchemaMismatch {
+        existing_hash,
+        new_hash,
+        collections,
+    }) = result
+    {
+        assert_ne!(existing_hash, new_hash);
+        assert_eq!(collections, vec!["col", "col2"]);
+    } else {
+        panic!("expected a schema mismatch");
+    }
+
+    drop(same);
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_perform_maintenance() {
+    let isar = open_instance("test_perform_maintenance", value_schema());
+
+    let report = isar.perform_maintenance(Duration::ZERO).unwrap();
+    assert_eq!(report, MaintenanceReport::default());
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let blob = isar.blobs.put(&mut txn, b"unreferenced").unwrap();
+    txn.commit().unwrap();
+
+    let report = isar.perform_maintenance(Duration::from_secs(10)).unwrap();
+    assert_eq!(report.stale_readers, 0);
+    assert_eq!(report.collected_blobs, 1);
+    assert!(report.synced);
+    assert!(report.completed);
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert!(isar.blobs.get(&mut txn, blob).unwrap().is_none());
+    txn.abort();
+
+    isar.set_read_only(true);
+    let report = isar.perform_maintenance(Duration::from_secs(10)).unwrap();
+    assert!(!report.completed);
+    isar.set_read_only(false);
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_notify_mode() {
+    let isar = open_instance("test_notify_mode", value_schema());
+    let col = &isar.collections[0];
+
+    let col_notified = Arc::new(AtomicU32::new(0));
+    let object_notified = Arc::new(AtomicU32::new(0));
+    let counter = col_notified.clone();
+    let _col_handle = isar.watch_collection(
+        col,
+        Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+    let counter = object_notified.clone();
+    let _object_handle = isar.watch_object(
+        col,
+        99,
+        Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+
+    let put_many = |mode: NotifyMode| {
+        let mut txn = isar.begin_txn(true, false).unwrap();
+        txn.set_notify_mode(mode);
+        for id in 1..10 {
+            let ob = col.new_object_builder(None);
+            col.put(&mut txn, Some(id), ob.finish()).unwrap();
+        }
+        txn.commit().unwrap();
+        (
+            col_notified.swap(0, Ordering::SeqCst),
+            object_notified.swap(0, Ordering::SeqCst),
+        )
+    };
+
+    assert_eq!(put_many(NotifyMode::Normal), (1, 0));
+    assert_eq!(put_many(NotifyMode::Collapsed), (1, 1));
+    assert_eq!(put_many(NotifyMode::Silent), (0, 0));
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_write_pressure() {
+    let isar = open_instance("test_write_pressure", value_schema());
+    let col = &isar.collections[0];
+
+    let pressure = isar.get_write_pressure().unwrap();
+    assert_eq!(pressure.pending_write_txns, 0);
+    assert!(pressure.used_bytes <= pressure.max_bytes);
+
+    let read_txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(isar.get_write_pressure().unwrap().pending_write_txns, 0);
+    read_txn.abort();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    assert_eq!(isar.get_write_pressure().unwrap().pending_write_txns, 1);
+    for id in 0..1000 {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, id);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+    txn.commit().unwrap();
+
+    let after = isar.get_write_pressure().unwrap();
+    assert_eq!(after.pending_write_txns, 0);
+    assert!(after.used_bytes > pressure.used_bytes);
+    assert_eq!(after.max_bytes, pressure.max_bytes);
+
+    let txn = isar.begin_txn(true, false).unwrap();
+    txn.abort();
+    assert_eq!(isar.get_write_pressure().unwrap().pending_write_txns, 0);
+
+    isar.close_and_delete();
+}
+
+#[test]
+fn test_flush() {
+    let dir = std::env::temp_dir();
+    let open = || open_instance_in("test_flush", dir.to_str().unwrap(), value_schema(), true);
+    let isar = open();
+    let col = &isar.collections[0];
+    isar.flush().unwrap();
+
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(col.properties[0].offset, 5);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    txn.commit().unwrap();
+    isar.flush().unwrap();
+
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(count_value(col, &mut txn, &col.properties[0], 5), 1);
+    txn.abort();
+    assert!(isar.close());
+
+    let isar = open();
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(count_value(col, &mut txn, &col.properties[0], 5), 1);
+    txn.abort();
+    isar.close_and_delete();
+}
+
+fn compact_condition(temp_dir: Option<&Path>) -> CompactCondition {
+    CompactCondition {
+        min_file_size: 0,
+        min_bytes: 0,
+        min_ratio: 0.0,
+        temp_dir: temp_dir.map(|dir| dir.to_str().unwrap().to_string()),
+    }
+}
+
+fn open_compacted(name: &str, dir: &Path, temp_dir: Option<&Path>) -> Result<Arc<IsarInstance>> {
+    let condition = compact_condition(temp_dir);
+    IsarInstance::open(
+        name,
+        dir.to_str(),
+        value_schema(),
+        10,
+        0,
+        false,
+        Some(condition),
+        None,
+    )
+}
+
+#[test]
+fn test_compact_temp_dir() {
+    let dir = std::env::temp_dir();
+    let temp_dir = dir.join("test_compact_temp_dir_tmp");
+    std::fs::create_dir_all(&temp_dir).unwrap();
+
+    let isar = open_instance("test_compact_temp_dir", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    for id in 0..100 {
+        let mut ob = col.new_object_builder(None);
+        ob.write_long(col.properties[0].offset, id);
+        col.put(&mut txn, Some(id), ob.finish()).unwrap();
+    }
+    txn.commit().unwrap();
+    assert!(isar.close());
+
+    // A leftover of an interrupted compaction does not prevent compaction.
+    let stale = dir.join("test_compact_temp_dir.isar.compact");
+    std::fs::write(&stale, b"stale").unwrap();
+
+    let isar = open_compacted("test_compact_temp_dir", &dir, Some(&temp_dir)).unwrap();
+    assert!(!stale.exists());
+    assert!(!temp_dir.join("test_compact_temp_dir.isar.compact").exists());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(col.count(&mut txn).unwrap(), 100);
+    assert_eq!(count_value(col, &mut txn, &col.properties[0], 42), 1);
+    txn.abort();
+
+    isar.close_and_delete();
+    std::fs::remove_dir_all(&temp_dir).unwrap();
+}
+
+#[test]
+fn test_compact_failure_cleanup() {
+    let dir = std::env::temp_dir();
+    let missing_dir = dir.join("test_compact_failure_cleanup_missing");
+
+    let isar = open_instance("test_compact_failure_cleanup", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(true, false).unwrap();
+    let mut ob = col.new_object_builder(None);
+    ob.write_long(col.properties[0].offset, 5);
+    col.put(&mut txn, Some(1), ob.finish()).unwrap();
+    txn.commit().unwrap();
+    assert!(isar.close());
+
+    let result = open_compacted("test_compact_failure_cleanup", &dir, Some(&missing_dir));
+    assert!(result.is_err());
+    assert!(!missing_dir.exists());
+    assert!(!dir
+        .join("test_compact_failure_cleanup.isar.compact")
+        .exists());
+
+    let isar = open_instance("test_compact_failure_cleanup", value_schema());
+    let col = &isar.collections[0];
+    let mut txn = isar.begin_txn(false, false).unwrap();
+    assert_eq!(count_value(col, &mut txn, &col.properties[0], 5), 1);
+    txn.abort();
+    isar.close_and_delete();
+}
@@ -0,0 +1,79 @@
+use crate::txn::CIsarTxn;
+use crate::UintSend;
+use isar_core::instance::IsarInstance;
+
+pub struct BlobPtr(*mut u8);
+unsafe impl Send for BlobPtr {}
+
+pub struct BlobConstPtr(*const u8);
+unsafe impl Send for BlobConstPtr {}
+
+pub struct BlobOutPtr(*mut *const u8);
+unsafe impl Send for BlobOutPtr {}
+
+unsafe fn read_hash(hash: *const u8) -> u128 {
+    let mut bytes = [0; 16];
+    std::ptr::copy_nonoverlapping(hash, bytes.as_mut_ptr(), 16);
+    u128::from_be_bytes(bytes)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_blob_put(
+    instance: &'static IsarInstance,
+    txn: &mut CIsarTxn,
+    bytes: *const u8,
+    length: u32,
+    hash: *mut u8,
+) -> i64 {
+    let bytes = BlobConstPtr(bytes);
+    let hash = BlobPtr(hash);
+    isar_try_txn!(txn, move |txn| {
+        let bytes = bytes;
+        let hash = hash;
+        let bytes = std::slice::from_raw_parts(bytes.0, length as usize);
+        let result = instance.blobs.put(txn, bytes)?;
+        let result = result.to_be_bytes();
+        std::ptr::copy_nonoverlapping(result.as_ptr(), hash.0, 16);
+        Ok(())
+    })
+}
+
+/// The returned bytes are only valid until the transaction is finished.
+#[no_mangle]
+pub unsafe extern "C" fn isar_blob_get(
+    instance: &'static IsarInstance,
+    txn: &mut CIsarTxn,
+    hash: *const u8,
+    bytes: *mut *const u8,
+    length: &'static mut u32,
+) -> i64 {
+    let hash = read_hash(hash);
+    let bytes = BlobOutPtr(bytes);
+    let length = UintSend(length);
+    isar_try_txn!(txn, move |txn| {
+        let bytes = bytes;
+        let length = length;
+        if let Some(blob) = instance.blobs.get(txn, hash)? {
+            bytes.0.write(blob.as_ptr());
+            *length.0 = blob.len() as u32;
+        } else {
+            bytes.0.write(std::ptr::null());
+            *length.0 = 0;
+        }
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_blob_collect_garbage(
+    instance: &'static IsarInstance,
+    txn: &mut CIsarTxn,
+    count: &'static mut u32,
+) -> i64 {
+    let count = UintSend(count);
+    isar_try_txn!(txn, move |txn| {
+        let count = count;
+        *count.0 = instance.blobs.collect_garbage(txn)? as u32;
+        Ok(())
+    })
+}
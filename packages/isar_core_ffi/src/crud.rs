@@ -1,12 +1,19 @@
 use crate::c_object_set::{CObject, CObjectSet};
+use crate::filter::get_property;
+use crate::query::{JsonBytes, JsonLen};
 use crate::txn::CIsarTxn;
-use crate::{from_c_str, BoolSend, UintSend};
+use crate::{from_c_str, write_json, BoolSend, UintSend};
 use intmap::IntMap;
 use isar_core::collection::IsarCollection;
-use isar_core::error::IsarError;
+use isar_core::error::{illegal_arg, IsarError, Result};
 use isar_core::index::index_key::IndexKey;
+use isar_core::object::columns::ColumnData;
+use isar_core::object::data_type::DataType;
+use isar_core::object::isar_object::IsarObject;
+use isar_core::object::property::Property;
 use serde_json::Value;
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
+use std::slice;
 
 #[no_mangle]
 pub unsafe extern "C" fn isar_get(
@@ -22,6 +29,48 @@ pub unsafe extern "C" fn isar_get(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_exists_all(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    ids: *const i64,
+    ids_length: u32,
+    exists: *mut bool,
+) -> i64 {
+    let ids = std::slice::from_raw_parts(ids, ids_length as usize);
+    let exists = std::slice::from_raw_parts_mut(exists, ids_length as usize);
+    isar_try_txn!(txn, move |txn| {
+        let result = collection.exists_many(txn, ids)?;
+        exists.copy_from_slice(&result);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_get_json(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    id: i64,
+    id_name: *const c_char,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) -> i64 {
+    let id_name = from_c_str(id_name).unwrap();
+    let json = JsonBytes(json_bytes);
+    let json_length = JsonLen(json_length);
+    isar_try_txn!(txn, move |txn| {
+        let json = json;
+        let json_length = json_length;
+        if let Some(value) = collection.get_json(txn, id, id_name, true)? {
+            write_json(&value, json.0, json_length.0);
+        } else {
+            json_length.0.write(0);
+            json.0.write(std::ptr::null_mut());
+        }
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_get_by_index(
     collection: &'static IsarCollection,
@@ -101,6 +150,28 @@ pub unsafe extern "C" fn isar_put(
     })
 }
 
+/// Writes `i64::MIN` to `new_id` if the object does not exist.
+#[no_mangle]
+pub unsafe extern "C" fn isar_copy_object(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    id: i64,
+    target: &'static IsarCollection,
+    target_id: i64,
+    new_id: &'static mut i64,
+) -> i64 {
+    isar_try_txn!(txn, move |txn| {
+        let target_id = if target_id != i64::MIN {
+            Some(target_id)
+        } else {
+            None
+        };
+        let id = collection.copy_object(txn, id, target, target_id)?;
+        *new_id = id.unwrap_or(i64::MIN);
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_put_by_index(
     collection: &'static mut IsarCollection,
@@ -151,6 +222,111 @@ pub unsafe extern "C" fn isar_put_all_by_index(
     })
 }
 
+#[repr(C)]
+pub struct CColumn {
+    property_id: u64,
+    values: *const c_void,
+}
+
+pub struct CColumnsSend(*const CColumn, *const i64, *mut i64);
+unsafe impl Send for CColumnsSend {}
+
+enum ColumnValues<'a> {
+    Bool(Vec<Option<bool>>),
+    Strings(Vec<Option<&'a str>>),
+    Data(ColumnData<'a>),
+}
+
+unsafe fn read_column<'a>(
+    property: &Property,
+    values: *const c_void,
+    rows: usize,
+) -> Result<ColumnValues<'a>> {
+    let values = match property.data_type {
+        DataType::Bool => {
+            let bytes = slice::from_raw_parts(values as *const u8, rows);
+            let bools = bytes
+                .iter()
+                .map(|b| match *b {
+                    IsarObject::FALSE_BOOL => Some(false),
+                    IsarObject::TRUE_BOOL => Some(true),
+                    _ => None,
+                })
+                .collect();
+            ColumnValues::Bool(bools)
+        }
+        DataType::String => {
+            let ptrs = slice::from_raw_parts(values as *const *const c_char, rows);
+            let mut strings = Vec::with_capacity(rows);
+            for ptr in ptrs {
+                strings.push(from_c_str(*ptr)?);
+            }
+            ColumnValues::Strings(strings)
+        }
+        DataType::Byte => {
+            ColumnValues::Data(ColumnData::Byte(slice::from_raw_parts(values as _, rows)))
+        }
+        DataType::Int => {
+            ColumnValues::Data(ColumnData::Int(slice::from_raw_parts(values as _, rows)))
+        }
+        DataType::Float => {
+            ColumnValues::Data(ColumnData::Float(slice::from_raw_parts(values as _, rows)))
+        }
+        DataType::Long => {
+            ColumnValues::Data(ColumnData::Long(slice::from_raw_parts(values as _, rows)))
+        }
+        DataType::Double => {
+            ColumnValues::Data(ColumnData::Double(slice::from_raw_parts(values as _, rows)))
+        }
+        _ => return illegal_arg("Only primitive and String properties can be used as columns."),
+    };
+    Ok(values)
+}
+
+/// Bool columns use the Isar encoding and String columns are arrays of nullable C strings.
+/// `ids` may be null to use auto increment ids. The resulting ids are written to `result_ids`.
+#[no_mangle]
+pub unsafe extern "C" fn isar_put_columns(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    ids: *const i64,
+    row_count: u32,
+    columns: *const CColumn,
+    column_count: u32,
+    result_ids: *mut i64,
+) -> i64 {
+    let ptrs = CColumnsSend(columns, ids, result_ids);
+    isar_try_txn!(txn, move |txn| {
+        let ptrs = ptrs;
+        let rows = row_count as usize;
+        let c_columns = slice::from_raw_parts(ptrs.0, column_count as usize);
+        let mut values = vec![];
+        for c_column in c_columns {
+            let property = get_property(collection, 0, c_column.property_id)?;
+            values.push((property, read_column(property, c_column.values, rows)?));
+        }
+        let columns = values
+            .iter()
+            .map(|(property, values)| {
+                let data = match values {
+                    ColumnValues::Bool(bools) => ColumnData::Bool(bools),
+                    ColumnValues::Strings(strings) => ColumnData::String(strings),
+                    ColumnValues::Data(data) => *data,
+                };
+                (*property, data)
+            })
+            .collect();
+        let ids = if !ptrs.1.is_null() {
+            Some(slice::from_raw_parts(ptrs.1, rows))
+        } else {
+            None
+        };
+        let new_ids = collection.put_columns(txn, ids, columns)?;
+        std::ptr::copy_nonoverlapping(new_ids.as_ptr(), ptrs.2, new_ids.len());
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_delete(
     collection: &'static IsarCollection,
@@ -203,6 +379,22 @@ pub unsafe extern "C" fn isar_delete_all(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_purge(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    ids: *const i64,
+    ids_length: u32,
+    count: &'static mut u32,
+) -> i64 {
+    let ids = std::slice::from_raw_parts(ids, ids_length as usize);
+    let count = UintSend(count);
+    isar_try_txn!(txn, move |txn| {
+        *count.0 = collection.purge(txn, ids)?;
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_delete_all_by_index(
     collection: &'static IsarCollection,
@@ -251,6 +443,30 @@ pub unsafe extern "C" fn isar_json_import(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_json_import_partial(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    id_name: *const c_char,
+    json_bytes: *const u8,
+    json_length: u32,
+    report_bytes: *mut *mut u8,
+    report_length: *mut u32,
+) -> i64 {
+    let id_name = from_c_str(id_name).unwrap();
+    let bytes = std::slice::from_raw_parts(json_bytes, json_length as usize);
+    let report_bytes = JsonBytes(report_bytes);
+    let report_length = JsonLen(report_length);
+    isar_try_txn!(txn, move |txn| {
+        let report_bytes = report_bytes;
+        let report_length = report_length;
+        let json: Value = serde_json::from_slice(bytes).map_err(|_| IsarError::InvalidJson {})?;
+        let report = collection.import_json_partial(txn, id_name, json)?;
+        write_json(&report, report_bytes.0, report_length.0);
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_count(
     collection: &'static IsarCollection,
@@ -263,6 +479,22 @@ pub unsafe extern "C" fn isar_count(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_get_id_range(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    min_id: &'static mut i64,
+    max_id: &'static mut i64,
+    next_id: &'static mut i64,
+) -> i64 {
+    isar_try_txn!(txn, move |txn| {
+        *min_id = collection.min_id(txn)?.unwrap_or(i64::MIN);
+        *max_id = collection.max_id(txn)?.unwrap_or(i64::MIN);
+        *next_id = collection.next_auto_increment(txn).unwrap_or(i64::MIN);
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_get_size(
     collection: &'static IsarCollection,
@@ -277,6 +509,52 @@ pub unsafe extern "C" fn isar_get_size(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_get_write_stats(
+    collection: &IsarCollection,
+    puts: &mut i64,
+    deletes: &mut i64,
+    bytes: &mut i64,
+) {
+    let stats = collection.get_write_stats();
+    *puts = stats.puts as i64;
+    *deletes = stats.deletes as i64;
+    *bytes = stats.bytes as i64;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_get_index_advice(
+    collection: &IsarCollection,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) {
+    write_json(&collection.get_index_advice(), json_bytes, json_length);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_reset_index_advice(collection: &IsarCollection) {
+    collection.reset_index_advice();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_check_index_consistency(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    sample_every: u32,
+    report_bytes: *mut *mut u8,
+    report_length: *mut u32,
+) -> i64 {
+    let report_bytes = JsonBytes(report_bytes);
+    let report_length = JsonLen(report_length);
+    isar_try_txn!(txn, move |txn| {
+        let report_bytes = report_bytes;
+        let report_length = report_length;
+        let report = collection.check_index_consistency(txn, sample_every as u64)?;
+        write_json(&report, report_bytes.0, report_length.0);
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_verify(
     collection: &'static IsarCollection,
@@ -1,4 +1,6 @@
 use once_cell::sync::OnceCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
 
 static DART_POST_C_OBJECT: OnceCell<DartPostCObjectFnType> = OnceCell::new();
 
@@ -7,6 +9,13 @@ pub fn dart_post_int(port: DartPort, value: i64) {
     dart_post(port, &mut DartCObject::new(value));
 }
 
+/// Dart copies the string before this returns.
+pub fn dart_post_string(port: DartPort, value: &str) {
+    let dart_post = DART_POST_C_OBJECT.get().unwrap();
+    let value = CString::new(value).unwrap();
+    dart_post(port, &mut DartCObject::new_string(&value));
+}
+
 pub type DartPort = i64;
 
 pub type DartPostCObjectFnType = extern "C" fn(port_id: DartPort, message: *mut DartCObject) -> i8;
@@ -24,11 +33,21 @@ impl DartCObject {
             value: DartCObjectValue { value },
         }
     }
+
+    fn new_string(value: &CString) -> Self {
+        DartCObject {
+            ty: 5,
+            value: DartCObjectValue {
+                as_string: value.as_ptr(),
+            },
+        }
+    }
 }
 
 #[repr(C)]
 union DartCObjectValue {
     pub value: i64,
+    pub as_string: *const c_char,
     _union_align: [u64; 5usize],
 }
 
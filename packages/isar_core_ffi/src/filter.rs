@@ -5,6 +5,7 @@ use isar_core::error::Result;
 use isar_core::object::data_type::DataType;
 use isar_core::object::property::Property;
 use isar_core::query::filter::*;
+use isar_core::query::string_normalizer::StringNormalizer;
 use std::os::raw::c_char;
 use std::slice;
 
@@ -46,6 +47,38 @@ pub unsafe extern "C" fn isar_filter_not(filter: *mut *const Filter, condition:
     filter.write(ptr);
 }
 
+/// Stores `condition` as a fragment that can be embedded into multiple queries.
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_fragment_create(
+    fragment: *mut *const FilterFragment,
+    name: *const c_char,
+    condition: *mut Filter,
+) -> i64 {
+    isar_try! {
+        let condition = *Box::from_raw(condition);
+        let name = match from_c_str(name)? {
+            Some(name) => name,
+            None => return illegal_arg("Fragment name must not be null."),
+        };
+        let ptr = Box::into_raw(Box::new(FilterFragment::new(name, condition)));
+        fragment.write(ptr);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_fragment(
+    filter: *mut *const Filter,
+    fragment: &FilterFragment,
+) {
+    let ptr = Box::into_raw(Box::new(fragment.filter()));
+    filter.write(ptr);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_fragment_free(fragment: *mut FilterFragment) {
+    drop(Box::from_raw(fragment));
+}
+
 pub fn get_property(
     collection: &IsarCollection,
     embedded_col_id: u64,
@@ -63,7 +96,10 @@ pub fn get_property(
     if let Some(property) = properties.get(property_id as usize) {
         Ok(property)
     } else {
-        illegal_arg("Property does not exist.")
+        illegal_arg(&format!(
+            "Property {} does not exist in collection {}.",
+            property_id, collection.name
+        ))
     }
 }
 
@@ -309,6 +345,27 @@ unsafe fn get_upper_str(upper: Option<Vec<u8>>, include_upper: bool) -> Option<O
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_flags(
+    collection: &IsarCollection,
+    filter: *mut *const Filter,
+    mask: i64,
+    mode: u8,
+    embedded_col_id: u64,
+    property_id: u64,
+) -> i64 {
+    isar_try! {
+        let property = get_property(collection, embedded_col_id, property_id)?;
+        let query_filter = match mode {
+            0 => Filter::has_flag(property, mask)?,
+            1 => Filter::has_any_flag(property, mask)?,
+            _ => Filter::has_all_flags(property, mask)?,
+        };
+        let ptr = Box::into_raw(Box::new(query_filter));
+        filter.write(ptr);
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_filter_string(
     collection: &IsarCollection,
@@ -354,7 +411,10 @@ macro_rules! filter_string_ffi {
         ) -> i64 {
             isar_try! {
                 let property = get_property(collection, embedded_col_id, property_id)?;
-                let str = from_c_str(value)?.unwrap();
+                let str = match from_c_str(value)? {
+                    Some(str) => str,
+                    None => return illegal_arg("Value must not be null."),
+                };
                 let query_filter = isar_core::query::filter::Filter::$filter_name(property, str, case_sensitive)?;
                 let ptr = Box::into_raw(Box::new(query_filter));
                 filter.write(ptr);
@@ -367,3 +427,30 @@ filter_string_ffi!(string_starts_with, isar_filter_string_starts_with);
 filter_string_ffi!(string_ends_with, isar_filter_string_ends_with);
 filter_string_ffi!(string_contains, isar_filter_string_contains);
 filter_string_ffi!(string_matches, isar_filter_string_matches);
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_filter_string_equal(
+    collection: &IsarCollection,
+    filter: *mut *const Filter,
+    value: *const c_char,
+    case_sensitive: bool,
+    trim: bool,
+    ignore_whitespace: bool,
+    ignore_diacritics: bool,
+    embedded_col_id: u64,
+    property_id: u64,
+) -> i64 {
+    isar_try! {
+        let property = get_property(collection, embedded_col_id, property_id)?;
+        // a null value matches null like `isar_filter_string` with null bounds
+        let query_filter = if let Some(str) = from_c_str(value)? {
+            let normalizer =
+                StringNormalizer::new(case_sensitive, trim, ignore_whitespace, ignore_diacritics);
+            Filter::string_equal(property, str, normalizer)?
+        } else {
+            Filter::string(property, None, None, case_sensitive)?
+        };
+        let ptr = Box::into_raw(Box::new(query_filter));
+        filter.write(ptr);
+    }
+}
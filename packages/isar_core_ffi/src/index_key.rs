@@ -61,6 +61,16 @@ pub unsafe extern "C" fn isar_key_add_string(
     key.add_string(value, case_sensitive)
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_key_add_folded_string(
+    key: &mut IndexKey,
+    value: *const c_char,
+    case_sensitive: bool,
+) {
+    let value = from_c_str(value).unwrap();
+    key.add_folded_string(value, case_sensitive)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_key_add_string_hash(
     key: &mut IndexKey,
@@ -72,6 +82,16 @@ pub unsafe extern "C" fn isar_key_add_string_hash(
     key.add_hash(hash);
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_key_add_folded_string_hash(
+    key: &mut IndexKey,
+    value: *const c_char,
+    case_sensitive: bool,
+) {
+    let value = from_c_str(value).unwrap();
+    key.add_folded_string_hash(value, case_sensitive)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_key_add_string_list_hash(
     key: &mut IndexKey,
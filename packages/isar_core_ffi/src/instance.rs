@@ -1,17 +1,22 @@
 use crate::app_dir::get_app_dir;
-use crate::dart::{dart_post_int, DartPort};
+use crate::dart::{dart_post_int, dart_post_string, DartPort};
 use crate::error::DartErrCode;
 use crate::from_c_str;
+use crate::query::{JsonBytes, JsonLen};
 use crate::txn::run_async;
 use crate::txn::CIsarTxn;
-use crate::CharsSend;
+use crate::{write_json, CharsSend, UintSend};
+use isar_core::archive::{self, Archive};
+use isar_core::clock::SystemClock;
 use isar_core::collection::IsarCollection;
-use isar_core::error::{illegal_arg, Result};
-use isar_core::instance::{CompactCondition, IsarInstance};
+use isar_core::delta;
+use isar_core::error::{illegal_arg, IsarError, Result};
+use isar_core::instance::{CompactCondition, IsarInstance, MigrationListener, RetryPolicy};
 use isar_core::schema::Schema;
 use std::ffi::CString;
 use std::os::raw::c_char;
 use std::sync::Arc;
+use std::time::Duration;
 
 include!(concat!(env!("OUT_DIR"), "/version.rs"));
 
@@ -31,10 +36,13 @@ pub unsafe extern "C" fn isar_instance_create(
     path: *const c_char,
     schema_json: *const c_char,
     max_size_mib: i64,
+    max_readers: u32,
     relaxed_durability: bool,
     compact_min_file_size: u32,
     compact_min_bytes: u32,
     compact_min_ratio: f64,
+    compact_temp_dir: *const c_char,
+    migration_port: DartPort,
 ) -> i64 {
     let open = || -> Result<()> {
         let name = from_c_str(name).unwrap().unwrap();
@@ -49,16 +57,32 @@ pub unsafe extern "C" fn isar_instance_create(
                 min_file_size: compact_min_file_size as u64,
                 min_bytes: compact_min_bytes as u64,
                 min_ratio: compact_min_ratio,
+                temp_dir: from_c_str(compact_temp_dir)?.map(|dir| dir.to_string()),
             })
         };
 
+        // posts a JSON record to `migration_port` after each collection migration
+        let migration_listener = if migration_port != 0 {
+            let instance_name = name.to_string();
+            let listener: MigrationListener = Box::new(move |record| {
+                let mut json = serde_json::to_value(record).unwrap();
+                json["instance"] = instance_name.as_str().into();
+                dart_post_string(migration_port, &json.to_string());
+            });
+            Some(listener)
+        } else {
+            None
+        };
+
         let instance = IsarInstance::open(
             name,
             path,
             schema,
             max_size_mib as usize,
+            max_readers,
             relaxed_durability,
             compact_condition,
+            migration_listener,
         )?;
         isar.write(Arc::into_raw(instance));
         Ok(())
@@ -74,31 +98,39 @@ pub unsafe extern "C" fn isar_instance_create_async(
     path: *const c_char,
     schema_json: *const c_char,
     max_size_mib: i64,
+    max_readers: u32,
     relaxed_durability: bool,
     compact_min_file_size: u32,
     compact_min_bytes: u32,
     compact_min_ratio: f64,
+    compact_temp_dir: *const c_char,
+    migration_port: DartPort,
     port: DartPort,
 ) {
     let isar = IsarInstanceSend(isar);
     let name = CharsSend(name);
     let path = CharsSend(path);
     let schema_json = CharsSend(schema_json);
+    let compact_temp_dir = CharsSend(compact_temp_dir);
     run_async(move || {
         let isar = isar;
         let name = name;
         let path = path;
         let schema_json = schema_json;
+        let compact_temp_dir = compact_temp_dir;
         let result = isar_instance_create(
             isar.0,
             name.0,
             path.0,
             schema_json.0,
             max_size_mib,
+            max_readers,
             relaxed_durability,
             compact_min_file_size,
             compact_min_bytes,
             compact_min_ratio,
+            compact_temp_dir.0,
+            migration_port,
         );
         dart_post_int(port, result);
     });
@@ -166,6 +198,170 @@ pub unsafe extern "C" fn isar_instance_copy_to_file(
     });
 }
 
+/// Writes all collections to a JSON file from a read snapshot in the background.
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_export_json_to_file(
+    instance: &'static IsarInstance,
+    path: *const c_char,
+    id_name: *const c_char,
+    port: DartPort,
+) {
+    let path = CharsSend(path);
+    let id_name = CharsSend(id_name);
+    run_async(move || {
+        let path = path;
+        let id_name = id_name;
+        let path = from_c_str(path.0).unwrap().unwrap();
+        let id_name = from_c_str(id_name.0).unwrap();
+        let result = instance
+            .run_txn(false, true, |txn| instance.export_json(txn, id_name, true))
+            .and_then(|json| {
+                let bytes = serde_json::to_vec(&json).unwrap();
+                std::fs::write(path, bytes).map_err(|_| IsarError::PathError {})
+            });
+        dart_post_int(port, result.into_dart_result_code());
+    });
+}
+
+/// Moves the objects of a collection to an archive file in the background.
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_detach_to_archive(
+    instance: &'static IsarInstance,
+    collection: &'static IsarCollection,
+    path: *const c_char,
+    port: DartPort,
+) {
+    let path = CharsSend(path);
+    run_async(move || {
+        let path = path;
+        let path = from_c_str(path.0).unwrap().unwrap();
+        let result = archive::detach_to_archive(instance, collection, path).map(|_| ());
+        dart_post_int(port, result.into_dart_result_code());
+    });
+}
+
+/// Puts the objects of an archive file back into its collection in the background.
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_attach_archive(
+    instance: &'static IsarInstance,
+    collection: &'static IsarCollection,
+    path: *const c_char,
+    port: DartPort,
+) {
+    let path = CharsSend(path);
+    run_async(move || {
+        let path = path;
+        let path = from_c_str(path.0).unwrap().unwrap();
+        let result = Archive::open(path)
+            .and_then(|archive| archive::attach_archive(instance, collection, &archive))
+            .map(|_| ());
+        dart_post_int(port, result.into_dart_result_code());
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_set_read_only(instance: &IsarInstance, read_only: bool) {
+    instance.set_read_only(read_only);
+}
+
+/// Retries operations that fail with a transient error. Transactions started from Dart only
+/// retry starting the transaction. Passing `0` attempts disables retries.
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_set_retry_policy(
+    instance: &IsarInstance,
+    max_attempts: u32,
+    initial_backoff_ms: u32,
+    max_backoff_ms: u32,
+) {
+    let retry_policy = if max_attempts > 0 {
+        Some(RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(initial_backoff_ms as u64),
+            max_backoff: Duration::from_millis(max_backoff_ms as u64),
+        })
+    } else {
+        None
+    };
+    instance.set_retry_policy(retry_policy);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_get_retry_count(instance: &IsarInstance) -> u64 {
+    instance.get_retry_count()
+}
+
+/// Shifts timestamps written by the instance by `offset_ms` to correct a skewed device clock.
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_set_clock_offset(instance: &IsarInstance, offset_ms: i64) {
+    instance.set_clock(Arc::new(SystemClock::new(offset_ms)));
+}
+
+/// Expires read transactions that are open longer than `max_age_ms` and posts their age in
+/// milliseconds to `port`. Passing `0` stops the watchdog.
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_set_txn_watchdog(
+    instance: &IsarInstance,
+    max_age_ms: u32,
+    port: DartPort,
+) {
+    if max_age_ms == 0 {
+        instance.stop_txn_watchdog();
+    } else {
+        instance.start_txn_watchdog(
+            Duration::from_millis(max_age_ms as u64),
+            Box::new(move |age| {
+                dart_post_int(port, age.as_millis() as i64);
+            }),
+        );
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_check_readers(
+    instance: &'static IsarInstance,
+    count: &mut u32,
+) -> i64 {
+    isar_try! {
+        *count = instance.check_readers()?;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_get_write_pressure(
+    instance: &'static IsarInstance,
+    pending_write_txns: &mut u32,
+    unsynced_bytes: &mut i64,
+    used_bytes: &mut i64,
+    max_bytes: &mut i64,
+) -> i64 {
+    isar_try! {
+        let pressure = instance.get_write_pressure()?;
+        *pending_write_txns = pressure.pending_write_txns;
+        *unsynced_bytes = pressure.unsynced_bytes as i64;
+        *used_bytes = pressure.used_bytes as i64;
+        *max_bytes = pressure.max_bytes as i64;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_perform_maintenance(
+    instance: &'static IsarInstance,
+    budget_ms: u32,
+    completed: &mut bool,
+) -> i64 {
+    isar_try! {
+        let report = instance.perform_maintenance(Duration::from_millis(budget_ms as u64))?;
+        *completed = report.completed;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_flush(instance: &'static IsarInstance) -> i64 {
+    isar_try! {
+        instance.flush()?;
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_instance_verify(
     instance: &'static IsarInstance,
@@ -192,3 +388,52 @@ pub unsafe extern "C" fn isar_get_offsets(
     let property = properties.iter().max_by_key(|p| p.offset);
     property.map_or(2, |p| p.offset + p.data_type.get_static_size()) as u32
 }
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_get_fragmentation_report(
+    instance: &'static IsarInstance,
+    txn: &mut CIsarTxn,
+    include_collections: bool,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) -> i64 {
+    let json = JsonBytes(json_bytes);
+    let json_length = JsonLen(json_length);
+    isar_try_txn!(txn, move |txn| {
+        let json = json;
+        let json_length = json_length;
+        let report = instance.get_fragmentation_report(txn, include_collections)?;
+        write_json(&report, json.0, json_length.0);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_diff(
+    old: &'static IsarInstance,
+    new: &'static IsarInstance,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) -> i64 {
+    isar_try! {
+        let deltas = delta::diff(old, new)?;
+        write_json(&deltas, json_bytes, json_length);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_instance_sweep_orphans(
+    instance: &'static IsarInstance,
+    txn: &mut CIsarTxn,
+    index_entries: &'static mut u32,
+    links: &'static mut u32,
+) -> i64 {
+    let index_entries = UintSend(index_entries);
+    let links = UintSend(links);
+    isar_try_txn!(txn, move |txn| {
+        let report = instance.sweep_orphans(txn)?;
+        *index_entries.0 = report.index_entries as u32;
+        *links.0 = report.links as u32;
+        Ok(())
+    })
+}
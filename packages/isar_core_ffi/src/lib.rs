@@ -1,6 +1,8 @@
 #![allow(clippy::missing_safety_doc)]
 
 use isar_core::error::{illegal_arg, Result};
+use isar_core::object::row_format::ROW_FORMAT_VERSION;
+use serde::Serialize;
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::mem;
@@ -11,6 +13,7 @@ use unicode_segmentation::UnicodeSegmentation;
 mod error;
 
 pub mod app_dir;
+pub mod blob;
 pub mod c_object_set;
 pub mod crud;
 mod dart;
@@ -34,6 +37,20 @@ pub unsafe fn from_c_str<'a>(str: *const c_char) -> Result<Option<&'a str>> {
     }
 }
 
+/// Serializes `value` into a buffer that is handed to the caller and freed with
+/// `isar_free_json`.
+pub(crate) unsafe fn write_json<T: Serialize>(
+    value: &T,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) {
+    let bytes = serde_json::to_vec(value).unwrap();
+    let mut bytes = bytes.into_boxed_slice();
+    json_length.write(bytes.len() as u32);
+    json_bytes.write(bytes.as_mut_ptr());
+    mem::forget(bytes);
+}
+
 pub struct UintSend(&'static mut u32);
 
 unsafe impl Send for UintSend {}
@@ -72,6 +89,11 @@ pub unsafe extern "C" fn isar_free_word_boundaries(boundaries: *mut u32, word_co
     Vec::from_raw_parts(boundaries, len, len);
 }
 
+#[no_mangle]
+pub extern "C" fn isar_row_format_version() -> u32 {
+    ROW_FORMAT_VERSION
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_free_string(string: *mut c_char) {
     let _ = CString::from_raw(string);
@@ -74,6 +74,39 @@ pub unsafe extern "C" fn isar_link_update_all(
     })
 }
 
+/// `ids` contains `link_count` pairs of source and target ids.
+#[no_mangle]
+pub unsafe extern "C" fn isar_link_many(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    link_id: u64,
+    ids: *const i64,
+    link_count: u32,
+    replace: bool,
+) -> i64 {
+    let ids = std::slice::from_raw_parts(ids, link_count as usize * 2);
+    let links = ids.iter().copied().tuples().collect_vec();
+    isar_try_txn!(txn, move |txn| -> Result<()> {
+        collection.link_many(txn, link_id, &links, replace)?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_link_unlink_all_many(
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    link_id: u64,
+    ids: *const i64,
+    ids_count: u32,
+) -> i64 {
+    let ids = std::slice::from_raw_parts(ids, ids_count as usize);
+    isar_try_txn!(txn, move |txn| -> Result<()> {
+        collection.unlink_all_many(txn, link_id, ids)?;
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_link_verify(
     collection: &'static IsarCollection,
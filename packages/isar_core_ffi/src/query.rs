@@ -1,13 +1,20 @@
 use super::c_object_set::{CObject, CObjectSet};
 use crate::filter::get_property;
 use crate::txn::CIsarTxn;
-use crate::{from_c_str, UintSend};
+use crate::{from_c_str, write_json, UintSend};
 use isar_core::collection::IsarCollection;
+use isar_core::error::IsarError;
 use isar_core::index::index_key::IndexKey;
+use isar_core::instance::IsarInstance;
 use isar_core::query::filter::Filter;
+use isar_core::query::query_ast::QueryAst;
 use isar_core::query::query_builder::QueryBuilder;
+use isar_core::query::query_parser::parse_query;
+use isar_core::query::sort_expression::{ArithmeticOp, SortExpression};
 use isar_core::query::{Query, Sort};
+use std::collections::VecDeque;
 use std::os::raw::c_char;
+use std::slice;
 
 #[no_mangle]
 pub extern "C" fn isar_qb_create(collection: &IsarCollection) -> *mut QueryBuilder {
@@ -88,6 +95,90 @@ pub unsafe extern "C" fn isar_qb_add_sort_by(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_qb_add_sort_by_case(
+    builder: &mut QueryBuilder,
+    property_id: u64,
+    asc: bool,
+    case_sensitive: bool,
+) -> i64 {
+    let sort = if asc {
+        Sort::Ascending
+    } else {
+        Sort::Descending
+    };
+    isar_try! {
+        let property = get_property(builder.collection, 0, property_id)?;
+        builder.add_sort_with_case(property, sort, case_sensitive)?;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_sort_expr_property(
+    collection: &IsarCollection,
+    property_id: u64,
+    expression: *mut *const SortExpression,
+) -> i64 {
+    isar_try! {
+        let property = get_property(collection, 0, property_id)?;
+        let property = SortExpression::property(property)?;
+        expression.write(Box::into_raw(Box::new(property)));
+    }
+}
+
+/// `op` is `0` for addition, `1` for subtraction and `2` for multiplication.
+#[no_mangle]
+pub unsafe extern "C" fn isar_sort_expr_arithmetic(
+    op: u8,
+    left: *mut SortExpression,
+    right: *mut SortExpression,
+    expression: *mut *const SortExpression,
+) -> i64 {
+    let left = *Box::from_raw(left);
+    let right = *Box::from_raw(right);
+    isar_try! {
+        let op = match op {
+            0 => ArithmeticOp::Add,
+            1 => ArithmeticOp::Sub,
+            _ => ArithmeticOp::Mul,
+        };
+        let arithmetic = SortExpression::arithmetic(op, left, right)?;
+        expression.write(Box::into_raw(Box::new(arithmetic)));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_sort_expr_coalesce(
+    expressions: *mut *mut SortExpression,
+    length: u32,
+    expression: *mut *const SortExpression,
+) -> i64 {
+    let expressions = slice::from_raw_parts(expressions, length as usize)
+        .iter()
+        .map(|e| *Box::from_raw(*e))
+        .collect();
+    isar_try! {
+        let coalesce = SortExpression::coalesce(expressions)?;
+        expression.write(Box::into_raw(Box::new(coalesce)));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_qb_add_sort_by_expression(
+    builder: &mut QueryBuilder,
+    expression: *mut SortExpression,
+    asc: bool,
+    case_sensitive: bool,
+) {
+    let expression = *Box::from_raw(expression);
+    let sort = if asc {
+        Sort::Ascending
+    } else {
+        Sort::Descending
+    };
+    builder.add_sort_expression(expression, sort, case_sensitive);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_qb_add_distinct_by(
     builder: &mut QueryBuilder,
@@ -122,11 +213,64 @@ pub unsafe extern "C" fn isar_qb_build(builder: *mut QueryBuilder) -> *mut Query
     Box::into_raw(Box::new(query))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_parse(
+    collection: &IsarCollection,
+    input: *const c_char,
+    query: *mut *const Query,
+) -> i64 {
+    isar_try! {
+        let input = from_c_str(input)?.unwrap_or_default();
+        let parsed = parse_query(collection, input)?;
+        query.write(Box::into_raw(Box::new(parsed)));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_to_ast(
+    instance: &IsarInstance,
+    collection: &IsarCollection,
+    query: &Query,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) -> i64 {
+    isar_try! {
+        let ast = query.to_ast(collection, &instance.collections)?;
+        write_json(&ast, json_bytes, json_length);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_from_ast(
+    instance: &IsarInstance,
+    collection: &IsarCollection,
+    json_bytes: *const u8,
+    json_length: u32,
+    query: *mut *const Query,
+) -> i64 {
+    isar_try! {
+        let bytes = slice::from_raw_parts(json_bytes, json_length as usize);
+        let ast: QueryAst = serde_json::from_slice(bytes).map_err(|_| IsarError::InvalidJson {})?;
+        let built = ast.build(collection, &instance.collections)?;
+        query.write(Box::into_raw(Box::new(built)));
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_q_free(query: *mut Query) {
     let _ = Box::from_raw(query);
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_seek(query: &Query, after_id: i64) -> *mut Query {
+    Box::into_raw(Box::new(query.with_seek(after_id)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_skip(query: &Query, count: u32) -> *mut Query {
+    Box::into_raw(Box::new(query.with_skip(count as usize)))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_q_find(
     query: &'static Query,
@@ -151,6 +295,88 @@ pub unsafe extern "C" fn isar_q_find(
     })
 }
 
+pub struct QueryChunks {
+    chunks: VecDeque<(Vec<u8>, u32)>,
+    current: Vec<u8>,
+}
+
+pub(crate) struct ChunksPtr(*mut *mut QueryChunks);
+unsafe impl Send for ChunksPtr {}
+
+/// Executes the query and keeps the results as chunks that can be fetched with
+/// [isar_q_chunks_next] after the transaction finished.
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_find_chunked(
+    query: &'static Query,
+    txn: &mut CIsarTxn,
+    max_chunk_size: u32,
+    chunks: *mut *mut QueryChunks,
+) -> i64 {
+    let chunks = ChunksPtr(chunks);
+    isar_try_txn!(txn, move |txn| {
+        let chunks = chunks;
+        let mut result = VecDeque::new();
+        query.find_chunks(txn, max_chunk_size as usize, |chunk, rows| {
+            result.push_back((chunk, rows));
+            true
+        })?;
+        let query_chunks = QueryChunks {
+            chunks: result,
+            current: vec![],
+        };
+        chunks.0.write(Box::into_raw(Box::new(query_chunks)));
+        Ok(())
+    })
+}
+
+/// Returns the next chunk or `false` if all chunks have been returned. The bytes stay valid
+/// until the next call.
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_chunks_next(
+    chunks: &mut QueryChunks,
+    bytes: *mut *const u8,
+    length: *mut u32,
+    rows: *mut u32,
+) -> bool {
+    if let Some((chunk, chunk_rows)) = chunks.chunks.pop_front() {
+        chunks.current = chunk;
+        bytes.write(chunks.current.as_ptr());
+        length.write(chunks.current.len() as u32);
+        rows.write(chunk_rows);
+        true
+    } else {
+        false
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_chunks_free(chunks: *mut QueryChunks) {
+    let _ = Box::from_raw(chunks);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_find_first(
+    query: &'static Query,
+    txn: &mut CIsarTxn,
+    object: &'static mut CObject,
+    last: bool,
+) -> i64 {
+    isar_try_txn!(txn, move |txn| {
+        let result = if last {
+            query.find_last(txn)?
+        } else {
+            query.find_first(txn)?
+        };
+        if let Some((id, obj)) = result {
+            object.set_id(id);
+            object.set_object(Some(obj));
+        } else {
+            object.set_object(None);
+        }
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_q_delete(
     query: &'static Query,
@@ -163,7 +389,7 @@ pub unsafe extern "C" fn isar_q_delete(
     let count = UintSend(count);
     isar_try_txn!(txn, move |txn| {
         let mut ids_to_delete = vec![];
-        query.find_while(txn, |id, _| {
+        query.find_ids_while(txn, |id| {
             ids_to_delete.push(id);
             ids_to_delete.len() <= limit
         })?;
@@ -175,10 +401,34 @@ pub unsafe extern "C" fn isar_q_delete(
     })
 }
 
-struct JsonBytes(*mut *mut u8);
+/// Deletes the first `count` results of the query and returns them as chunks like
+/// [isar_q_find_chunked].
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_pop(
+    query: &'static Query,
+    collection: &'static IsarCollection,
+    txn: &mut CIsarTxn,
+    count: u32,
+    max_chunk_size: u32,
+    chunks: *mut *mut QueryChunks,
+) -> i64 {
+    let chunks = ChunksPtr(chunks);
+    isar_try_txn!(txn, move |txn| {
+        let chunks = chunks;
+        let result = query.pop(txn, collection, count as usize, max_chunk_size as usize)?;
+        let query_chunks = QueryChunks {
+            chunks: result.into(),
+            current: vec![],
+        };
+        chunks.0.write(Box::into_raw(Box::new(query_chunks)));
+        Ok(())
+    })
+}
+
+pub(crate) struct JsonBytes(pub *mut *mut u8);
 unsafe impl Send for JsonBytes {}
 
-struct JsonLen(*mut u32);
+pub(crate) struct JsonLen(pub *mut u32);
 unsafe impl Send for JsonLen {}
 
 #[no_mangle]
@@ -206,6 +456,54 @@ pub unsafe extern "C" fn isar_q_export_json(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_explain(
+    query: &Query,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) -> i64 {
+    isar_try! {
+        write_json(&query.explain(), json_bytes, json_length);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_plan_signature(
+    query: &Query,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) -> i64 {
+    isar_try! {
+        write_json(&query.plan_signature(), json_bytes, json_length);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_check_plan(query: &Query, expected: *const c_char) -> i64 {
+    isar_try! {
+        let expected = from_c_str(expected)?.unwrap_or_default();
+        query.check_plan(expected)?;
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn isar_q_profile(
+    query: &'static Query,
+    txn: &mut CIsarTxn,
+    json_bytes: *mut *mut u8,
+    json_length: *mut u32,
+) -> i64 {
+    let json = JsonBytes(json_bytes);
+    let json_length = JsonLen(json_length);
+    isar_try_txn!(txn, move |txn| {
+        let json = json;
+        let json_length = json_length;
+        let profile = query.profile(txn)?;
+        write_json(&profile, json.0, json_length.0);
+        Ok(())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn isar_free_json(json_bytes: *mut u8, json_length: u32) {
     Vec::from_raw_parts(json_bytes, json_length as usize, json_length as usize);
@@ -45,6 +45,7 @@ const EMPTY_PROP: &Property = &Property {
     data_type: DataType::Bool,
     offset: 0,
     target_id: None,
+    case_sensitive: true,
 };
 
 fn aggregate(
@@ -71,7 +72,7 @@ fn aggregate(
 
     let property = property.unwrap_or(EMPTY_PROP);
 
-    query.find_while(txn, |_, obj| {
+    query.scan_while(txn, |_, obj| {
         match op {
             AggregationOp::Min | AggregationOp::Max => {
                 if obj.is_null(property.offset, property.data_type) {
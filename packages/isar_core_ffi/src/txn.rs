@@ -3,6 +3,7 @@ use crate::error::DartErrCode;
 use isar_core::error::{IsarError, Result};
 use isar_core::instance::IsarInstance;
 use isar_core::txn::IsarTxn;
+use isar_core::watch::NotifyMode;
 use once_cell::sync::Lazy;
 use std::borrow::BorrowMut;
 use std::sync::mpsc;
@@ -48,6 +49,19 @@ pub unsafe extern "C" fn isar_txn_finish(txn: *mut CIsarTxn, commit: bool) -> i6
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn isar_txn_set_notify_mode(txn: &mut CIsarTxn, mode: u8) -> i64 {
+    let mode = match mode {
+        1 => NotifyMode::Collapsed,
+        2 => NotifyMode::Silent,
+        _ => NotifyMode::Normal,
+    };
+    isar_try_txn!(txn, move |txn| {
+        txn.set_notify_mode(mode);
+        Ok(())
+    })
+}
+
 pub struct IsarTxnSend(IsarTxn<'static>);
 
 unsafe impl Send for IsarTxnSend {}